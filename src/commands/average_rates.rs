@@ -0,0 +1,104 @@
+use std::error::Error;
+
+use clap::Args;
+use serde::Deserialize;
+
+use crate::{
+    conversions::{
+        currency::{dispatch, CurrencyDispatch, CurrencyType},
+        period::PeriodKind,
+        period_exchange_rates::PeriodExchangeRates,
+        rounding::RoundingMode,
+        validation::RateChecks,
+    },
+    currency::CurrencyPair,
+    io::{
+        average_rates::write_period_rates,
+        utils::{rates_pair, read_exchange_rates},
+    },
+};
+
+/// Average daily exchange rates per month, quarter or year.
+#[derive(Args, Debug)]
+pub struct AverageRates {
+    /// The daily exchange rates file.
+    #[clap(short, long)]
+    exchange_rates: Box<str>,
+
+    /// The output CSV file.
+    #[clap(short, long)]
+    output: Box<str>,
+
+    /// The length of the periods to average over.
+    #[clap(long, value_enum)]
+    period: PeriodKind,
+
+    /// How the exchange rates file is quoted, e.g. EUR/USD. Only needed if the file does not
+    /// name its rate column after the pair.
+    #[clap(long)]
+    rates_pair: Option<CurrencyPair>,
+
+    /// Round the averages to this many decimal places. Without it, averages are written
+    /// exactly, or with 12 decimal places if they have no exact decimal representation.
+    #[clap(long)]
+    decimals: Option<u32>,
+
+    /// How to round the averages.
+    #[clap(long, value_enum, default_value_t)]
+    rounding: RoundingMode,
+}
+
+pub fn average_rates(args: &AverageRates) -> Result<(), Box<dyn Error>> {
+    let pair = rates_pair(&args.exchange_rates, args.rates_pair)?;
+    dispatch(pair.quote(), Quote { args, pair })?
+}
+
+struct Quote<'a> {
+    args: &'a AverageRates,
+    pair: CurrencyPair,
+}
+
+impl CurrencyDispatch for Quote<'_> {
+    type Output = Result<(), Box<dyn Error>>;
+
+    fn call<N>(self) -> Self::Output
+    where
+        N: CurrencyType + for<'de> Deserialize<'de> + 'static,
+    {
+        dispatch(
+            self.pair.base(),
+            Base::<N> {
+                args: self.args,
+                phantom: std::marker::PhantomData,
+            },
+        )?
+    }
+}
+
+struct Base<'a, N> {
+    args: &'a AverageRates,
+    phantom: std::marker::PhantomData<N>,
+}
+
+impl<N> CurrencyDispatch for Base<'_, N>
+where
+    N: CurrencyType + for<'de> Deserialize<'de> + 'static,
+{
+    type Output = Result<(), Box<dyn Error>>;
+
+    fn call<D>(self) -> Self::Output
+    where
+        D: CurrencyType + for<'de> Deserialize<'de> + 'static,
+    {
+        let args = self.args;
+        let daily = read_exchange_rates::<N, D>(
+            &args.exchange_rates,
+            args.rates_pair,
+            &RateChecks::default(),
+        )?;
+        let averages = PeriodExchangeRates::average_of(&daily, args.period)
+            .ok_or("Average rate out of range")?;
+
+        write_period_rates(&args.output, &averages, args.decimals, args.rounding)
+    }
+}
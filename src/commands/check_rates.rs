@@ -0,0 +1,52 @@
+use std::{error::Error, sync::Arc};
+
+use clap::Args;
+
+use crate::{
+    conversions::validation::RateChecks,
+    io::utils::{read_calendar, read_rate_rows},
+};
+
+/// Check a daily exchange rates file for duplicate dates, invalid rates, unsorted dates,
+/// sudden jumps and, given a calendar, business days without a rate.
+#[derive(Args, Debug)]
+pub struct CheckRates {
+    /// The daily exchange rates file.
+    #[clap(short, long)]
+    exchange_rates: Box<str>,
+
+    /// Report day-over-day changes of more than this many percent.
+    #[clap(long, default_value_t = 10.0)]
+    max_jump: f64,
+
+    /// Report the business days without a rate: TARGET2, NYSE, Fed, or a CSV file with the
+    /// dates and names of holidays. Join calendars with +, e.g. TARGET2+closures.csv.
+    #[clap(long)]
+    calendar: Option<Box<str>>,
+}
+
+pub fn check_rates(args: &CheckRates) -> Result<(), Box<dyn Error>> {
+    let rows = read_rate_rows(&args.exchange_rates)?;
+    let mut checks = RateChecks::new(Some(args.max_jump))?;
+    if let Some(calendar) = &args.calendar {
+        checks = checks.with_calendar(Arc::new(read_calendar(calendar)?));
+    }
+    let issues = checks.check(&rows);
+
+    for issue in &issues {
+        let severity = if issue.is_error() { "error" } else { "warning" };
+        println!("{}: {}", severity, issue);
+    }
+    let errors = issues.iter().filter(|issue| issue.is_error()).count();
+    println!(
+        "{} rates, {} errors, {} warnings",
+        rows.len(),
+        errors,
+        issues.len() - errors
+    );
+
+    if errors > 0 {
+        return Err(format!("{} has invalid rates", args.exchange_rates).into());
+    }
+    Ok(())
+}
@@ -1,32 +1,459 @@
-use std::error::Error;
+use std::{error::Error, path::PathBuf, sync::Arc};
 
-use clap::Args;
+use chrono::{Days, NaiveDate};
+use clap::{Args, ValueEnum};
+use serde::Deserialize;
 
 use crate::{
-    conversions::currency::{EUR, USD},
-    io::{convert_transactions, utils::read_exchange_rates},
+    conversions::{
+        bid_ask_rates::SidePolicy,
+        calendar::Settlement,
+        cross_exchange_rates::CrossExchangeRates,
+        currency::{dispatch, CurrencyDispatch, CurrencyType},
+        daily_exchange_rates::DailyExchangeRates,
+        fallback::{Fallback, FallbackPolicy},
+        rate_provider::RateProvider,
+        rate_series::RateSeries,
+        rate_source::{RateSource, RateTable},
+        rate_store::RateStore,
+        rounding::RoundingMode,
+        timezone::Zone,
+        validation::RateChecks,
+    },
+    currency::{Currency, CurrencyPair, Locale},
+    io::{
+        convert_transactions::{self, Columns},
+        providers::{CachedProvider, EcbProvider},
+        utils::{
+            rate_file_format, read_bid_ask_rates, read_bundesbank_rates, read_calendar,
+            read_exchange_rates, read_fed_h10_rates, read_period_rates, read_rate_store,
+            read_wide_rates, RateFileFormat,
+        },
+    },
 };
 
 /// Convert transactions to a different currency.
 #[derive(Args, Debug)]
 pub struct ConvertTransactions {
-    /// The input CSV file.
+    /// The input CSV file, with the columns date and amount, and optionally id, description
+    /// and category. Other columns are copied to the end of the output.
     #[clap(short, long)]
     input: Box<str>,
 
+    /// The input column with the amounts to convert.
+    #[clap(long, default_value = "amount")]
+    amount_column: String,
+
+    /// Copy every input column to the output unchanged and append the conversion to it.
+    /// Rows whose date or amount cannot be read are copied with an empty conversion.
+    #[clap(long)]
+    keep_columns: bool,
+
+    /// The locale of the input amounts, such as de-DE for 1.234,56. Without it, amounts
+    /// like 1,234 whose separator could be a decimal or a thousands separator are rejected.
+    #[clap(long)]
+    locale: Option<Locale>,
+
+    /// The time zone of input dates with a time but without an offset, such as
+    /// America/New_York or +01:00.
+    #[clap(long, default_value = "UTC")]
+    timezone: Zone,
+
+    /// The time zone whose calendar dates decide the rates of transactions with a time.
+    /// Defaults to --timezone.
+    #[clap(long)]
+    reporting_timezone: Option<Zone>,
+
     /// The output CSV file.
     #[clap(short, long)]
     output: Box<str>,
 
-    /// The exchange rates file.
-    #[clap(short, long)]
-    exchange_rates: Box<str>,
+    /// The exchange rates file: a CSV file, or an ECB zip archive or SDMX-ML file.
+    #[clap(short, long, required_unless_present = "provider")]
+    exchange_rates: Option<Box<str>>,
+
+    /// Fetch the rates for the dates of the transactions from a provider instead of reading
+    /// them from a file.
+    #[clap(long, value_enum, conflicts_with = "exchange_rates")]
+    provider: Option<Provider>,
+
+    /// The directory to cache rates fetched from the provider in.
+    #[clap(long, requires = "provider", default_value = ".rate-cache")]
+    cache_dir: PathBuf,
+
+    /// The currency of the input amounts.
+    #[clap(long, default_value = "EUR")]
+    from: Currency,
+
+    /// The currency to convert to.
+    #[clap(long, default_value = "USD")]
+    to: Currency,
+
+    /// How the exchange rates file is quoted, e.g. EUR/USD if it gives the price of one
+    /// EUR in USD. Only needed if the file does not name its rate column after the pair.
+    #[clap(long)]
+    rates_pair: Option<CurrencyPair>,
+
+    /// The currency that a file with one column per currency is quoted against, such as
+    /// EUR for the ECB's eurofxref-hist.csv.
+    #[clap(long, default_value = "EUR")]
+    rates_base: Currency,
+
+    /// How to round amounts to the minor units of their currency.
+    #[clap(long, value_enum, default_value_t)]
+    rounding: RoundingMode,
+
+    /// Which rate to use if the exchange rates file has bid and ask columns.
+    #[clap(long, value_enum, default_value_t)]
+    rate_side: SidePolicy,
+
+    /// Which rate to use for dates without one, such as weekends and holidays.
+    #[clap(long, value_enum, default_value_t)]
+    fallback: FallbackPolicy,
+
+    /// Warn about day-over-day changes of the rates of more than this many percent.
+    #[clap(long, default_value_t = 10.0)]
+    max_jump: f64,
+
+    /// How many days the rate used may be away from the transaction date: calendar days, or
+    /// business days of --calendar if given.
+    #[clap(long, default_value_t = 7)]
+    max_staleness: u32,
+
+    /// The business days of the rates: TARGET2, NYSE, Fed, or a CSV file with the dates and
+    /// names of holidays. Join calendars with +, e.g. TARGET2+closures.csv. Weekends and
+    /// holidays are then not counted as stale.
+    #[clap(long)]
+    calendar: Option<Box<str>>,
+
+    /// Use the rates of the settlement dates, this many business days of --calendar after
+    /// the transaction dates.
+    #[clap(long, requires = "calendar")]
+    settlement_days: Option<u32>,
+}
+
+/// A provider to fetch exchange rates from.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Provider {
+    /// The ECB reference rates from the ECB data API.
+    Ecb,
 }
 
 pub fn convert_transactions(args: &ConvertTransactions) -> Result<(), Box<dyn Error>> {
-    let rates = read_exchange_rates::<USD, EUR>(&args.exchange_rates)?;
+    dispatch(args.to, ToCurrency { args })?
+}
+
+struct ToCurrency<'a> {
+    args: &'a ConvertTransactions,
+}
+
+impl CurrencyDispatch for ToCurrency<'_> {
+    type Output = Result<(), Box<dyn Error>>;
+
+    fn call<N>(self) -> Self::Output
+    where
+        N: CurrencyType + for<'de> Deserialize<'de> + 'static,
+    {
+        dispatch(
+            self.args.from,
+            FromCurrency::<N> {
+                args: self.args,
+                phantom: std::marker::PhantomData,
+            },
+        )?
+    }
+}
+
+struct FromCurrency<'a, N> {
+    args: &'a ConvertTransactions,
+    phantom: std::marker::PhantomData<N>,
+}
+
+impl<N> CurrencyDispatch for FromCurrency<'_, N>
+where
+    N: CurrencyType + for<'de> Deserialize<'de> + 'static,
+{
+    type Output = Result<(), Box<dyn Error>>;
+
+    fn call<D>(self) -> Self::Output
+    where
+        D: CurrencyType + for<'de> Deserialize<'de> + 'static,
+    {
+        let args = self.args;
+        let calendar = args
+            .calendar
+            .as_deref()
+            .map(read_calendar)
+            .transpose()?
+            .map(Arc::new);
+        let mut fallback = Fallback::new(args.fallback, args.max_staleness);
+        if let Some(calendar) = &calendar {
+            fallback = fallback.with_calendar(calendar.clone());
+        }
+        let settlement = calendar
+            .zip(args.settlement_days)
+            .map(|(calendar, days)| Settlement::new(calendar, days));
+        let columns = Columns {
+            amount: args.amount_column.clone(),
+            keep: args.keep_columns,
+            locale: args.locale,
+            timezone: args.timezone,
+            reporting_timezone: args.reporting_timezone.unwrap_or(args.timezone),
+        };
+
+        let rates = match (&args.exchange_rates, args.provider) {
+            (Some(path), _) => file_rates::<N, D>(args, path, fallback)?,
+            (None, Some(provider)) => {
+                let Some(dates) = convert_transactions::rate_dates::<D>(
+                    &args.input,
+                    settlement.as_ref(),
+                    &columns,
+                )?
+                else {
+                    return Err(format!("{} has no transactions", args.input).into());
+                };
+                provider_rates::<N, D>(args, provider, dates, fallback)?
+            }
+            (None, None) => return Err("Pass --exchange-rates or --provider".into()),
+        };
+        let unconverted = convert_transactions::convert(
+            &args.input,
+            &args.output,
+            rates,
+            args.rounding,
+            args.rate_side,
+            settlement.as_ref(),
+            &columns,
+        )?;
+        if !unconverted.is_empty() {
+            let lines: Vec<String> = unconverted.iter().map(ToString::to_string).collect();
+            log::warn!(
+                "{} rows of {} could not be read and were copied without a conversion: lines {}",
+                unconverted.len(),
+                args.input,
+                lines.join(", ")
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// The rates for converting `D` to `N` from the exchange rates file at `path`.
+fn file_rates<N, D>(
+    args: &ConvertTransactions,
+    path: &str,
+    fallback: Fallback,
+) -> Result<Box<dyn RateSource<N, D>>, Box<dyn Error>>
+where
+    N: CurrencyType + for<'de> Deserialize<'de> + 'static,
+    D: CurrencyType + for<'de> Deserialize<'de> + 'static,
+{
+    let format = rate_file_format(path)?;
+    let checks = RateChecks::new(Some(args.max_jump))?;
+
+    if args.rate_side != SidePolicy::Mid && format != RateFileFormat::BidAsk {
+        log::warn!(
+            "{} has a single rate per date, using it as the mid rate",
+            path
+        );
+    }
+    if args.fallback != FallbackPolicy::Exact && format == RateFileFormat::Period {
+        log::warn!("{} has a rate for every period, ignoring --fallback", path);
+    }
+
+    Ok(match format {
+        RateFileFormat::Daily => Box::new(
+            read_exchange_rates::<N, D>(path, args.rates_pair, &checks)?.with_fallback(fallback),
+        ),
+        RateFileFormat::BidAsk => Box::new(
+            read_bid_ask_rates::<N, D>(path, args.rates_pair, &checks)?.with_fallback(fallback),
+        ),
+        RateFileFormat::Period => Box::new(read_period_rates::<N, D>(path, args.rates_pair)?),
+        RateFileFormat::Wide => wide_rates::<N, D>(args, path, fallback, &checks)?,
+        RateFileFormat::Store => store_rates::<N, D>(&read_rate_store(path)?, path, fallback)?,
+        RateFileFormat::Bundesbank => Box::new(
+            series_rates::<N, D>(path, read_bundesbank_rates(path)?)?.with_fallback(fallback),
+        ),
+        RateFileFormat::FedH10 => {
+            Box::new(series_rates::<N, D>(path, read_fed_h10_rates(path)?)?.with_fallback(fallback))
+        }
+    })
+}
+
+/// The rates for converting `D` to `N` from `provider` for the dates of the transactions,
+/// along with the rates around them that the fallback may need.
+fn provider_rates<N, D>(
+    args: &ConvertTransactions,
+    provider: Provider,
+    (first, last): (NaiveDate, NaiveDate),
+    fallback: Fallback,
+) -> Result<Box<dyn RateSource<N, D>>, Box<dyn Error>>
+where
+    N: CurrencyType + for<'de> Deserialize<'de> + 'static,
+    D: CurrencyType + for<'de> Deserialize<'de> + 'static,
+{
+    if args.rate_side != SidePolicy::Mid {
+        log::warn!("The provider has a single rate per date, using it as the mid rate");
+    }
+    let provider = match provider {
+        Provider::Ecb => CachedProvider::new(EcbProvider::default(), args.cache_dir.clone()),
+    };
+
+    // Enough for --max-staleness business days along with the weekends and holidays
+    // between them.
+    let margin = Days::new(u64::from(args.max_staleness) * 2 + 7);
+    let store = provider.fetch_store(
+        CurrencyPair::new(D::currency(), N::currency()),
+        first - margin,
+        last + margin,
+    )?;
+    store_rates::<N, D>(&store, provider.name(), fallback)
+}
+
+/// The rates for converting `D` to `N` from the first of the series of a statistics
+/// provider's file that is quoted in that pair, either way round.
+fn series_rates<N, D>(
+    path: &str,
+    series: Vec<RateSeries>,
+) -> Result<DailyExchangeRates<N, D>, Box<dyn Error>>
+where
+    N: CurrencyType + for<'de> Deserialize<'de>,
+    D: CurrencyType + for<'de> Deserialize<'de>,
+{
+    for series in &series {
+        if let Some(rates) = series.daily::<N, D>() {
+            log::info!("Using series {} ({})", series.id(), series.pair());
+            return Ok(rates);
+        }
+    }
+    Err(format!(
+        "{} has no series for converting {} to {}",
+        path,
+        D::currency(),
+        N::currency()
+    )
+    .into())
+}
+
+/// The rates for converting `D` to `N` from a rate store, as stored or inverted if it has
+/// them, or else as cross rates through a currency both are stored against. `name` names
+/// the store in errors.
+fn store_rates<N, D>(
+    store: &RateStore,
+    name: &str,
+    fallback: Fallback,
+) -> Result<Box<dyn RateSource<N, D>>, Box<dyn Error>>
+where
+    N: CurrencyType + for<'de> Deserialize<'de> + 'static,
+    D: CurrencyType + for<'de> Deserialize<'de> + 'static,
+{
+    if let Some(rates) = store.pair::<N, D>() {
+        return Ok(Box::new(rates.with_fallback(fallback)));
+    }
+
+    let through = store
+        .common_currency(N::currency(), D::currency())
+        .ok_or_else(|| {
+            format!(
+                "{} has no rates for converting {} to {}",
+                name,
+                D::currency(),
+                N::currency()
+            )
+        })?;
+    let missing =
+        |currency: Currency| format!("{} has no rates for {} against {}", name, currency, through);
+    dispatch(
+        through,
+        TableCross {
+            rates: store,
+            fallback,
+            missing: &missing,
+            phantom: std::marker::PhantomData::<(N, D)>,
+        },
+    )?
+}
+
+/// The rates for converting `D` to `N` from a file with one column per currency. Pairs
+/// that do not involve the base currency are cross rates, so their legs are written out.
+fn wide_rates<N, D>(
+    args: &ConvertTransactions,
+    path: &str,
+    fallback: Fallback,
+    checks: &RateChecks,
+) -> Result<Box<dyn RateSource<N, D>>, Box<dyn Error>>
+where
+    N: CurrencyType + for<'de> Deserialize<'de> + 'static,
+    D: CurrencyType + for<'de> Deserialize<'de> + 'static,
+{
+    let rates = read_wide_rates(path, args.rates_base, checks)?;
+    let missing = |currency: Currency| {
+        format!(
+            "{} has no rates for {} against {}",
+            path,
+            currency,
+            rates.base()
+        )
+    };
+
+    if N::currency() != rates.base() && D::currency() != rates.base() {
+        return dispatch(
+            rates.base(),
+            TableCross {
+                rates: &rates,
+                fallback,
+                missing: &missing,
+                phantom: std::marker::PhantomData::<(N, D)>,
+            },
+        )?;
+    }
+
+    let missing_currency = if N::currency() == rates.base() {
+        D::currency()
+    } else {
+        N::currency()
+    };
+    Ok(Box::new(
+        rates
+            .pair::<N, D>()
+            .ok_or_else(|| missing(missing_currency))?
+            .with_fallback(fallback),
+    ))
+}
+
+/// Cross rates through a currency that both `N` and `D` are quoted against in a table.
+struct TableCross<'a, T, N, D> {
+    rates: &'a T,
+    fallback: Fallback,
+    missing: &'a dyn Fn(Currency) -> String,
+    phantom: std::marker::PhantomData<(N, D)>,
+}
+
+impl<T, N, D> CurrencyDispatch for TableCross<'_, T, N, D>
+where
+    T: RateTable,
+    N: CurrencyType + for<'de> Deserialize<'de> + 'static,
+    D: CurrencyType + for<'de> Deserialize<'de> + 'static,
+{
+    type Output = Result<Box<dyn RateSource<N, D>>, Box<dyn Error>>;
 
-    convert_transactions::convert(&args.input, &args.output, rates)?;
+    fn call<B>(self) -> Self::Output
+    where
+        B: CurrencyType + for<'de> Deserialize<'de> + 'static,
+    {
+        let base_to_n = self
+            .rates
+            .pair::<N, B>()
+            .ok_or_else(|| (self.missing)(N::currency()))?;
+        let base_to_d = self
+            .rates
+            .pair::<D, B>()
+            .ok_or_else(|| (self.missing)(D::currency()))?;
 
-    Ok(())
+        Ok(Box::new(CrossExchangeRates::new(
+            base_to_n.with_fallback(self.fallback.clone()),
+            base_to_d.with_fallback(self.fallback),
+        )))
+    }
 }
@@ -1 +1,4 @@
+pub mod average_rates;
+pub mod check_rates;
 pub mod convert_transactions;
+pub mod rates;
@@ -0,0 +1,337 @@
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use chrono::NaiveDate;
+use clap::{Args, Subcommand};
+use serde::Deserialize;
+
+use crate::{
+    conversions::{
+        currency::{dispatch, CurrencyDispatch, CurrencyType},
+        rate_provider::RateProvider,
+        rate_source::RateTable,
+        rate_store::{MergeSummary, RateStore},
+        validation::RateChecks,
+    },
+    currency::{Currency, CurrencyPair},
+    io::{
+        providers::{CachedProvider, EcbProvider, FileProvider, ECB_API},
+        rates::{write_coverage, write_daily_rates, write_gaps, write_stored_rates},
+        utils::{read_calendar, read_rate_store, read_stored_rates, write_rate_store},
+    },
+};
+
+/// Manage the local rate store, a CSV file that collects rates from several sources.
+#[derive(Args, Debug)]
+pub struct Rates {
+    /// The rate store file. It can also be passed as the exchange rates file of
+    /// convert-transactions.
+    #[clap(long, default_value = "rate-store.csv")]
+    store: Box<str>,
+
+    #[command(subcommand)]
+    command: RatesCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum RatesCommand {
+    Import(Import),
+    Fetch(Fetch),
+    Show(Show),
+    Export(Export),
+    Coverage(Coverage),
+    Gaps(Gaps),
+}
+
+/// Add the rates of files to the store. Rates that conflict with stored ones are reported
+/// and left out.
+#[derive(Args, Debug)]
+struct Import {
+    /// The rates files, in any format with one rate per day.
+    #[clap(required = true)]
+    files: Vec<Box<str>>,
+
+    /// How files with a single pair are quoted, if they do not name their rate column after
+    /// the pair.
+    #[clap(long)]
+    rates_pair: Option<CurrencyPair>,
+
+    /// The currency that files with one column per currency are quoted against.
+    #[clap(long, default_value = "EUR")]
+    rates_base: Currency,
+
+    /// The name to store as the source of the rates. Defaults to the file name.
+    #[clap(long)]
+    source: Option<String>,
+
+    /// Report day-over-day changes of more than this many percent.
+    #[clap(long, default_value_t = 10.0)]
+    max_jump: f64,
+}
+
+/// Fetch rates from the ECB data API, or from a file, into the store. Rates fetched from the
+/// API are cached, so fetching them again works offline.
+#[derive(Args, Debug)]
+struct Fetch {
+    /// The pair to fetch. Pairs without the EUR are fetched as the rates of both currencies.
+    #[clap(long)]
+    pair: CurrencyPair,
+
+    /// The first date to fetch.
+    #[clap(long)]
+    from: NaiveDate,
+
+    /// The last date to fetch.
+    #[clap(long)]
+    to: NaiveDate,
+
+    /// The URL of the exchange rates dataset of the ECB data API.
+    #[clap(long, default_value = ECB_API)]
+    url: String,
+
+    /// The directory to cache fetched rates in.
+    #[clap(long, default_value = ".rate-cache")]
+    cache_dir: PathBuf,
+
+    /// Fetch the rates from this file instead, in any format with one rate per day.
+    #[clap(long, conflicts_with = "url")]
+    file: Option<String>,
+
+    /// How the file is quoted if it has a single pair and does not name its rate column
+    /// after it.
+    #[clap(long, requires = "file")]
+    rates_pair: Option<CurrencyPair>,
+
+    /// The currency that a file with one column per currency is quoted against.
+    #[clap(long, requires = "file", default_value = "EUR")]
+    rates_base: Currency,
+}
+
+/// Print stored rates as CSV.
+#[derive(Args, Debug)]
+struct Show {
+    /// Only show rates of this pair, stored either way round.
+    #[clap(long)]
+    pair: Option<CurrencyPair>,
+
+    /// The first date to show.
+    #[clap(long)]
+    from: Option<NaiveDate>,
+
+    /// The last date to show.
+    #[clap(long)]
+    to: Option<NaiveDate>,
+}
+
+/// Write the rates of a pair to a CSV file that convert-transactions can read.
+#[derive(Args, Debug)]
+struct Export {
+    /// The pair to export, stored either way round.
+    #[clap(long)]
+    pair: CurrencyPair,
+
+    /// The output CSV file.
+    #[clap(short, long)]
+    output: Box<str>,
+
+    /// The first date to export.
+    #[clap(long)]
+    from: Option<NaiveDate>,
+
+    /// The last date to export.
+    #[clap(long)]
+    to: Option<NaiveDate>,
+}
+
+/// Print the first and last date, the number of rates and the sources of every pair.
+#[derive(Args, Debug)]
+struct Coverage {
+    /// Also count the business days without a rate: TARGET2, NYSE, Fed, or a CSV file with
+    /// the dates and names of holidays. Join calendars with +, e.g. TARGET2+closures.csv.
+    #[clap(long)]
+    calendar: Option<Box<str>>,
+}
+
+/// Print the days without a rate between the first and last rate of a pair, telling which
+/// ones are weekends or holidays and which ones are missing.
+#[derive(Args, Debug)]
+struct Gaps {
+    /// The pair to check, as stored.
+    #[clap(long)]
+    pair: CurrencyPair,
+
+    /// The business days of the rates: TARGET2, NYSE, Fed, or a CSV file with the dates and
+    /// names of holidays. Join calendars with +, e.g. TARGET2+closures.csv.
+    #[clap(long, default_value = "TARGET2")]
+    calendar: Box<str>,
+}
+
+pub fn rates(args: &Rates) -> Result<(), Box<dyn Error>> {
+    let mut store = read_rate_store(&args.store)?;
+
+    match &args.command {
+        RatesCommand::Import(import) => {
+            let conflicts = import_rates(&mut store, import)?;
+            write_rate_store(&args.store, &store)?;
+            if conflicts > 0 {
+                return Err(format!("{} conflicting rates were not imported", conflicts).into());
+            }
+            Ok(())
+        }
+        RatesCommand::Fetch(fetch) => {
+            check_range(Some(fetch.from), Some(fetch.to))?;
+            let provider: Box<dyn RateProvider> = match &fetch.file {
+                Some(file) => Box::new(FileProvider::new(
+                    file.clone(),
+                    fetch.rates_pair,
+                    fetch.rates_base,
+                )),
+                None => Box::new(CachedProvider::new(
+                    EcbProvider::new(fetch.url.clone()),
+                    fetch.cache_dir.clone(),
+                )),
+            };
+            let rates = provider.fetch(fetch.pair, fetch.from, fetch.to)?;
+            let conflicts = report(provider.name(), &store.merge(rates));
+            write_rate_store(&args.store, &store)?;
+            if conflicts > 0 {
+                return Err(format!("{} conflicting rates were not stored", conflicts).into());
+            }
+            Ok(())
+        }
+        RatesCommand::Show(show) => {
+            check_range(show.from, show.to)?;
+            let pairs = match show.pair {
+                Some(pair) => vec![pair],
+                None => store.pairs(),
+            };
+            let rates = pairs
+                .into_iter()
+                .flat_map(|pair| store.range(pair, show.from, show.to));
+            Ok(write_stored_rates(std::io::stdout(), rates)?)
+        }
+        RatesCommand::Export(export) => {
+            check_range(export.from, export.to)?;
+            dispatch(
+                export.pair.quote(),
+                Quote {
+                    store: &store,
+                    export,
+                },
+            )?
+        }
+        RatesCommand::Coverage(coverage) => {
+            let calendar = coverage
+                .calendar
+                .as_deref()
+                .map(read_calendar)
+                .transpose()?;
+            Ok(write_coverage(
+                std::io::stdout(),
+                &store.coverage(calendar.as_ref()),
+            )?)
+        }
+        RatesCommand::Gaps(gaps) => {
+            let calendar = read_calendar(&gaps.calendar)?;
+            let rates = store
+                .pair_rates(gaps.pair)
+                .ok_or_else(|| format!("The store has no {} rates", gaps.pair))?;
+            Ok(write_gaps(std::io::stdout(), &rates.gaps(), &calendar)?)
+        }
+    }
+}
+
+/// Fails if `from` is after `to`.
+fn check_range(from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<(), String> {
+    match (from, to) {
+        (Some(from), Some(to)) if from > to => Err(format!("--from {} is after --to {}", from, to)),
+        _ => Ok(()),
+    }
+}
+
+/// Merges the files into the store, reporting what was added. Returns the number of
+/// conflicts.
+fn import_rates(store: &mut RateStore, import: &Import) -> Result<usize, Box<dyn Error>> {
+    let checks = RateChecks::new(Some(import.max_jump))?;
+    let mut conflicts = 0;
+
+    for file in &import.files {
+        let source = match &import.source {
+            Some(source) => source.clone(),
+            None => Path::new(file.as_ref())
+                .file_name()
+                .map_or(file.to_string(), |name| name.to_string_lossy().into_owned()),
+        };
+        let rates =
+            read_stored_rates(file, import.rates_pair, import.rates_base, &source, &checks)?;
+        conflicts += report(file, &store.merge(rates));
+    }
+
+    Ok(conflicts)
+}
+
+/// Prints what merging the rates from `source` did. Returns the number of conflicts.
+fn report(source: &str, summary: &MergeSummary) -> usize {
+    println!(
+        "{}: {} added, {} unchanged, {} conflicting",
+        source,
+        summary.added,
+        summary.unchanged,
+        summary.conflicts.len()
+    );
+    for conflict in &summary.conflicts {
+        println!("  {}", conflict);
+    }
+    summary.conflicts.len()
+}
+
+struct Quote<'a> {
+    store: &'a RateStore,
+    export: &'a Export,
+}
+
+impl CurrencyDispatch for Quote<'_> {
+    type Output = Result<(), Box<dyn Error>>;
+
+    fn call<N>(self) -> Self::Output
+    where
+        N: CurrencyType + for<'de> Deserialize<'de> + 'static,
+    {
+        dispatch(
+            self.export.pair.base(),
+            Base::<N> {
+                store: self.store,
+                export: self.export,
+                phantom: std::marker::PhantomData,
+            },
+        )?
+    }
+}
+
+struct Base<'a, N> {
+    store: &'a RateStore,
+    export: &'a Export,
+    phantom: std::marker::PhantomData<N>,
+}
+
+impl<N> CurrencyDispatch for Base<'_, N>
+where
+    N: CurrencyType + for<'de> Deserialize<'de> + 'static,
+{
+    type Output = Result<(), Box<dyn Error>>;
+
+    fn call<D>(self) -> Self::Output
+    where
+        D: CurrencyType + for<'de> Deserialize<'de> + 'static,
+    {
+        let export = self.export;
+        let rates = self
+            .store
+            .pair::<N, D>()
+            .ok_or_else(|| format!("The store has no {} rates", export.pair))?;
+
+        write_daily_rates(&export.output, &rates, export.from, export.to)
+    }
+}
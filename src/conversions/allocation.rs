@@ -0,0 +1,205 @@
+use std::{error::Error, fmt::Display};
+
+use crate::{
+    conversions::{
+        currency::{Currency, CurrencyType},
+        decimal::Decimal,
+        rounding::RoundingMode,
+    },
+    currency::Money,
+};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AllocationError {
+    NoParts,
+    NegativeWeight,
+    ZeroWeights,
+    Overflow,
+}
+
+impl Display for AllocationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AllocationError::NoParts => write!(f, "Cannot allocate to zero parts"),
+            AllocationError::NegativeWeight => write!(f, "Allocation weights must not be negative"),
+            AllocationError::ZeroWeights => write!(f, "Allocation weights must not all be zero"),
+            AllocationError::Overflow => write!(f, "Allocation overflowed"),
+        }
+    }
+}
+
+impl Error for AllocationError {}
+
+/// Splits `amount` minor units in proportion to `weights`, so that the parts add up to
+/// exactly `amount`.
+///
+/// Every part first gets its exact share rounded toward zero. The minor units that are
+/// left over are then handed out one at a time to the parts with the largest discarded
+/// fractions (the largest remainder method). Parts with equal fractions are served in
+/// the order they were given in, so the result is deterministic. Negative amounts are
+/// split like their absolute value and then negated.
+pub fn allocate(amount: i128, weights: &[Decimal]) -> Result<Vec<i128>, AllocationError> {
+    if weights.is_empty() {
+        return Err(AllocationError::NoParts);
+    }
+    if weights.iter().any(|weight| weight.mantissa() < 0) {
+        return Err(AllocationError::NegativeWeight);
+    }
+
+    let scale = weights.iter().map(Decimal::scale).max().unwrap_or(0);
+    let weights = weights
+        .iter()
+        .map(|weight| weight.rescale(scale, RoundingMode::default()))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(AllocationError::Overflow)?;
+    let total = weights
+        .iter()
+        .try_fold(0i128, |total, weight| total.checked_add(*weight))
+        .ok_or(AllocationError::Overflow)?;
+    if total == 0 {
+        return Err(AllocationError::ZeroWeights);
+    }
+
+    let magnitude = amount.checked_abs().ok_or(AllocationError::Overflow)?;
+    let shares = weights
+        .iter()
+        .map(|weight| magnitude.checked_mul(*weight))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(AllocationError::Overflow)?;
+    let mut parts: Vec<i128> = shares.iter().map(|share| share / total).collect();
+
+    let leftover = magnitude - parts.iter().sum::<i128>();
+    let mut by_remainder: Vec<usize> = (0..parts.len()).collect();
+    by_remainder.sort_by_key(|&i| std::cmp::Reverse(shares[i] % total));
+    for &i in by_remainder.iter().take(leftover as usize) {
+        parts[i] += 1;
+    }
+
+    if amount < 0 {
+        parts.iter_mut().for_each(|part| *part = -*part);
+    }
+    Ok(parts)
+}
+
+impl<T> Currency<T>
+where
+    T: CurrencyType,
+{
+    /// Splits the amount in proportion to `weights` without losing or creating minor units.
+    /// See [`allocate`] for where the remainder goes.
+    pub fn allocate(self, weights: &[Decimal]) -> Result<Vec<Self>, AllocationError> {
+        Ok(allocate(self.raw_amount(), weights)?
+            .into_iter()
+            .map(Currency::from_raw_amount)
+            .collect())
+    }
+
+    /// Splits the amount into `parts` equal parts that differ by at most one minor unit,
+    /// with the larger parts first.
+    pub fn split(self, parts: usize) -> Result<Vec<Self>, AllocationError> {
+        self.allocate(&vec![Decimal::new(1, 0); parts])
+    }
+}
+
+impl Money {
+    /// Splits the amount in proportion to `weights` without losing or creating minor units.
+    /// See [`allocate`] for where the remainder goes.
+    pub fn allocate(self, weights: &[Decimal]) -> Result<Vec<Self>, AllocationError> {
+        Ok(allocate(self.amount(), weights)?
+            .into_iter()
+            .map(|amount| Money::from_minor_units(amount, self.currency()))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversions::currency::{EUR, JPY};
+    use crate::currency;
+
+    fn weights(weights: &[&str]) -> Vec<Decimal> {
+        weights
+            .iter()
+            .map(|weight| weight.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_split() {
+        let parts = Currency::<EUR>::from(100).split(3).unwrap();
+
+        assert_eq!(
+            parts,
+            vec![
+                Currency::try_from(33.34).unwrap(),
+                Currency::try_from(33.33).unwrap(),
+                Currency::try_from(33.33).unwrap()
+            ]
+        );
+        assert_eq!(parts.iter().sum::<Currency<EUR>>(), Currency::from(100));
+    }
+
+    #[test]
+    fn test_allocate_largest_remainder() {
+        // Exact shares are 0.05 * 1/6, 2/6 and 3/6 = 0.8333, 1.6667 and 2.5 cents.
+        let parts = Currency::<EUR>::try_from(0.05)
+            .unwrap()
+            .allocate(&weights(&["1", "2", "3"]))
+            .unwrap();
+
+        assert_eq!(
+            parts,
+            vec![
+                Currency::try_from(0.01).unwrap(),
+                Currency::try_from(0.02).unwrap(),
+                Currency::try_from(0.02).unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_allocate_fractional_weights_and_negative_amount() {
+        let parts = Currency::<JPY>::from(-1000)
+            .allocate(&weights(&["1.5", "0.25", "1"]))
+            .unwrap();
+
+        assert_eq!(
+            parts,
+            vec![
+                Currency::from(-545),
+                Currency::from(-91),
+                Currency::from(-364)
+            ]
+        );
+        assert_eq!(parts.iter().sum::<Currency<JPY>>(), Currency::from(-1000));
+    }
+
+    #[test]
+    fn test_allocate_money() {
+        let fee = Money::from_minor_units(1001, currency::Currency::USD);
+
+        assert_eq!(
+            fee.allocate(&weights(&["1", "1"])).unwrap(),
+            vec![
+                Money::from_minor_units(501, currency::Currency::USD),
+                Money::from_minor_units(500, currency::Currency::USD)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_allocate_errors() {
+        let amount = Currency::<EUR>::from(1);
+
+        assert_eq!(amount.allocate(&[]), Err(AllocationError::NoParts));
+        assert_eq!(
+            amount.allocate(&weights(&["0", "0"])),
+            Err(AllocationError::ZeroWeights)
+        );
+        assert_eq!(
+            amount.allocate(&weights(&["1", "-1"])),
+            Err(AllocationError::NegativeWeight)
+        );
+    }
+}
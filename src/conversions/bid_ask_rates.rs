@@ -0,0 +1,409 @@
+use std::{fmt::Display, sync::Arc};
+
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use csv::{Reader, StringRecord};
+use serde::{Deserialize, Serialize};
+
+use crate::conversions::{
+    currency::{Currency, CurrencyType},
+    daily_exchange_rates::ConversionError,
+    date_map::DateMap,
+    exchange_rate::ExchangeRate,
+    fallback::Fallback,
+    provenance::{Provenance, RateOrigin},
+    rate_source::{RateQuote, RateSource},
+    validation::{reject_errors, RateChecks, RateIssue, RateRow, ValidationError},
+};
+
+/// Which of the published rates of a day a conversion used.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RateSide {
+    /// The rate at which the bank buys `D` ("Geldkurs").
+    Bid,
+    /// The rate at which the bank sells `D` ("Briefkurs").
+    Ask,
+    /// The published middle rate, or the average of bid and ask.
+    #[default]
+    Mid,
+}
+
+impl Display for RateSide {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RateSide::Bid => write!(f, "bid"),
+            RateSide::Ask => write!(f, "ask"),
+            RateSide::Mid => write!(f, "mid"),
+        }
+    }
+}
+
+/// How to pick the side of the rate for a transaction.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum SidePolicy {
+    /// Always use the mid rate.
+    #[default]
+    Mid,
+    /// Always use the bid rate.
+    Bid,
+    /// Always use the ask rate.
+    Ask,
+    /// Use the bid rate for incoming (positive) amounts and the ask rate for outgoing
+    /// (negative) ones.
+    ByDirection,
+}
+
+impl SidePolicy {
+    pub fn side<T>(self, amount: Currency<T>) -> RateSide
+    where
+        T: CurrencyType,
+    {
+        match self {
+            SidePolicy::Mid => RateSide::Mid,
+            SidePolicy::Bid => RateSide::Bid,
+            SidePolicy::Ask => RateSide::Ask,
+            SidePolicy::ByDirection if amount.raw_amount() < 0 => RateSide::Ask,
+            SidePolicy::ByDirection => RateSide::Bid,
+        }
+    }
+}
+
+/// The bid and ask rates of a day, and the mid rate if it was published separately.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BidAskRate<N, D>
+where
+    N: CurrencyType,
+    D: CurrencyType,
+{
+    bid: ExchangeRate<N, D>,
+    ask: ExchangeRate<N, D>,
+    mid: Option<ExchangeRate<N, D>>,
+}
+
+impl<N, D> BidAskRate<N, D>
+where
+    N: CurrencyType,
+    D: CurrencyType,
+{
+    pub fn new(
+        bid: ExchangeRate<N, D>,
+        ask: ExchangeRate<N, D>,
+        mid: Option<ExchangeRate<N, D>>,
+    ) -> Self {
+        BidAskRate { bid, ask, mid }
+    }
+
+    /// The same rates quoted the other way round. The bank's bid for `D` is its ask for
+    /// `N`, so bid and ask swap sides. Returns `None` if any rate is zero.
+    pub fn checked_invert(&self) -> Option<BidAskRate<D, N>> {
+        Some(BidAskRate::new(
+            self.ask.checked_invert()?,
+            self.bid.checked_invert()?,
+            match self.mid {
+                Some(mid) => Some(mid.checked_invert()?),
+                None => None,
+            },
+        ))
+    }
+
+    /// The rate of `side`. Without a published mid rate, the mid rate is the exact
+    /// average of bid and ask. Returns `None` if that average overflows.
+    pub fn side(&self, side: RateSide) -> Option<ExchangeRate<N, D>> {
+        match side {
+            RateSide::Bid => Some(self.bid),
+            RateSide::Ask => Some(self.ask),
+            RateSide::Mid => self.mid.or_else(|| self.bid.checked_midpoint(&self.ask)),
+        }
+    }
+}
+
+/// Daily bid and ask rates, read from a CSV file with `date`, `bid` and `ask` columns and
+/// an optional `mid` column.
+#[derive(Debug, PartialEq)]
+pub struct DailyBidAskRates<N, D>
+where
+    N: CurrencyType,
+    D: CurrencyType,
+{
+    rates: DateMap<BidAskRate<N, D>>,
+    provenance: DateMap<Provenance>,
+    fallback: Fallback,
+}
+
+/// The date of a row, to check the rate columns before reading them.
+#[derive(Debug, Deserialize)]
+struct DayDate {
+    date: NaiveDate,
+}
+
+#[derive(Debug, Deserialize)]
+struct DayBidAskRate<N, D>
+where
+    N: CurrencyType,
+    D: CurrencyType,
+{
+    date: NaiveDate,
+    bid: ExchangeRate<N, D>,
+    ask: ExchangeRate<N, D>,
+    mid: Option<ExchangeRate<N, D>>,
+}
+
+impl<N, D> DailyBidAskRates<N, D>
+where
+    N: CurrencyType + for<'de> Deserialize<'de>,
+    D: CurrencyType + for<'de> Deserialize<'de>,
+{
+    /// Records that all rates came from `origin`, keeping the lines they were read from.
+    pub fn with_origin(mut self, origin: RateOrigin) -> Self {
+        let origin = Arc::new(origin);
+        self.provenance = self
+            .rates
+            .iter()
+            .map(|(date, _)| {
+                let provenance = match self.provenance.get(date) {
+                    Some(provenance) => provenance.with_origin(origin.clone()),
+                    None => Provenance::new(origin.clone(), None),
+                };
+                (*date, provenance)
+            })
+            .collect();
+        self
+    }
+
+    /// Uses `fallback` for dates without rates, instead of only the rates of the date itself.
+    pub fn with_fallback(self, fallback: Fallback) -> Self {
+        DailyBidAskRates { fallback, ..self }
+    }
+
+    /// The rates published for `date`, without falling back to other days.
+    pub fn day_rate(&self, date: &NaiveDate) -> Option<&BidAskRate<N, D>> {
+        self.rates.get(date)
+    }
+
+    /// The same rates quoted the other way round, or `None` if any rate is zero.
+    pub fn checked_invert(&self) -> Option<DailyBidAskRates<D, N>> {
+        Some(DailyBidAskRates {
+            rates: self.rates.try_map(BidAskRate::checked_invert)?,
+            provenance: self.provenance.clone(),
+            fallback: self.fallback.clone(),
+        })
+    }
+
+    /// Reads a CSV file with `date`, `bid` and `ask` columns, checking it with the default
+    /// [`RateChecks`].
+    pub fn read_from_csv<R>(reader: Reader<R>) -> Result<Self, ValidationError>
+    where
+        R: std::io::Read,
+    {
+        Self::read_checked(reader, &RateChecks::default())
+    }
+
+    /// Reads a CSV file with `date`, `bid` and `ask` columns. Every rate column is checked
+    /// with `checks`, and days whose bid is above their ask are rejected.
+    pub fn read_checked<R>(
+        mut reader: Reader<R>,
+        checks: &RateChecks,
+    ) -> Result<Self, ValidationError>
+    where
+        R: std::io::Read,
+    {
+        let headers = reader.headers()?.clone();
+        let records = reader.records().collect::<Result<Vec<_>, _>>()?;
+        let line = |record: &StringRecord| record.position().map_or(0, |position| position.line());
+
+        let mut issues = Vec::new();
+        for column in ["bid", "ask", "mid"] {
+            let Some(i) = headers.iter().position(|header| header.trim() == column) else {
+                continue;
+            };
+            let rows = records
+                .iter()
+                .filter(|record| !record.get(i).unwrap_or_default().trim().is_empty())
+                .map(|record| {
+                    Ok(RateRow {
+                        line: line(record),
+                        date: record.deserialize::<DayDate>(Some(&headers))?.date,
+                        rate: record.get(i).unwrap_or_default().trim().to_string(),
+                    })
+                })
+                .collect::<Result<Vec<_>, csv::Error>>()?;
+            issues.extend(match column {
+                "bid" => checks.check(&rows),
+                _ => checks.check_rates(&rows),
+            });
+        }
+        reject_errors(issues)?;
+
+        let mut rates = Vec::new();
+        let mut provenance = DateMap::new();
+        let mut crossed = Vec::new();
+        for record in &records {
+            let day_rate: DayBidAskRate<N, D> = record.deserialize(Some(&headers))?;
+            if day_rate.bid.rate() > day_rate.ask.rate() {
+                if let (Some(bid), Some(ask)) =
+                    (day_rate.bid.to_decimal(), day_rate.ask.to_decimal())
+                {
+                    crossed.push(RateIssue::BidAboveAsk {
+                        line: line(record),
+                        bid,
+                        ask,
+                    });
+                }
+            }
+            rates.push((
+                day_rate.date,
+                BidAskRate::new(day_rate.bid, day_rate.ask, day_rate.mid),
+            ));
+            provenance.insert(
+                day_rate.date,
+                Provenance::new(Arc::default(), Some(line(record))),
+            );
+        }
+        if !crossed.is_empty() {
+            return Err(ValidationError::Invalid(crossed));
+        }
+
+        Ok(DailyBidAskRates {
+            rates: rates.into_iter().collect(),
+            provenance,
+            fallback: Fallback::default(),
+        })
+    }
+}
+
+impl<N, D> RateSource<N, D> for DailyBidAskRates<N, D>
+where
+    N: CurrencyType + for<'de> Deserialize<'de>,
+    D: CurrencyType + for<'de> Deserialize<'de>,
+{
+    fn quote(&self, date: &NaiveDate) -> Result<RateQuote<N, D>, ConversionError> {
+        self.quote_side(date, RateSide::Mid)
+    }
+
+    fn quote_side(
+        &self,
+        date: &NaiveDate,
+        side: RateSide,
+    ) -> Result<RateQuote<N, D>, ConversionError> {
+        let quote = self
+            .fallback
+            .find(date, &self.rates)
+            .ok_or(ConversionError::MissingExchangeRate)?
+            .try_map(|rate| rate.side(side))
+            .and_then(|rate| rate.into_quote(date))
+            .ok_or(ConversionError::AmountOverflow)?;
+        let provenance = quote
+            .dates()
+            .iter()
+            .filter_map(|date| self.provenance.get(date))
+            .cloned()
+            .collect();
+        Ok(quote.with_side(side).with_provenance(provenance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversions::currency::{EUR, USD};
+
+    fn rates() -> DailyBidAskRates<USD, EUR> {
+        let csv = "date,bid,ask,mid
+                   2024-05-02,1.0655,1.0715,1.0686
+                   2024-05-03,1.0700,1.0760,";
+        DailyBidAskRates::read_from_csv(Reader::from_reader(csv.as_bytes())).unwrap()
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap()
+    }
+
+    #[test]
+    fn test_quote_side() {
+        let rates = rates();
+
+        for (day, side, rate) in [
+            (2, RateSide::Bid, 1.0655),
+            (2, RateSide::Ask, 1.0715),
+            (2, RateSide::Mid, 1.0686),
+            (3, RateSide::Mid, 1.073),
+        ] {
+            let quote = rates.quote_side(&date(day), side).unwrap();
+            assert_eq!(quote.rate(), &ExchangeRate::new(rate));
+            assert_eq!(quote.side(), side);
+            assert_eq!(
+                quote.provenance()[0].line(),
+                Some(day as u64),
+                "{} {}",
+                day,
+                side
+            );
+        }
+        assert_eq!(
+            rates.quote_side(&date(6), RateSide::Bid),
+            Err(ConversionError::MissingExchangeRate)
+        );
+    }
+
+    #[test]
+    fn test_checked_columns() {
+        let csv = "date,bid,ask\n2024-05-02,1.0655,1.0715\n2024-05-03,1.0700,0\n";
+
+        match DailyBidAskRates::<USD, EUR>::read_from_csv(Reader::from_reader(csv.as_bytes())) {
+            Err(ValidationError::Invalid(issues)) => assert!(matches!(
+                issues[..],
+                [RateIssue::NonPositiveRate { line: 3, .. }]
+            )),
+            other => panic!("Expected invalid rates, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_bid_above_ask() {
+        let csv = "date,bid,ask\n2024-05-02,1.0655,1.0715\n2024-05-03,1.0760,1.0700\n";
+
+        match DailyBidAskRates::<USD, EUR>::read_from_csv(Reader::from_reader(csv.as_bytes())) {
+            Err(ValidationError::Invalid(issues)) => assert_eq!(
+                issues,
+                vec![RateIssue::BidAboveAsk {
+                    line: 3,
+                    bid: "1.0760".parse().unwrap(),
+                    ask: "1.0700".parse().unwrap(),
+                }]
+            ),
+            other => panic!("Expected invalid rates, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_checked_invert() {
+        let inverted = rates().checked_invert().unwrap();
+        let rate = inverted.day_rate(&date(2)).unwrap();
+
+        assert_eq!(
+            rate.side(RateSide::Bid),
+            Some(ExchangeRate::new(1.0715).invert())
+        );
+        assert_eq!(
+            rate.side(RateSide::Ask),
+            Some(ExchangeRate::new(1.0655).invert())
+        );
+        assert_eq!(
+            rate.side(RateSide::Mid),
+            Some(ExchangeRate::new(1.0686).invert())
+        );
+    }
+
+    #[test]
+    fn test_side_policy() {
+        let incoming = Currency::<EUR>::from(100);
+        let outgoing = Currency::<EUR>::from(-100);
+
+        assert_eq!(SidePolicy::ByDirection.side(incoming), RateSide::Bid);
+        assert_eq!(SidePolicy::ByDirection.side(outgoing), RateSide::Ask);
+        assert_eq!(SidePolicy::Ask.side(incoming), RateSide::Ask);
+        assert_eq!(SidePolicy::default().side(outgoing), RateSide::Mid);
+    }
+}
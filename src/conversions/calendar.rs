@@ -0,0 +1,490 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    ops::Bound,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use csv::Reader;
+use serde::Deserialize;
+
+use crate::conversions::date_map::DateMap;
+
+/// The calendars built into the crate, whose holidays follow from fixed rules.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BuiltinCalendar {
+    /// The TARGET2 payment system, which the ECB publishes its reference rates on.
+    Target2,
+    /// The New York Stock Exchange.
+    Nyse,
+    /// The Federal Reserve banks, which the H.10 rates are published on.
+    Fed,
+}
+
+impl BuiltinCalendar {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BuiltinCalendar::Target2 => "TARGET2",
+            BuiltinCalendar::Nyse => "NYSE",
+            BuiltinCalendar::Fed => "Fed",
+        }
+    }
+
+    /// The holidays of `year` that fall on weekdays, with their names. Holidays that fall
+    /// on a weekend are observed on the Friday before or the Monday after, as the calendar
+    /// does.
+    pub fn holidays(&self, year: i32) -> Vec<(NaiveDate, &'static str)> {
+        let date = |month, day| NaiveDate::from_ymd_opt(year, month, day);
+        let easter = easter_sunday(year);
+        let mut holidays = match self {
+            BuiltinCalendar::Target2 => vec![
+                (date(1, 1), "New Year's Day"),
+                (
+                    easter.and_then(|easter| easter.checked_sub_days(Days::new(2))),
+                    "Good Friday",
+                ),
+                (
+                    easter.and_then(|easter| easter.checked_add_days(Days::new(1))),
+                    "Easter Monday",
+                ),
+                (date(5, 1), "Labour Day"),
+                (date(12, 25), "Christmas Day"),
+                (date(12, 26), "Christmas Holiday"),
+            ],
+            BuiltinCalendar::Nyse => vec![
+                (
+                    date(1, 1).filter(|day| day.weekday() != Weekday::Sat),
+                    "New Year's Day",
+                ),
+                (
+                    nth_weekday(year, 1, Weekday::Mon, 3),
+                    "Martin Luther King Jr. Day",
+                ),
+                (
+                    nth_weekday(year, 2, Weekday::Mon, 3),
+                    "Washington's Birthday",
+                ),
+                (
+                    easter.and_then(|easter| easter.checked_sub_days(Days::new(2))),
+                    "Good Friday",
+                ),
+                (last_weekday(year, 5, Weekday::Mon), "Memorial Day"),
+                (date(6, 19).filter(|_| year >= 2022), "Juneteenth"),
+                (date(7, 4), "Independence Day"),
+                (nth_weekday(year, 9, Weekday::Mon, 1), "Labor Day"),
+                (nth_weekday(year, 11, Weekday::Thu, 4), "Thanksgiving Day"),
+                (date(12, 25), "Christmas Day"),
+            ],
+            BuiltinCalendar::Fed => vec![
+                (date(1, 1), "New Year's Day"),
+                (
+                    nth_weekday(year, 1, Weekday::Mon, 3),
+                    "Martin Luther King Jr. Day",
+                ),
+                (
+                    nth_weekday(year, 2, Weekday::Mon, 3),
+                    "Washington's Birthday",
+                ),
+                (last_weekday(year, 5, Weekday::Mon), "Memorial Day"),
+                (date(6, 19).filter(|_| year >= 2022), "Juneteenth"),
+                (date(7, 4), "Independence Day"),
+                (nth_weekday(year, 9, Weekday::Mon, 1), "Labor Day"),
+                (nth_weekday(year, 10, Weekday::Mon, 2), "Columbus Day"),
+                (date(11, 11), "Veterans Day"),
+                (nth_weekday(year, 11, Weekday::Thu, 4), "Thanksgiving Day"),
+                (date(12, 25), "Christmas Day"),
+            ],
+        }
+        .into_iter()
+        .filter_map(|(date, name)| Some((self.observed(date?)?, name)))
+        .collect::<Vec<_>>();
+        holidays.sort();
+        holidays
+    }
+
+    /// The weekday a holiday on `date` is observed on, if any. TARGET2 does not make up for
+    /// holidays on weekends, the NYSE moves them to the Friday before or the Monday after,
+    /// and the Fed only to the Monday after.
+    fn observed(&self, date: NaiveDate) -> Option<NaiveDate> {
+        match (self, date.weekday()) {
+            (_, Weekday::Mon | Weekday::Tue | Weekday::Wed | Weekday::Thu | Weekday::Fri) => {
+                Some(date)
+            }
+            (BuiltinCalendar::Target2, _) => None,
+            (BuiltinCalendar::Nyse, Weekday::Sat) => date.pred_opt(),
+            (BuiltinCalendar::Fed, Weekday::Sat) => None,
+            (_, Weekday::Sun) => date.succ_opt(),
+        }
+    }
+}
+
+impl FromStr for BuiltinCalendar {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "target2" | "target" => Ok(BuiltinCalendar::Target2),
+            "nyse" => Ok(BuiltinCalendar::Nyse),
+            "fed" => Ok(BuiltinCalendar::Fed),
+            _ => Err(format!("Unknown calendar {}", s)),
+        }
+    }
+}
+
+/// Easter Sunday of `year` in the Gregorian calendar.
+fn easter_sunday(year: i32) -> Option<NaiveDate> {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: u8) -> Option<NaiveDate> {
+    NaiveDate::from_weekday_of_month_opt(year, month, weekday, n)
+}
+
+fn last_weekday(year: i32, month: u32, weekday: Weekday) -> Option<NaiveDate> {
+    nth_weekday(year, month, weekday, 5).or_else(|| nth_weekday(year, month, weekday, 4))
+}
+
+/// What kind of day a date is in a [`BusinessCalendar`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DayKind<'a> {
+    BusinessDay,
+    Weekend,
+    /// A holiday, with its name.
+    Holiday(&'a str),
+}
+
+impl Display for DayKind<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DayKind::BusinessDay => write!(f, "business day"),
+            DayKind::Weekend => write!(f, "weekend"),
+            DayKind::Holiday(name) => write!(f, "holiday ({})", name),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct HolidayRow {
+    date: NaiveDate,
+    #[serde(default)]
+    name: String,
+}
+
+/// The holidays of the years that a built-in calendar was asked about, so that they are
+/// derived from the rules once per year.
+#[derive(Debug, Default)]
+struct HolidayCache(Mutex<HashMap<i32, Arc<BTreeMap<NaiveDate, &'static str>>>>);
+
+impl HolidayCache {
+    fn year(&self, rules: BuiltinCalendar, year: i32) -> Arc<BTreeMap<NaiveDate, &'static str>> {
+        let mut years = self.0.lock().unwrap_or_else(|error| error.into_inner());
+        years
+            .entry(year)
+            .or_insert_with(|| Arc::new(rules.holidays(year).into_iter().collect()))
+            .clone()
+    }
+}
+
+impl Clone for HolidayCache {
+    fn clone(&self) -> Self {
+        HolidayCache::default()
+    }
+}
+
+/// The days that a market or payment system is open, on which rates are published and
+/// trades settle. Saturdays and Sundays are never business days.
+#[derive(Clone, Debug)]
+pub struct BusinessCalendar {
+    name: String,
+    rules: Option<BuiltinCalendar>,
+    holidays: BTreeMap<NaiveDate, String>,
+    cache: HolidayCache,
+}
+
+impl PartialEq for BusinessCalendar {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.rules == other.rules && self.holidays == other.holidays
+    }
+}
+
+impl Eq for BusinessCalendar {}
+
+impl BusinessCalendar {
+    pub fn builtin(calendar: BuiltinCalendar) -> Self {
+        BusinessCalendar {
+            name: calendar.name().to_string(),
+            rules: Some(calendar),
+            holidays: BTreeMap::new(),
+            cache: HolidayCache::default(),
+        }
+    }
+
+    /// A calendar with just the given holidays.
+    pub fn new(name: String, holidays: impl IntoIterator<Item = (NaiveDate, String)>) -> Self {
+        BusinessCalendar {
+            name,
+            rules: None,
+            holidays: holidays.into_iter().collect(),
+            cache: HolidayCache::default(),
+        }
+    }
+
+    /// This calendar with the holidays of `other` added, e.g. a built-in calendar with
+    /// extra closing days from a file.
+    pub fn extend(mut self, other: BusinessCalendar) -> Self {
+        self.name = format!("{}+{}", self.name, other.name);
+        self.holidays.extend(other.holidays);
+        if self.rules.is_none() {
+            self.rules = other.rules;
+        }
+        self
+    }
+
+    /// Reads the holidays of a calendar from a CSV file with a `date` and an optional
+    /// `name` column.
+    pub fn read_from_csv<R>(name: String, mut reader: Reader<R>) -> Result<Self, csv::Error>
+    where
+        R: std::io::Read,
+    {
+        let holidays = reader
+            .deserialize()
+            .map(|row| {
+                let row: HolidayRow = row?;
+                Ok((row.date, row.name))
+            })
+            .collect::<Result<Vec<_>, csv::Error>>()?;
+        Ok(BusinessCalendar::new(name, holidays))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The name of the holiday on `date`, if it is one.
+    pub fn holiday(&self, date: &NaiveDate) -> Option<&str> {
+        if let Some(name) = self.holidays.get(date) {
+            return Some(name);
+        }
+        let rules = self.rules?;
+        self.cache.year(rules, date.year()).get(date).copied()
+    }
+
+    pub fn day_kind(&self, date: &NaiveDate) -> DayKind<'_> {
+        if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+            DayKind::Weekend
+        } else if let Some(name) = self.holiday(date) {
+            DayKind::Holiday(name)
+        } else {
+            DayKind::BusinessDay
+        }
+    }
+
+    pub fn is_business_day(&self, date: &NaiveDate) -> bool {
+        self.day_kind(date) == DayKind::BusinessDay
+    }
+
+    /// The number of business days after `from` up to and including `to`.
+    pub fn business_days(&self, from: &NaiveDate, to: &NaiveDate) -> u32 {
+        let days = (*to - *from).num_days();
+        if days <= 0 {
+            return 0;
+        }
+
+        let is_weekday = |date: &NaiveDate| !matches!(date.weekday(), Weekday::Sat | Weekday::Sun);
+        let rest = from
+            .iter_days()
+            .skip(1 + (days / 7 * 7) as usize)
+            .take((days % 7) as usize)
+            .filter(is_weekday)
+            .count() as i64;
+        let weekdays = days / 7 * 5 + rest;
+
+        let range = (Bound::Excluded(*from), Bound::Included(*to));
+        let mut holidays: Vec<NaiveDate> = self
+            .holidays
+            .range(range)
+            .map(|(date, _)| *date)
+            .filter(is_weekday)
+            .collect();
+        if let Some(rules) = self.rules {
+            for year in from.year()..=to.year() {
+                holidays.extend(
+                    self.cache
+                        .year(rules, year)
+                        .range(range)
+                        .map(|(date, _)| *date),
+                );
+            }
+        }
+        holidays.sort();
+        holidays.dedup();
+
+        (weekdays - holidays.len() as i64) as u32
+    }
+
+    /// The business day `days` business days after `date`, such as the settlement date of a
+    /// trade. Zero days is `date` itself if it is a business day, or else the next one.
+    pub fn add_business_days(&self, date: &NaiveDate, days: u32) -> Option<NaiveDate> {
+        let mut business_days = date
+            .iter_days()
+            .skip(usize::from(days > 0))
+            .filter(|date| self.is_business_day(date));
+        business_days.nth(days.saturating_sub(1) as usize)
+    }
+
+    /// The business days between the first and the last date of `values` that have no
+    /// value, i.e. where a daily source is incomplete.
+    pub fn missing_days<T>(&self, values: &DateMap<T>) -> Vec<NaiveDate> {
+        values
+            .gaps()
+            .into_iter()
+            .flat_map(|gap| {
+                gap.first
+                    .iter_days()
+                    .take_while(move |date| *date <= gap.last)
+            })
+            .filter(|date| self.is_business_day(date))
+            .collect()
+    }
+}
+
+/// When trades settle: a number of business days after the trade date.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Settlement {
+    calendar: Arc<BusinessCalendar>,
+    days: u32,
+}
+
+impl Settlement {
+    pub fn new(calendar: Arc<BusinessCalendar>, days: u32) -> Self {
+        Settlement { calendar, days }
+    }
+
+    pub fn days(&self) -> u32 {
+        self.days
+    }
+
+    /// The settlement date of a trade on `trade_date`.
+    pub fn date(&self, trade_date: &NaiveDate) -> Option<NaiveDate> {
+        self.calendar.add_business_days(trade_date, self.days)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_holidays() {
+        let target2 = BusinessCalendar::builtin(BuiltinCalendar::Target2);
+        assert_eq!(
+            target2.day_kind(&date(2024, 3, 29)),
+            DayKind::Holiday("Good Friday")
+        );
+        assert_eq!(
+            target2.day_kind(&date(2024, 4, 1)),
+            DayKind::Holiday("Easter Monday")
+        );
+        assert_eq!(target2.day_kind(&date(2024, 3, 30)), DayKind::Weekend);
+        assert!(target2.is_business_day(&date(2024, 7, 4)));
+
+        let nyse = BusinessCalendar::builtin(BuiltinCalendar::Nyse);
+        assert_eq!(nyse.holiday(&date(2024, 11, 28)), Some("Thanksgiving Day"));
+        assert_eq!(nyse.holiday(&date(2021, 12, 24)), Some("Christmas Day"));
+        assert_eq!(nyse.holiday(&date(2021, 12, 31)), None);
+        assert!(nyse.is_business_day(&date(2024, 10, 14)));
+
+        let fed = BusinessCalendar::builtin(BuiltinCalendar::Fed);
+        assert_eq!(fed.holiday(&date(2024, 10, 14)), Some("Columbus Day"));
+        assert_eq!(fed.holiday(&date(2023, 1, 2)), Some("New Year's Day"));
+        assert!(fed.is_business_day(&date(2024, 3, 29)));
+    }
+
+    #[test]
+    fn test_settlement() {
+        let target2 = BusinessCalendar::builtin(BuiltinCalendar::Target2);
+
+        assert_eq!(
+            target2.add_business_days(&date(2024, 3, 27), 2),
+            Some(date(2024, 4, 2))
+        );
+        assert_eq!(
+            target2.add_business_days(&date(2024, 3, 30), 0),
+            Some(date(2024, 4, 2))
+        );
+        assert_eq!(
+            target2.business_days(&date(2024, 3, 28), &date(2024, 4, 2)),
+            1
+        );
+        assert_eq!(
+            Settlement::new(Arc::new(target2), 1).date(&date(2024, 12, 24)),
+            Some(date(2024, 12, 27))
+        );
+    }
+
+    #[test]
+    fn test_read_from_csv() {
+        let csv = "date,name\n2024-06-03,Whit Monday\n";
+        let calendar =
+            BusinessCalendar::read_from_csv("CH".to_string(), Reader::from_reader(csv.as_bytes()))
+                .unwrap();
+        let rates: DateMap<()> = [date(2024, 5, 31), date(2024, 6, 5)]
+            .into_iter()
+            .map(|date| (date, ()))
+            .collect();
+
+        assert_eq!(calendar.holiday(&date(2024, 6, 3)), Some("Whit Monday"));
+        assert_eq!(calendar.missing_days(&rates), vec![date(2024, 6, 4)]);
+
+        let extended = BusinessCalendar::builtin(BuiltinCalendar::Target2).extend(calendar);
+        assert_eq!(extended.name(), "TARGET2+CH");
+        assert_eq!(extended.holiday(&date(2024, 6, 3)), Some("Whit Monday"));
+        assert_eq!(extended.holiday(&date(2024, 5, 1)), Some("Labour Day"));
+        assert_eq!(
+            extended.business_days(&date(2024, 4, 30), &date(2024, 6, 7)),
+            26
+        );
+    }
+
+    #[test]
+    fn test_business_days_across_years() {
+        let target2 = BusinessCalendar::builtin(BuiltinCalendar::Target2);
+        let count = |from: NaiveDate, to: NaiveDate| {
+            from.iter_days()
+                .skip(1)
+                .take_while(|date| *date <= to)
+                .filter(|date| target2.is_business_day(date))
+                .count() as u32
+        };
+
+        for (from, to) in [
+            (date(2023, 12, 20), date(2025, 1, 3)),
+            (date(2024, 3, 28), date(2024, 4, 2)),
+            (date(2024, 5, 4), date(2024, 5, 5)),
+            (date(2024, 5, 6), date(2024, 5, 6)),
+            (date(2024, 5, 7), date(2024, 5, 6)),
+        ] {
+            assert_eq!(target2.business_days(&from, &to), count(from, to));
+        }
+    }
+}
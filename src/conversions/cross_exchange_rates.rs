@@ -0,0 +1,138 @@
+use std::marker::PhantomData;
+
+use chrono::NaiveDate;
+
+use crate::conversions::{
+    currency::CurrencyType,
+    daily_exchange_rates::{ConversionError, DailyExchangeRates},
+    rate_source::{RateLeg, RateQuote, RateSource},
+};
+
+/// Rates for converting `D` to `N`, derived from two rate sources that quote both
+/// currencies against a common base `B`, such as the ECB reference rates against EUR.
+///
+/// The cross rate of a date is the `N` rate of `B` divided by the `D` rate of `B`, kept as
+/// an exact fraction. Each source looks up its rate with its own fallback policy.
+#[derive(Debug, PartialEq)]
+pub struct CrossExchangeRates<N, D, B, SN = DailyExchangeRates<N, B>, SD = DailyExchangeRates<D, B>>
+where
+    N: CurrencyType,
+    D: CurrencyType,
+    B: CurrencyType,
+{
+    base_to_n: SN,
+    base_to_d: SD,
+    phantom: PhantomData<(N, D, B)>,
+}
+
+impl<N, D, B, SN, SD> CrossExchangeRates<N, D, B, SN, SD>
+where
+    N: CurrencyType,
+    D: CurrencyType,
+    B: CurrencyType,
+    SN: RateSource<N, B>,
+    SD: RateSource<D, B>,
+{
+    pub fn new(base_to_n: SN, base_to_d: SD) -> Self {
+        CrossExchangeRates {
+            base_to_n,
+            base_to_d,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<N, D, B, SN, SD> RateSource<N, D> for CrossExchangeRates<N, D, B, SN, SD>
+where
+    N: CurrencyType,
+    D: CurrencyType,
+    B: CurrencyType,
+    SN: RateSource<N, B>,
+    SD: RateSource<D, B>,
+{
+    fn quote(&self, date: &NaiveDate) -> Result<RateQuote<N, D>, ConversionError> {
+        let to_n = self.base_to_n.quote(date)?;
+        let to_d = self.base_to_d.quote(date)?;
+        let rate = to_n.rate().checked_compose(
+            &to_d
+                .rate()
+                .checked_invert()
+                .ok_or(ConversionError::ZeroExchangeRate)?,
+        );
+        let legs = [RateLeg::new(to_n.rate()), RateLeg::new(to_d.rate())];
+
+        let mut dates = [to_n.dates(), to_d.dates()].concat();
+        dates.sort();
+        dates.dedup();
+
+        let mut provenance = to_n.provenance().to_vec();
+        for other in to_d.provenance() {
+            if !provenance.contains(other) {
+                provenance.push(other.clone());
+            }
+        }
+
+        match (rate, legs) {
+            (Some(rate), [Some(to_n), Some(to_d)]) => {
+                Ok(RateQuote::derived(rate, vec![to_n, to_d], dates).with_provenance(provenance))
+            }
+            _ => Err(ConversionError::AmountOverflow),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversions::currency::{Currency, CHF, EUR, JPY};
+    use crate::conversions::exchange_rate::ExchangeRate;
+    use csv::Reader;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    fn rates() -> CrossExchangeRates<JPY, CHF, EUR> {
+        CrossExchangeRates::new(
+            DailyExchangeRates::read_from_csv(Reader::from_reader(
+                "date,rate\n2024-03-01,162.45\n2024-03-04,162.71\n".as_bytes(),
+            ))
+            .unwrap(),
+            DailyExchangeRates::read_from_csv(Reader::from_reader(
+                "date,rate\n2024-03-01,0.9565\n".as_bytes(),
+            ))
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_quote() {
+        let quote = rates().quote(&date(1)).unwrap();
+
+        assert_eq!(
+            quote.rate(),
+            &ExchangeRate::<JPY, EUR>::new(162.45).compose(&ExchangeRate::new(0.9565).invert())
+        );
+        assert_eq!(
+            quote
+                .legs()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["EUR/JPY 162.45", "EUR/CHF 0.9565"]
+        );
+        // 1000.00 CHF * 162.45 / 0.9565 = 169837.95 JPY
+        assert_eq!(
+            quote.rate().convert(Currency::<CHF>::from(1000)),
+            Currency::<JPY>::from(169838)
+        );
+    }
+
+    #[test]
+    fn test_quote_missing_leg() {
+        assert_eq!(
+            rates().quote(&date(4)),
+            Err(ConversionError::MissingExchangeRate)
+        );
+    }
+}
@@ -1,45 +1,89 @@
-use std::{fmt::Display, marker::PhantomData};
+use std::{
+    fmt::Display,
+    iter::Sum,
+    marker::PhantomData,
+    ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign},
+};
 
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    conversions::{
+        daily_exchange_rates::ConversionError,
+        decimal::{pow10, Decimal},
+        rounding::RoundingMode,
+    },
+    currency::{self, locale, CurrencyError, Locale, Money},
+};
 
 pub trait CurrencyType: core::fmt::Debug + Serialize + Copy {
-    fn formatter(raw_amount: i64) -> String;
+    /// The ISO 4217 currency this type stands for.
+    fn currency() -> currency::Currency;
+
+    fn formatter(raw_amount: i128) -> String {
+        format!(
+            "{} {}",
+            Self::currency().symbol(),
+            Decimal::new(raw_amount, Self::minor_units())
+        )
+    }
 
-    /// The factor to store the currency amount in the struct.
-    fn store_factor() -> f64;
+    /// The number of decimal places stored in the struct, e.g. 0 for JPY, 3 for KWD and 18
+    /// for ETH.
+    fn minor_units() -> u32 {
+        Self::currency().minor_units().into()
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub struct Currency<T>(i64, PhantomData<T>)
+pub struct Currency<T>(i128, PhantomData<T>)
 where
     T: CurrencyType;
 
-#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
-pub struct EUR;
+/// An operation that is generic over a currency type, so it can be called with a currency
+/// that is only known at runtime through [`dispatch`].
+pub trait CurrencyDispatch {
+    type Output;
 
-#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
-pub struct USD;
-
-impl CurrencyType for EUR {
-    fn formatter(raw_amount: i64) -> String {
-        format!("€ {:.2}", raw_amount as f64 / 100.0)
-    }
-
-    fn store_factor() -> f64 {
-        100.0
-    }
+    fn call<T>(self) -> Self::Output
+    where
+        T: CurrencyType + for<'de> Deserialize<'de> + 'static;
 }
 
-impl CurrencyType for USD {
-    fn formatter(raw_amount: i64) -> String {
-        format!("$ {:.2}", raw_amount as f64 / 100.0)
-    }
+macro_rules! currency_types {
+    ($($code:ident),*) => {
+        $(
+            #[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+            pub struct $code;
 
-    fn store_factor() -> f64 {
-        100.0
-    }
+            impl CurrencyType for $code {
+                fn currency() -> currency::Currency {
+                    currency::Currency::$code
+                }
+            }
+        )*
+
+        /// Calls `operation` with the currency type standing for `currency`, or fails if
+        /// there is no such type.
+        pub fn dispatch<F>(
+            currency: currency::Currency,
+            operation: F,
+        ) -> Result<F::Output, ConversionError>
+        where
+            F: CurrencyDispatch,
+        {
+            $(
+                if currency == currency::Currency::$code {
+                    return Ok(operation.call::<$code>());
+                }
+            )*
+            Err(ConversionError::UnsupportedCurrency(currency))
+        }
+    };
 }
 
+currency_types!(BHD, BTC, CAD, CHF, ETH, EUR, GBP, JPY, KWD, SEK, USD);
+
 impl<'de, T> Deserialize<'de> for Currency<T>
 where
     T: CurrencyType,
@@ -48,8 +92,8 @@ where
     where
         D: Deserializer<'de>,
     {
-        let amount = f64::deserialize(deserializer)?;
-        Ok(Currency::from(amount))
+        let amount = locale::deserialize_amount_for(deserializer, T::minor_units())?;
+        Currency::from_decimal_exact(amount).map_err(de::Error::custom)
     }
 }
 
@@ -61,7 +105,7 @@ where
     where
         S: Serializer,
     {
-        serializer.serialize_f64(self.0 as f64 / T::store_factor())
+        self.to_decimal().serialize(serializer)
     }
 }
 
@@ -69,21 +113,57 @@ impl<T> Currency<T>
 where
     T: CurrencyType,
 {
-    pub fn from_raw_amount(raw_amount: i64) -> Self {
+    pub fn from_raw_amount(raw_amount: i128) -> Self {
         Currency(raw_amount, PhantomData)
     }
 
-    pub fn raw_amount(&self) -> i64 {
+    pub fn raw_amount(&self) -> i128 {
         self.0
     }
+
+    /// Converts an exact decimal amount, rounding it with `mode` if it has more decimal
+    /// places than the currency.
+    pub fn from_decimal(amount: Decimal, mode: RoundingMode) -> Result<Self, CurrencyError> {
+        amount
+            .rescale(T::minor_units(), mode)
+            .map(Currency::from_raw_amount)
+            .ok_or_else(|| CurrencyError::AmountOverflow(amount.to_string()))
+    }
+
+    /// Converts an exact decimal amount, rejecting it if it has more decimal places than
+    /// the currency.
+    pub fn from_decimal_exact(amount: Decimal) -> Result<Self, CurrencyError> {
+        if amount.normalize().scale() > T::minor_units() {
+            return Err(CurrencyError::TooManyDecimals {
+                amount: amount.to_string(),
+                minor_units: T::minor_units().try_into().unwrap_or(u8::MAX),
+            });
+        }
+        Currency::from_decimal(amount, RoundingMode::default())
+    }
+
+    pub fn to_decimal(&self) -> Decimal {
+        Decimal::new(self.0, T::minor_units())
+    }
+
+    /// Formats the amount following the conventions of `locale`, e.g. `1.234,56 €`.
+    pub fn format(&self, locale: &Locale) -> String {
+        locale.format(self.to_decimal(), T::currency())
+    }
 }
 
-impl<T> From<f64> for Currency<T>
+impl<T> TryFrom<f64> for Currency<T>
 where
     T: CurrencyType,
 {
-    fn from(value: f64) -> Self {
-        Currency::from_raw_amount((value * T::store_factor()) as i64)
+    type Error = CurrencyError;
+
+    /// Converts the shortest decimal representation of `value`, so `0.29` becomes 29 cents.
+    /// Fails if `value` is not finite or out of range.
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        let amount = Decimal::from_f64(value)
+            .map_err(|_| CurrencyError::InvalidAmount(value.to_string()))?;
+        Currency::from_decimal(amount, RoundingMode::default())
     }
 }
 
@@ -91,8 +171,173 @@ impl<T> From<i64> for Currency<T>
 where
     T: CurrencyType,
 {
+    /// Panics if the amount does not fit into the minor units of the currency.
     fn from(value: i64) -> Self {
-        Currency::from_raw_amount(value * T::store_factor() as i64)
+        pow10(T::minor_units())
+            .and_then(|factor| factor.checked_mul(value.into()))
+            .map(Currency::from_raw_amount)
+            .expect("Amount out of range")
+    }
+}
+
+impl<T> Currency<T>
+where
+    T: CurrencyType,
+{
+    pub fn zero() -> Self {
+        Currency::from_raw_amount(0)
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Currency::from_raw_amount)
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Currency::from_raw_amount)
+    }
+
+    pub fn checked_neg(self) -> Option<Self> {
+        self.0.checked_neg().map(Currency::from_raw_amount)
+    }
+
+    pub fn checked_mul(self, rhs: i64) -> Option<Self> {
+        self.0
+            .checked_mul(rhs.into())
+            .map(Currency::from_raw_amount)
+    }
+
+    /// Multiplies the amount by a possibly fractional quantity, e.g. a price by a number of
+    /// shares, and rounds the result to the minor units of the currency with `mode`.
+    pub fn checked_mul_quantity(self, quantity: Decimal, mode: RoundingMode) -> Option<Self> {
+        let product = self.0.checked_mul(quantity.mantissa())?;
+        Decimal::new(product, quantity.scale())
+            .rescale(0, mode)
+            .map(Currency::from_raw_amount)
+    }
+}
+
+// The operators below panic on overflow in both debug and release builds. Amounts of
+// different currencies cannot be mixed, since `Currency<EUR> + Currency<USD>` does not
+// type check.
+
+impl<T> Add for Currency<T>
+where
+    T: CurrencyType,
+{
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        self.checked_add(rhs).expect("Currency addition overflowed")
+    }
+}
+
+impl<T> AddAssign for Currency<T>
+where
+    T: CurrencyType,
+{
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<T> Sub for Currency<T>
+where
+    T: CurrencyType,
+{
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self.checked_sub(rhs)
+            .expect("Currency subtraction overflowed")
+    }
+}
+
+impl<T> SubAssign for Currency<T>
+where
+    T: CurrencyType,
+{
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<T> Neg for Currency<T>
+where
+    T: CurrencyType,
+{
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        self.checked_neg().expect("Currency negation overflowed")
+    }
+}
+
+impl<T> Mul<i64> for Currency<T>
+where
+    T: CurrencyType,
+{
+    type Output = Self;
+
+    fn mul(self, rhs: i64) -> Self {
+        self.checked_mul(rhs)
+            .expect("Currency multiplication overflowed")
+    }
+}
+
+impl<T> Mul<Decimal> for Currency<T>
+where
+    T: CurrencyType,
+{
+    type Output = Self;
+
+    /// Rounds the product with the default [`RoundingMode`].
+    fn mul(self, rhs: Decimal) -> Self {
+        self.checked_mul_quantity(rhs, RoundingMode::default())
+            .expect("Currency multiplication overflowed")
+    }
+}
+
+impl<T> Sum for Currency<T>
+where
+    T: CurrencyType,
+{
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Currency::zero(), Add::add)
+    }
+}
+
+impl<'a, T> Sum<&'a Currency<T>> for Currency<T>
+where
+    T: CurrencyType,
+{
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+impl<T> From<Currency<T>> for Money
+where
+    T: CurrencyType,
+{
+    fn from(value: Currency<T>) -> Self {
+        Money::from_minor_units(value.0, T::currency())
+    }
+}
+
+impl<T> TryFrom<Money> for Currency<T>
+where
+    T: CurrencyType,
+{
+    type Error = CurrencyError;
+
+    fn try_from(value: Money) -> Result<Self, Self::Error> {
+        if value.currency() != T::currency() {
+            return Err(CurrencyError::CurrencyMismatch {
+                expected: T::currency(),
+                found: value.currency(),
+            });
+        }
+        Currency::from_decimal(value.to_decimal(), RoundingMode::default())
     }
 }
 
@@ -112,15 +357,61 @@ mod tests {
     #[test]
     fn test_currency() {
         let amount = 123.45;
-        let amount = Currency::<EUR>::from(amount);
+        let amount = Currency::<EUR>::try_from(amount).unwrap();
 
         assert_eq!(format!("{}", amount), "€ 123.45");
     }
 
+    #[test]
+    fn test_try_from_f64() {
+        assert_eq!(
+            Currency::<EUR>::try_from(0.29),
+            Ok(Currency::from_raw_amount(29))
+        );
+        assert!(Currency::<EUR>::try_from(f64::NAN).is_err());
+        assert!(Currency::<EUR>::try_from(f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn test_from_decimal_exact() {
+        assert_eq!(
+            Currency::<EUR>::from_decimal_exact("1.50".parse().unwrap()),
+            Ok(Currency::from_raw_amount(150))
+        );
+        assert!(matches!(
+            Currency::<EUR>::from_decimal_exact("1.005".parse().unwrap()),
+            Err(CurrencyError::TooManyDecimals { minor_units: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_serialize_round_trip() {
+        fn round_trip<T: CurrencyType>(amount: Currency<T>) -> Currency<T> {
+            let mut writer = csv::Writer::from_writer(vec![]);
+            writer.serialize([amount]).unwrap();
+            let data = writer.into_inner().unwrap();
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .from_reader(data.as_slice());
+            reader
+                .deserialize::<[Currency<T>; 1]>()
+                .next()
+                .unwrap()
+                .unwrap()[0]
+        }
+
+        for raw_amount in [1234, 12500, -1000000, 5] {
+            let kwd = Currency::<KWD>::from_raw_amount(raw_amount);
+            assert_eq!(round_trip(kwd), kwd);
+            let bhd = Currency::<BHD>::from_raw_amount(raw_amount);
+            assert_eq!(round_trip(bhd), bhd);
+        }
+    }
+
     #[test]
     fn test_currency_usd() {
         let amount = 100.00;
-        let amount = Currency::<USD>::from(amount);
+        let amount = Currency::<USD>::try_from(amount).unwrap();
 
         assert_eq!(format!("{}", amount), "$ 100.00");
     }
@@ -133,6 +424,161 @@ mod tests {
         assert_eq!(format!("{}", amount), "$ 100.00");
     }
 
+    #[test]
+    fn test_currency_jpy() {
+        let amount = Currency::<JPY>::from(1500);
+
+        assert_eq!(amount.raw_amount(), 1500);
+        assert_eq!(format!("{}", amount), "¥ 1500");
+    }
+
+    #[test]
+    fn test_currency_into_money() {
+        let amount = Currency::<GBP>::try_from(12.5).unwrap();
+
+        assert_eq!(
+            Money::from(amount),
+            Money::from_minor_units(1250, currency::Currency::GBP)
+        );
+    }
+
+    #[test]
+    fn test_currency_minor_units() {
+        assert_eq!(Currency::<KWD>::try_from(1.234).unwrap().raw_amount(), 1234);
+        assert_eq!(format!("{}", Currency::<BHD>::from(-2)), "BD -2.000");
+        assert_eq!(
+            Currency::<BTC>::try_from(0.00000001).unwrap().raw_amount(),
+            1
+        );
+        assert_eq!(
+            Currency::<ETH>::from(i64::MAX).raw_amount(),
+            i128::from(i64::MAX) * 10i128.pow(18)
+        );
+        assert_eq!(
+            format!("{}", Currency::<ETH>::try_from(1.5).unwrap()),
+            "Ξ 1.500000000000000000"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount out of range")]
+    fn test_currency_int_overflow() {
+        #[derive(Copy, Clone, Debug, Serialize)]
+        struct Tiny;
+
+        impl CurrencyType for Tiny {
+            fn currency() -> currency::Currency {
+                currency::Currency::ETH
+            }
+
+            fn minor_units() -> u32 {
+                24
+            }
+        }
+
+        let _ = Currency::<Tiny>::from(i64::MAX);
+    }
+
+    #[test]
+    fn test_currency_arithmetic() {
+        let a = Currency::<EUR>::try_from(10.25).unwrap();
+        let b = Currency::<EUR>::try_from(0.75).unwrap();
+
+        assert_eq!(a + b, Currency::from(11));
+        assert_eq!(a - b, Currency::try_from(9.5).unwrap());
+        assert_eq!(-a, Currency::try_from(-10.25).unwrap());
+        assert_eq!(b * 3, Currency::try_from(2.25).unwrap());
+
+        let mut c = a;
+        c += b;
+        c -= Currency::from(1);
+        assert_eq!(c, Currency::from(10));
+
+        assert_eq!(
+            vec![a, b, b].into_iter().sum::<Currency<EUR>>(),
+            Currency::try_from(11.75).unwrap()
+        );
+        assert_eq!([a, b].iter().sum::<Currency<EUR>>(), Currency::from(11));
+    }
+
+    #[test]
+    fn test_currency_mul_quantity() {
+        let price = Currency::<USD>::try_from(10.01).unwrap();
+
+        assert_eq!(
+            price * "2.5".parse::<Decimal>().unwrap(),
+            Currency::try_from(25.03).unwrap()
+        );
+        assert_eq!(
+            price.checked_mul_quantity("2.5".parse().unwrap(), RoundingMode::HalfEven),
+            Some(Currency::try_from(25.02).unwrap())
+        );
+        assert_eq!(
+            price.checked_mul_quantity(Decimal::new(i128::MAX, 0), RoundingMode::HalfUp),
+            None
+        );
+    }
+
+    #[test]
+    fn test_currency_checked_overflow() {
+        let max = Currency::<USD>::from_raw_amount(i128::MAX);
+
+        assert_eq!(max.checked_add(Currency::from_raw_amount(1)), None);
+        assert_eq!(
+            Currency::<USD>::from_raw_amount(i128::MIN).checked_neg(),
+            None
+        );
+        assert_eq!(max.checked_mul(2), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Currency addition overflowed")]
+    fn test_currency_add_overflow() {
+        let _ = Currency::<USD>::from_raw_amount(i128::MAX) + Currency::from_raw_amount(1);
+    }
+
+    #[test]
+    fn test_currency_try_from_money() {
+        let money = Money::from_minor_units(1250, currency::Currency::GBP);
+
+        assert_eq!(
+            Currency::<GBP>::try_from(money),
+            Ok(Currency::try_from(12.5).unwrap())
+        );
+        assert_eq!(
+            Currency::<EUR>::try_from(money),
+            Err(CurrencyError::CurrencyMismatch {
+                expected: currency::Currency::EUR,
+                found: currency::Currency::GBP
+            })
+        );
+    }
+
+    #[test]
+    fn test_dispatch() {
+        struct MinorUnits;
+
+        impl CurrencyDispatch for MinorUnits {
+            type Output = u32;
+
+            fn call<T>(self) -> u32
+            where
+                T: CurrencyType + for<'de> Deserialize<'de> + 'static,
+            {
+                T::minor_units()
+            }
+        }
+
+        assert_eq!(dispatch(currency::Currency::JPY, MinorUnits), Ok(0));
+        assert_eq!(dispatch(currency::Currency::CHF, MinorUnits), Ok(2));
+        assert_eq!(
+            dispatch(currency::Currency::NOK, MinorUnits),
+            Err(ConversionError::UnsupportedCurrency(
+                currency::Currency::NOK
+            ))
+        );
+    }
+
     #[test]
     fn test_currency_deserialize() {
         let csv = "amount\n123.45";
@@ -155,9 +601,58 @@ mod tests {
         assert_eq!(format!("{}", amount), "$ 100.00");
     }
 
+    #[test]
+    fn test_currency_exact_decimal() {
+        assert_eq!(Currency::<EUR>::try_from(0.29).unwrap().raw_amount(), 29);
+        assert_eq!(Currency::<EUR>::try_from(1.005).unwrap().raw_amount(), 101);
+        assert_eq!(
+            Currency::<EUR>::from_decimal("1.005".parse().unwrap(), RoundingMode::HalfEven),
+            Ok(Currency::from_raw_amount(100))
+        );
+        assert_eq!(
+            Currency::<EUR>::from_decimal("-1.009".parse().unwrap(), RoundingMode::TowardZero),
+            Ok(Currency::from_raw_amount(-100))
+        );
+    }
+
+    #[test]
+    fn test_currency_deserialize_exact() {
+        let csv = "amount\n0.29";
+        let amount: Currency<EUR> = csv::Reader::from_reader(csv.as_bytes())
+            .deserialize()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(amount.raw_amount(), 29);
+    }
+
+    #[test]
+    fn test_currency_deserialize_localized() {
+        let csv = "amount\n\"$1,234.56\"\n\"1.234,56\"\n(12.50)";
+        let amounts: Vec<Currency<USD>> = csv::Reader::from_reader(csv.as_bytes())
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            amounts,
+            vec![
+                Currency::try_from(1234.56).unwrap(),
+                Currency::try_from(1234.56).unwrap(),
+                Currency::try_from(-12.5).unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_currency_format() {
+        let amount = Currency::<EUR>::try_from(1234.5).unwrap();
+        assert_eq!(amount.format(&Locale::DE_DE), "1.234,50 €");
+        assert_eq!(amount.format(&Locale::EN_GB), "€1,234.50");
+    }
+
     #[test]
     fn test_currency_serialize() {
-        let amount = Currency::<EUR>::from(123.45);
+        let amount = Currency::<EUR>::try_from(123.45).unwrap();
         let mut wtr = csv::Writer::from_writer(vec![]);
         wtr.serialize(amount).unwrap();
         assert_eq!(
@@ -165,4 +660,16 @@ mod tests {
             "123.45\n"
         );
     }
+
+    #[test]
+    fn test_currency_serialize_exact() {
+        let mut wtr = csv::Writer::from_writer(vec![]);
+        wtr.serialize(Currency::<USD>::from(100)).unwrap();
+        wtr.serialize(Currency::<JPY>::try_from(0.29 * 100.0).unwrap())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(wtr.into_inner().unwrap()).unwrap(),
+            "100.00\n29\n"
+        );
+    }
 }
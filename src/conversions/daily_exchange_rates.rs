@@ -1,24 +1,42 @@
-use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::Display;
+use std::ops::RangeBounds;
+use std::sync::Arc;
 
 use chrono::NaiveDate;
 use csv::Reader;
 use serde::{Deserialize, Serialize};
 
 use crate::conversions::currency::CurrencyType;
+use crate::conversions::date_map::DateMap;
 use crate::conversions::exchange_rate::ExchangeRate;
-use crate::conversions::transaction::Transaction;
+use crate::conversions::fallback::Fallback;
+use crate::conversions::provenance::{Provenance, RateOrigin};
+use crate::conversions::rate_source::{RateQuote, RateSource};
+use crate::conversions::validation::{
+    read_rows, reject_errors, RateChecks, RateIssue, ValidationError,
+};
+use crate::currency;
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub enum ConversionError {
     MissingExchangeRate,
+    UnsupportedCurrency(currency::Currency),
+    AmountOverflow,
+    InvalidAmount(String),
+    ZeroExchangeRate,
 }
 
 impl Display for ConversionError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ConversionError::MissingExchangeRate => write!(f, "Missing exchange rate"),
+            ConversionError::UnsupportedCurrency(currency) => {
+                write!(f, "Conversions from {} are not supported", currency)
+            }
+            ConversionError::AmountOverflow => write!(f, "Amount out of range"),
+            ConversionError::InvalidAmount(amount) => write!(f, "Invalid amount {}", amount),
+            ConversionError::ZeroExchangeRate => write!(f, "Exchange rate is zero"),
         }
     }
 }
@@ -31,17 +49,9 @@ where
     N: CurrencyType,
     D: CurrencyType,
 {
-    rates: HashMap<NaiveDate, ExchangeRate<N, D>>,
-}
-
-#[derive(Debug, Deserialize)]
-struct DayRate<N, D>
-where
-    N: CurrencyType,
-    D: CurrencyType,
-{
-    date: NaiveDate,
-    rate: ExchangeRate<N, D>,
+    rates: DateMap<ExchangeRate<N, D>>,
+    provenance: DateMap<Provenance>,
+    fallback: Fallback,
 }
 
 impl<N, D> DailyExchangeRates<N, D>
@@ -49,38 +59,171 @@ where
     N: CurrencyType + for<'de> Deserialize<'de>,
     D: CurrencyType + for<'de> Deserialize<'de>,
 {
+    pub fn new(rates: impl IntoIterator<Item = (NaiveDate, ExchangeRate<N, D>)>) -> Self {
+        DailyExchangeRates {
+            rates: rates.into_iter().collect(),
+            provenance: DateMap::new(),
+            fallback: Fallback::default(),
+        }
+    }
+
+    /// Records where the rates of the given dates came from.
+    pub fn with_provenance(
+        mut self,
+        provenance: impl IntoIterator<Item = (NaiveDate, Provenance)>,
+    ) -> Self {
+        for (date, provenance) in provenance {
+            self.provenance.insert(date, provenance);
+        }
+        self
+    }
+
+    /// Records that all rates came from `origin`, keeping the lines they were read from.
+    pub fn with_origin(mut self, origin: RateOrigin) -> Self {
+        let origin = Arc::new(origin);
+        self.provenance = self
+            .rates
+            .iter()
+            .map(|(date, _)| {
+                let provenance = match self.provenance.get(date) {
+                    Some(provenance) => provenance.with_origin(origin.clone()),
+                    None => Provenance::new(origin.clone(), None),
+                };
+                (*date, provenance)
+            })
+            .collect();
+        self
+    }
+
+    /// Uses `fallback` for dates without a rate, instead of only the rate of the date itself.
+    pub fn with_fallback(self, fallback: Fallback) -> Self {
+        DailyExchangeRates { fallback, ..self }
+    }
+
+    /// The rate published for `date`, without falling back to other days.
     pub fn day_rate(&self, date: &NaiveDate) -> Option<&ExchangeRate<N, D>> {
-        self.rates.get(&date)
+        self.rates.get(date)
+    }
+
+    /// Where the rate published for `date` came from, if known.
+    pub fn provenance(&self, date: &NaiveDate) -> Option<&Provenance> {
+        self.provenance.get(date)
+    }
+
+    /// All rates in date order.
+    pub fn rates(&self) -> impl Iterator<Item = (&NaiveDate, &ExchangeRate<N, D>)> {
+        self.rates.iter()
     }
 
-    pub fn convert(&self, transaction: &Transaction<D>) -> Result<Transaction<N>, ConversionError> {
-        let rate = self
-            .day_rate(transaction.date())
-            .ok_or(ConversionError::MissingExchangeRate)?;
+    /// The rates of the dates in `range`, in date order.
+    pub fn range(
+        &self,
+        range: impl RangeBounds<NaiveDate>,
+    ) -> impl Iterator<Item = (&NaiveDate, &ExchangeRate<N, D>)> {
+        self.rates.range(range)
+    }
+
+    /// The rates by date, e.g. to find the first and last date or the gaps in between.
+    pub fn by_date(&self) -> &DateMap<ExchangeRate<N, D>> {
+        &self.rates
+    }
 
-        Ok(Transaction::new(
-            *transaction.date(),
-            rate.convert(transaction.amount()),
-        ))
+    /// The same rates quoted the other way round, or `None` if any rate is zero.
+    pub fn checked_invert(&self) -> Option<DailyExchangeRates<D, N>> {
+        Some(DailyExchangeRates {
+            rates: self.rates.try_map(ExchangeRate::checked_invert)?,
+            provenance: self.provenance.clone(),
+            fallback: self.fallback.clone(),
+        })
     }
 
-    pub fn read_from_csv<R>(mut reader: Reader<R>) -> Result<Self, csv::Error>
+    /// Reads a CSV file with `date` and `rate` columns, checking it with the default
+    /// [`RateChecks`].
+    pub fn read_from_csv<R>(reader: Reader<R>) -> Result<Self, ValidationError>
     where
         R: std::io::Read,
     {
+        Self::read_checked(reader, &RateChecks::default())
+    }
+
+    /// Reads a CSV file with `date` and `rate` columns. Fails with all errors that `checks`
+    /// finds, and logs the suspicious rates as warnings.
+    pub fn read_checked<R>(reader: Reader<R>, checks: &RateChecks) -> Result<Self, ValidationError>
+    where
+        R: std::io::Read,
+    {
+        let rows = read_rows(reader)?;
+        reject_errors(checks.check(&rows))?;
+
+        let mut rates = DateMap::new();
+        let mut provenance = DateMap::new();
+        for row in rows {
+            let rate = row
+                .rate
+                .parse()
+                .ok()
+                .and_then(ExchangeRate::from_decimal)
+                .ok_or_else(|| {
+                    ValidationError::Invalid(vec![RateIssue::InvalidRate {
+                        line: row.line,
+                        rate: row.rate.clone(),
+                    }])
+                })?;
+            rates.insert(row.date, rate);
+            provenance.insert(row.date, Provenance::new(Arc::default(), Some(row.line)));
+        }
+
         Ok(DailyExchangeRates {
-            rates: reader
-                .deserialize()
-                .map(|result| result.map(|day_rate: DayRate<N, D>| (day_rate.date, day_rate.rate)))
-                .collect::<Result<_, _>>()?,
+            rates,
+            provenance,
+            fallback: Fallback::default(),
         })
     }
 }
 
+impl<N, D> RateSource<N, D> for DailyExchangeRates<N, D>
+where
+    N: CurrencyType + for<'de> Deserialize<'de>,
+    D: CurrencyType + for<'de> Deserialize<'de>,
+{
+    fn quote(&self, date: &NaiveDate) -> Result<RateQuote<N, D>, ConversionError> {
+        let quote = self
+            .fallback
+            .find(date, &self.rates)
+            .ok_or(ConversionError::MissingExchangeRate)?
+            .copied()
+            .into_quote(date)
+            .ok_or(ConversionError::AmountOverflow)?;
+        let provenance = quote
+            .dates()
+            .iter()
+            .filter_map(|date| self.provenance.get(date))
+            .cloned()
+            .collect();
+        Ok(quote.with_provenance(provenance))
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use super::*;
     use crate::conversions::currency::{Currency, EUR, USD};
+    use crate::conversions::fallback::FallbackPolicy;
+    use crate::conversions::transaction::Transaction;
+
+    #[test]
+    fn test_read_invalid_rates() {
+        let csv = "date,rate\n2024-05-02,1.0702\n2024-05-03,-1.0773\n2024-05-02,1.0710\n";
+        let result =
+            DailyExchangeRates::<USD, EUR>::read_from_csv(Reader::from_reader(csv.as_bytes()));
+
+        match result {
+            Err(ValidationError::Invalid(issues)) => assert_eq!(issues.len(), 2),
+            other => panic!("Expected invalid rates, got {:?}", other),
+        }
+    }
 
     #[test]
     fn test_daily_exchange_rates() {
@@ -93,7 +236,7 @@ mod tests {
             NaiveDate::from_ymd_opt(2021, 1, 2).unwrap(),
             ExchangeRate::new(0.9),
         );
-        let daily_rates = DailyExchangeRates { rates };
+        let daily_rates = DailyExchangeRates::new(rates);
 
         assert_eq!(
             daily_rates.day_rate(&NaiveDate::from_ymd_opt(2021, 1, 1).unwrap()),
@@ -109,6 +252,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_range() {
+        let date = |day| NaiveDate::from_ymd_opt(2021, 1, day).unwrap();
+        let daily_rates = DailyExchangeRates::<EUR, USD>::new([
+            (date(4), ExchangeRate::new(0.84)),
+            (date(1), ExchangeRate::new(0.8)),
+            (date(2), ExchangeRate::new(0.82)),
+        ]);
+
+        assert_eq!(
+            daily_rates
+                .range(date(2)..)
+                .map(|(date, _)| *date)
+                .collect::<Vec<_>>(),
+            vec![date(2), date(4)]
+        );
+        assert_eq!(daily_rates.by_date().gaps().len(), 1);
+    }
+
     #[test]
     fn test_daily_rates_convert() {
         let mut rates: HashMap<NaiveDate, ExchangeRate<EUR, USD>> = HashMap::new();
@@ -120,7 +282,7 @@ mod tests {
             NaiveDate::from_ymd_opt(2021, 1, 3).unwrap(),
             ExchangeRate::new(0.9),
         );
-        let daily_rates = DailyExchangeRates { rates };
+        let daily_rates = DailyExchangeRates::new(rates);
 
         let date = NaiveDate::from_ymd_opt(2021, 1, 1).unwrap();
         let amount = Currency::<USD>::from(100);
@@ -174,7 +336,86 @@ mod tests {
                 ]
                 .into_iter()
                 .collect(),
+                provenance: [(1, 2), (2, 3)]
+                    .into_iter()
+                    .map(|(day, line)| (
+                        NaiveDate::from_ymd_opt(2021, 1, day).unwrap(),
+                        Provenance::new(Arc::default(), Some(line))
+                    ))
+                    .collect(),
+                fallback: Fallback::default(),
             }
         );
     }
+
+    #[test]
+    fn test_checked_invert() {
+        let csv = "date,rate\n2021-01-01,1.25\n";
+        let daily_rates: DailyExchangeRates<USD, EUR> =
+            DailyExchangeRates::read_from_csv(Reader::from_reader(csv.as_bytes())).unwrap();
+
+        assert_eq!(
+            daily_rates
+                .checked_invert()
+                .unwrap()
+                .day_rate(&NaiveDate::from_ymd_opt(2021, 1, 1).unwrap()),
+            Some(&ExchangeRate::new(0.8))
+        );
+    }
+
+    #[test]
+    fn test_convert_with_fallback() {
+        let csv = "date,rate\n2024-03-28,1.08\n2024-04-02,1.11\n";
+        let daily_rates: DailyExchangeRates<USD, EUR> =
+            DailyExchangeRates::read_from_csv(Reader::from_reader(csv.as_bytes())).unwrap();
+        let easter_sunday = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        let transaction = Transaction::new(easter_sunday, Currency::<EUR>::from(100));
+
+        assert_eq!(
+            daily_rates.convert(&transaction),
+            Err(ConversionError::MissingExchangeRate)
+        );
+
+        let daily_rates = daily_rates.with_fallback(Fallback::new(FallbackPolicy::Previous, 5));
+        assert_eq!(
+            daily_rates.convert(&transaction),
+            Ok(Transaction::new(easter_sunday, Currency::<USD>::from(108)))
+        );
+        assert_eq!(
+            daily_rates.quote(&easter_sunday).unwrap().dates(),
+            &[NaiveDate::from_ymd_opt(2024, 3, 28).unwrap()]
+        );
+
+        let daily_rates = daily_rates.with_fallback(Fallback::new(FallbackPolicy::Interpolate, 5));
+        let quote = daily_rates.quote(&easter_sunday).unwrap();
+        assert_eq!(quote.rate(), &ExchangeRate::new(1.098));
+        assert_eq!(
+            quote.dates(),
+            &[
+                NaiveDate::from_ymd_opt(2024, 3, 28).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 4, 2).unwrap()
+            ]
+        );
+
+        let daily_rates = daily_rates.with_origin(RateOrigin {
+            file: Some("usd.csv".to_string()),
+            ..RateOrigin::default()
+        });
+        assert_eq!(
+            daily_rates
+                .quote(&easter_sunday)
+                .unwrap()
+                .provenance()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["usd.csv:2", "usd.csv:3"]
+        );
+
+        let daily_rates = daily_rates.with_fallback(Fallback::new(FallbackPolicy::Previous, 2));
+        assert_eq!(
+            daily_rates.convert(&transaction),
+            Err(ConversionError::MissingExchangeRate)
+        );
+    }
 }
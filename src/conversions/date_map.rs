@@ -0,0 +1,225 @@
+use std::{
+    collections::{btree_map, BTreeMap},
+    ops::{Bound, RangeBounds},
+};
+
+use chrono::{Days, NaiveDate};
+
+/// Values indexed by date and kept in date order, such as the rates of a pair. Lookups,
+/// range queries and finding the nearest date before or after another take logarithmic time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DateMap<T> {
+    values: BTreeMap<NaiveDate, T>,
+}
+
+/// The calendar days from `first` to `last`, both included, that have no value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Gap {
+    pub first: NaiveDate,
+    pub last: NaiveDate,
+}
+
+impl Gap {
+    pub fn days(&self) -> i64 {
+        (self.last - self.first).num_days() + 1
+    }
+}
+
+impl<T> Default for DateMap<T> {
+    fn default() -> Self {
+        DateMap {
+            values: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T> DateMap<T> {
+    pub fn new() -> Self {
+        DateMap::default()
+    }
+
+    /// Sets the value of `date`, returning the one it replaces.
+    pub fn insert(&mut self, date: NaiveDate, value: T) -> Option<T> {
+        self.values.insert(date, value)
+    }
+
+    pub fn get(&self, date: &NaiveDate) -> Option<&T> {
+        self.values.get(date)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// All values in date order.
+    pub fn iter(&self) -> btree_map::Iter<'_, NaiveDate, T> {
+        self.values.iter()
+    }
+
+    /// The values of the dates in `range`, in date order. A range that ends before it
+    /// starts is empty.
+    pub fn range<R>(&self, range: R) -> btree_map::Range<'_, NaiveDate, T>
+    where
+        R: RangeBounds<NaiveDate>,
+    {
+        let start = range.start_bound().cloned();
+        let end = range.end_bound().cloned();
+        match (start, end) {
+            (Bound::Included(first) | Bound::Excluded(first), Bound::Included(last))
+                if first > last =>
+            {
+                self.values.range(first..first)
+            }
+            (Bound::Included(first) | Bound::Excluded(first), Bound::Excluded(last))
+                if first >= last =>
+            {
+                self.values.range(first..first)
+            }
+            _ => self.values.range((start, end)),
+        }
+    }
+
+    pub fn first(&self) -> Option<(&NaiveDate, &T)> {
+        self.values.first_key_value()
+    }
+
+    pub fn last(&self) -> Option<(&NaiveDate, &T)> {
+        self.values.last_key_value()
+    }
+
+    /// The value of the latest date before `date`.
+    pub fn before(&self, date: &NaiveDate) -> Option<(&NaiveDate, &T)> {
+        self.values.range(..date).next_back()
+    }
+
+    /// The value of the earliest date after `date`.
+    pub fn after(&self, date: &NaiveDate) -> Option<(&NaiveDate, &T)> {
+        self.values
+            .range((Bound::Excluded(date), Bound::Unbounded))
+            .next()
+    }
+
+    /// The runs of calendar days without a value between the first and the last date.
+    pub fn gaps(&self) -> Vec<Gap> {
+        self.values
+            .keys()
+            .zip(self.values.keys().skip(1))
+            .filter(|(before, after)| (**after - **before).num_days() > 1)
+            .filter_map(|(before, after)| {
+                Some(Gap {
+                    first: before.checked_add_days(Days::new(1))?,
+                    last: after.checked_sub_days(Days::new(1))?,
+                })
+            })
+            .collect()
+    }
+
+    /// The same dates with `f` applied to their values, or `None` if `f` fails for any.
+    pub fn try_map<U>(&self, f: impl Fn(&T) -> Option<U>) -> Option<DateMap<U>> {
+        Some(DateMap {
+            values: self
+                .values
+                .iter()
+                .map(|(date, value)| Some((*date, f(value)?)))
+                .collect::<Option<_>>()?,
+        })
+    }
+}
+
+impl<T> FromIterator<(NaiveDate, T)> for DateMap<T> {
+    /// Collects values in any order. Of several values for the same date, the last is kept.
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (NaiveDate, T)>,
+    {
+        DateMap {
+            values: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<T> IntoIterator for DateMap<T> {
+    type Item = (NaiveDate, T);
+    type IntoIter = btree_map::IntoIter<NaiveDate, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a DateMap<T> {
+    type Item = (&'a NaiveDate, &'a T);
+    type IntoIter = btree_map::Iter<'a, NaiveDate, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_lookups() {
+        let map: DateMap<u32> = [
+            (date(2024, 5, 6), 6),
+            (date(2024, 5, 2), 2),
+            (date(2024, 5, 3), 3),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(map.first(), Some((&date(2024, 5, 2), &2)));
+        assert_eq!(map.last(), Some((&date(2024, 5, 6), &6)));
+        assert_eq!(map.before(&date(2024, 5, 6)), Some((&date(2024, 5, 3), &3)));
+        assert_eq!(map.after(&date(2024, 5, 3)), Some((&date(2024, 5, 6), &6)));
+        assert_eq!(map.before(&date(2024, 5, 2)), None);
+        assert_eq!(
+            map.range(date(2024, 5, 3)..=date(2024, 5, 31))
+                .map(|(_, value)| *value)
+                .collect::<Vec<_>>(),
+            vec![3, 6]
+        );
+        assert_eq!(map.range(date(2024, 5, 31)..=date(2024, 5, 3)).count(), 0);
+        assert_eq!(
+            map.range((
+                Bound::Excluded(date(2024, 5, 3)),
+                Bound::Excluded(date(2024, 5, 3))
+            ))
+            .count(),
+            0
+        );
+        assert_eq!(
+            map.gaps(),
+            vec![Gap {
+                first: date(2024, 5, 4),
+                last: date(2024, 5, 5),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_decades_of_rates() {
+        let first = date(1999, 1, 4);
+        let map: DateMap<u64> = (0..365 * 30)
+            .filter(|day| day % 7 < 5)
+            .map(|day| (first + Days::new(day), day))
+            .collect();
+
+        assert_eq!(map.gaps().len(), 30 * 365 / 7);
+        assert!(map.gaps().iter().all(|gap| gap.days() == 2));
+        assert_eq!(
+            map.before(&date(2024, 5, 5)).map(|(date, _)| *date),
+            Some(date(2024, 5, 3))
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use std::{
+    cmp::Ordering,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::conversions::rounding::RoundingMode;
+
+/// An exact decimal number, `mantissa * 10^-scale`.
+///
+/// Decimals keep the number of decimal places they were written with, so `"1.50"` is
+/// displayed as `1.50` again, but compares equal to `1.5`.
+#[derive(Clone, Copy, Debug)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseDecimalError {
+    text: String,
+    /// Whether the text is a valid number in some locales but it is unclear which.
+    ambiguous: bool,
+}
+
+impl ParseDecimalError {
+    pub(crate) fn new(text: &str) -> Self {
+        ParseDecimalError {
+            text: text.to_string(),
+            ambiguous: false,
+        }
+    }
+
+    pub(crate) fn ambiguous(text: &str) -> Self {
+        ParseDecimalError {
+            text: text.to_string(),
+            ambiguous: true,
+        }
+    }
+
+    pub fn is_ambiguous(&self) -> bool {
+        self.ambiguous
+    }
+}
+
+impl Display for ParseDecimalError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.ambiguous {
+            write!(
+                f,
+                "Ambiguous amount {}: the separator may be a decimal or a thousands separator, \
+                 set the locale",
+                self.text
+            )
+        } else {
+            write!(f, "Invalid decimal number: {}", self.text)
+        }
+    }
+}
+
+impl Error for ParseDecimalError {}
+
+/// `10^exponent`, or `None` if it does not fit into an `i128`.
+pub(crate) fn pow10(exponent: u32) -> Option<i128> {
+    10i128.checked_pow(exponent)
+}
+
+impl Decimal {
+    pub fn new(mantissa: i128, scale: u32) -> Self {
+        Decimal { mantissa, scale }
+    }
+
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    /// The number of digits after the decimal point.
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Converts a float using its shortest decimal representation, e.g. `0.29` becomes
+    /// `0.29` rather than `0.28999999999999998`.
+    pub fn from_f64(value: f64) -> Result<Self, ParseDecimalError> {
+        value.to_string().parse()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().expect("Decimals are valid floats")
+    }
+
+    /// The mantissa of this number at another scale, rounded with `mode` if digits are
+    /// dropped. Returns `None` on overflow.
+    pub fn rescale(&self, scale: u32, mode: RoundingMode) -> Option<i128> {
+        match scale.cmp(&self.scale) {
+            Ordering::Equal => Some(self.mantissa),
+            Ordering::Greater => self.mantissa.checked_mul(pow10(scale - self.scale)?),
+            Ordering::Less => match pow10(self.scale - scale) {
+                Some(divisor) => mode.checked_divide(self.mantissa, divisor),
+                None => Some(0),
+            },
+        }
+    }
+
+    /// The same number without trailing zeros after the decimal point.
+    pub fn normalize(&self) -> Self {
+        let mut normalized = *self;
+        while normalized.scale > 0 && normalized.mantissa % 10 == 0 {
+            normalized.mantissa /= 10;
+            normalized.scale -= 1;
+        }
+        normalized
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        let (left, right) = (self.normalize(), other.normalize());
+        left.mantissa == right.mantissa && left.scale == right.scale
+    }
+}
+
+impl Eq for Decimal {}
+
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseDecimalError::new(s);
+
+        let trimmed = s.trim();
+        let (negative, digits) = match trimmed.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+        if integer.is_empty() && fraction.is_empty()
+            || !integer
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let mantissa = format!("0{}{}", integer, fraction)
+            .parse::<i128>()
+            .map_err(|_| invalid())?;
+
+        Ok(Decimal::new(
+            if negative { -mantissa } else { mantissa },
+            fraction.len() as u32,
+        ))
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let digits = format!(
+            "{:0>width$}",
+            self.mantissa.unsigned_abs(),
+            width = self.scale as usize + 1
+        );
+        let (integer, fraction) = digits.split_at(digits.len() - self.scale as usize);
+
+        if fraction.is_empty() {
+            write!(f, "{}{}", sign, integer)
+        } else {
+            write!(f, "{}{}.{}", sign, integer, fraction)
+        }
+    }
+}
+
+/// Deserializes a decimal from a string that is parsed with `parse`.
+pub(crate) fn deserialize_with<'de, D>(
+    deserializer: D,
+    parse: impl Fn(&str) -> Result<Decimal, ParseDecimalError>,
+) -> Result<Decimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct DecimalVisitor<F>(F);
+
+    impl<F> de::Visitor<'_> for DecimalVisitor<F>
+    where
+        F: Fn(&str) -> Result<Decimal, ParseDecimalError>,
+    {
+        type Value = Decimal;
+
+        fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+            write!(f, "a decimal number")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Decimal, E> {
+            (self.0)(value).map_err(E::custom)
+        }
+    }
+
+    deserializer.deserialize_str(DecimalVisitor(parse))
+}
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D>(deserializer: D) -> Result<Decimal, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_with(deserializer, str::parse)
+    }
+}
+
+impl Serialize for Decimal {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        for text in ["0.29", "-1234.50", "100", "0.0001", "-0.5"] {
+            assert_eq!(text.parse::<Decimal>().unwrap().to_string(), text);
+        }
+        assert_eq!(".5".parse(), Ok(Decimal::new(5, 1)));
+        assert_eq!("+7".parse(), Ok(Decimal::new(7, 0)));
+        assert!("1.2.3".parse::<Decimal>().is_err());
+        assert!("abc".parse::<Decimal>().is_err());
+        assert!("-".parse::<Decimal>().is_err());
+    }
+
+    #[test]
+    fn test_eq_ignores_trailing_zeros() {
+        assert_eq!(Decimal::new(150, 2), Decimal::new(15, 1));
+        assert_ne!(Decimal::new(150, 2), Decimal::new(15, 2));
+    }
+
+    #[test]
+    fn test_from_f64() {
+        assert_eq!(Decimal::from_f64(0.29), Ok(Decimal::new(29, 2)));
+        assert_eq!(Decimal::from_f64(123.45), Ok(Decimal::new(12345, 2)));
+    }
+
+    #[test]
+    fn test_rescale() {
+        let decimal = Decimal::new(12345, 3);
+        assert_eq!(decimal.rescale(3, RoundingMode::HalfUp), Some(12345));
+        assert_eq!(decimal.rescale(5, RoundingMode::HalfUp), Some(1234500));
+        assert_eq!(decimal.rescale(2, RoundingMode::HalfUp), Some(1235));
+        assert_eq!(decimal.rescale(2, RoundingMode::HalfEven), Some(1234));
+        assert_eq!(decimal.rescale(2, RoundingMode::TowardZero), Some(1234));
+        assert_eq!(
+            Decimal::new(i128::MAX, 0).rescale(1, RoundingMode::HalfUp),
+            None
+        );
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let csv = "amount\n0.29\n";
+        let amount: Decimal = csv::Reader::from_reader(csv.as_bytes())
+            .deserialize()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(amount, Decimal::new(29, 2));
+    }
+}
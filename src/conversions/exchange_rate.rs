@@ -1,44 +1,289 @@
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::{de, ser, Deserialize, Deserializer, Serialize};
 
-use crate::conversions::currency::{Currency, CurrencyType};
+use crate::conversions::{
+    currency::{Currency, CurrencyType},
+    decimal::{pow10, Decimal},
+    rounding::RoundingMode,
+};
 use std::marker::PhantomData;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct ExchangeRate<N, D>(
-    f64,
-    std::marker::PhantomData<N>,
-    std::marker::PhantomData<D>,
-)
+/// The number of decimal places that rates without an exact decimal representation, such
+/// as inverted ones, are written with. The last place is rounded half to even.
+pub const DERIVED_RATE_DECIMALS: u32 = 12;
+
+/// The price of one unit of `D` in `N`, e.g. `ExchangeRate::<EUR, USD>::new(0.9)` converts
+/// 1 USD to 0.90 EUR. Rates are given in whole units, regardless of how many minor units
+/// either currency has.
+///
+/// Rates are kept as the exact fraction `numerator / denominator`. A rate read as `1.0800`
+/// is stored as `10800 / 10000` and written back as `1.0800`, and inverting it swaps the
+/// two, so no precision is lost until an amount is converted.
+#[derive(Clone, Copy, Debug)]
+pub struct ExchangeRate<N, D>
 where
     N: CurrencyType,
-    D: CurrencyType;
+    D: CurrencyType,
+{
+    numerator: i128,
+    denominator: i128,
+    phantom: PhantomData<(N, D)>,
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a as i128
+}
 
 impl<N, D> ExchangeRate<N, D>
 where
     N: CurrencyType,
     D: CurrencyType,
 {
+    /// Takes the rate at the shortest decimal representation of `rate`.
+    ///
+    /// Panics if `rate` is not a finite, positive number.
     pub fn new(rate: f64) -> Self {
-        ExchangeRate(
-            rate * N::store_factor() / D::store_factor(),
-            PhantomData,
-            PhantomData,
+        Decimal::from_f64(rate)
+            .ok()
+            .and_then(Self::from_decimal)
+            .expect("Exchange rate must be a finite, positive number")
+    }
+
+    /// Takes the rate at the precision it was published with. Returns `None` if it is not
+    /// positive or has more decimal places than fit into an `i128`.
+    pub fn from_decimal(rate: Decimal) -> Option<Self> {
+        Self::from_ratio(rate.mantissa(), pow10(rate.scale())?)
+    }
+
+    /// The exact rate `numerator / denominator`, e.g. a cross rate from the rates of two
+    /// currencies against a common base. Returns `None` if the quotient is not positive or
+    /// on overflow. Dividing by one keeps the published precision of `numerator`.
+    pub fn from_quotient(numerator: Decimal, denominator: Decimal) -> Option<Self> {
+        if denominator.mantissa() == 0 {
+            return None;
+        }
+
+        Self::from_ratio(
+            numerator
+                .mantissa()
+                .checked_mul(pow10(denominator.scale())?)?,
+            denominator
+                .mantissa()
+                .checked_mul(pow10(numerator.scale())?)?,
         )
     }
 
+    /// The rate `numerator / denominator`, or `None` unless it is positive. An amount
+    /// converted with a zero or negative rate is never what was meant, and a zero rate has
+    /// no inverse.
+    pub(crate) fn from_ratio(numerator: i128, denominator: i128) -> Option<Self> {
+        if numerator == 0 || denominator == 0 || (numerator < 0) != (denominator < 0) {
+            return None;
+        }
+        let sign = denominator.signum();
+        Some(ExchangeRate {
+            numerator: numerator.checked_mul(sign)?,
+            denominator: denominator.checked_mul(sign)?,
+            phantom: PhantomData,
+        })
+    }
+
+    /// The rate as a float, for display and statistics only.
     pub fn rate(&self) -> f64 {
-        self.0 * D::store_factor() / N::store_factor()
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// The rate as a decimal number. Rates that were read or created from a decimal come
+    /// back exactly as they were given. Other rates have [`DERIVED_RATE_DECIMALS`] decimal
+    /// places, rounded half to even, without trailing zeros. Returns `None` on overflow.
+    pub fn to_decimal(&self) -> Option<Decimal> {
+        if let Some(scale) = (0..=38).find(|&scale| pow10(scale) == Some(self.denominator)) {
+            return Some(Decimal::new(self.numerator, scale));
+        }
+
+        let divisor = gcd(self.numerator, self.denominator);
+        let numerator = (self.numerator / divisor).checked_mul(pow10(DERIVED_RATE_DECIMALS)?)?;
+        let mantissa =
+            RoundingMode::HalfEven.checked_divide(numerator, self.denominator / divisor)?;
+        Some(Decimal::new(mantissa, DERIVED_RATE_DECIMALS).normalize())
     }
 
+    /// The exact reciprocal of this rate, without rounding.
     pub fn invert(&self) -> ExchangeRate<D, N> {
-        ExchangeRate(1.0 / self.0, PhantomData, PhantomData)
+        self.checked_invert().expect("Exchange rates are positive")
+    }
+
+    /// The exact reciprocal of this rate. Rates are positive, so there always is one.
+    pub fn checked_invert(&self) -> Option<ExchangeRate<D, N>> {
+        ExchangeRate::from_ratio(self.denominator, self.numerator)
+    }
+
+    /// The rate from `C` to `N` via `D`, i.e. the exact product of both rates. For example,
+    /// the USD rate of EUR composed with the EUR rate of GBP gives the USD rate of GBP.
+    ///
+    /// Panics on overflow.
+    pub fn compose<C>(&self, other: &ExchangeRate<D, C>) -> ExchangeRate<N, C>
+    where
+        C: CurrencyType,
+    {
+        self.checked_compose(other)
+            .expect("Exchange rate composition overflowed")
+    }
+
+    /// Composes two rates like [`compose`](Self::compose), or returns `None` on overflow.
+    /// The fractions are only reduced if multiplying them as given would overflow, so the
+    /// product of two published decimal rates is still a decimal with all their places.
+    pub fn checked_compose<C>(&self, other: &ExchangeRate<D, C>) -> Option<ExchangeRate<N, C>>
+    where
+        C: CurrencyType,
+    {
+        let multiply = |a: i128, b: i128, c: i128, d: i128| {
+            ExchangeRate::from_ratio(a.checked_mul(c)?, b.checked_mul(d)?)
+        };
+
+        multiply(
+            self.numerator,
+            self.denominator,
+            other.numerator,
+            other.denominator,
+        )
+        .or_else(|| {
+            let ((a, b), (c, d)) = (self.reduced(), other.reduced());
+            let (ad, cb) = (gcd(a, d), gcd(c, b));
+            multiply(a / ad, b / cb, c / cb, d / ad)
+        })
+    }
+
+    /// The exact average of two rates, e.g. the mid rate between a bid and an ask rate.
+    /// Returns `None` on overflow.
+    pub fn checked_midpoint(&self, other: &Self) -> Option<Self> {
+        if self.denominator == other.denominator {
+            return ExchangeRate::from_ratio(
+                self.numerator.checked_add(other.numerator)?,
+                self.denominator.checked_mul(2)?,
+            );
+        }
+
+        let numerator = self
+            .numerator
+            .checked_mul(other.denominator)?
+            .checked_add(other.numerator.checked_mul(self.denominator)?)?;
+        let denominator = self.denominator.checked_mul(other.denominator)?;
+        ExchangeRate::from_ratio(numerator, denominator.checked_mul(2)?)
+    }
+
+    /// The exact rate `position / length` of the way from this rate to `other`, e.g. the
+    /// rate of a holiday between the rates of the days around it. Returns `None` on
+    /// overflow, if `length` is zero or if extrapolating gives a rate that is not positive.
+    pub fn checked_interpolate(&self, other: &Self, position: i128, length: i128) -> Option<Self> {
+        if length == 0 {
+            return None;
+        }
+
+        let numerator = self
+            .numerator
+            .checked_mul(other.denominator)?
+            .checked_mul(length.checked_sub(position)?)?
+            .checked_add(
+                other
+                    .numerator
+                    .checked_mul(self.denominator)?
+                    .checked_mul(position)?,
+            )?;
+        let denominator = self
+            .denominator
+            .checked_mul(other.denominator)?
+            .checked_mul(length)?;
+        ExchangeRate::from_ratio(numerator, denominator)
+    }
+
+    /// The exact arithmetic mean of `rates`, or `None` if there are none or on overflow.
+    pub fn checked_mean(rates: &[Self]) -> Option<Self> {
+        if rates.is_empty() {
+            return None;
+        }
+
+        let denominator = rates.iter().try_fold(1i128, |lcm, rate| {
+            (lcm / gcd(lcm, rate.denominator)).checked_mul(rate.denominator)
+        })?;
+        let numerator = rates.iter().try_fold(0i128, |sum, rate| {
+            sum.checked_add(rate.numerator.checked_mul(denominator / rate.denominator)?)
+        })?;
+        let count = i128::try_from(rates.len()).ok()?;
+
+        ExchangeRate::from_ratio(numerator, denominator.checked_mul(count)?)
+    }
+
+    /// This rate rounded to `decimals` decimal places with `mode`, as published averages
+    /// are. Returns `None` on overflow or if the rate rounds to zero.
+    pub fn checked_round(&self, decimals: u32, mode: RoundingMode) -> Option<Self> {
+        let scale = pow10(decimals)?;
+        let numerator =
+            mode.checked_divide(self.numerator.checked_mul(scale)?, self.denominator)?;
+        ExchangeRate::from_ratio(numerator, scale)
     }
 
     pub fn convert(&self, from_amount: Currency<D>) -> Currency<N> {
-        Currency::from_raw_amount((from_amount.raw_amount() as f64 * self.0).round() as i64)
+        self.convert_with(from_amount, RoundingMode::default())
+    }
+
+    /// Converts `from_amount`, rounding the result to the minor units of `N` with `mode`.
+    ///
+    /// Panics if the result is out of range.
+    pub fn convert_with(&self, from_amount: Currency<D>, mode: RoundingMode) -> Currency<N> {
+        self.checked_convert_with(from_amount, mode)
+            .expect("Currency conversion overflowed")
+    }
+
+    /// Converts `from_amount` by computing `amount * numerator / denominator` exactly in
+    /// minor units and rounding the quotient once with `mode`. For an inverted rate this
+    /// divides by the published rate rather than multiplying by a rounded reciprocal, so
+    /// the result can be checked by hand against the published figure.
+    pub fn checked_convert_with(
+        &self,
+        from_amount: Currency<D>,
+        mode: RoundingMode,
+    ) -> Option<Currency<N>> {
+        let mut numerator = from_amount.raw_amount().checked_mul(self.numerator)?;
+        let mut denominator = self.denominator;
+        if N::minor_units() >= D::minor_units() {
+            numerator = numerator.checked_mul(pow10(N::minor_units() - D::minor_units())?)?;
+        } else {
+            denominator = denominator.checked_mul(pow10(D::minor_units() - N::minor_units())?)?;
+        }
+
+        Some(Currency::from_raw_amount(
+            mode.checked_divide(numerator, denominator)?,
+        ))
+    }
+
+    /// The fraction in lowest terms, for comparing rates given at different precisions.
+    fn reduced(&self) -> (i128, i128) {
+        let divisor = gcd(self.numerator, self.denominator);
+        (self.numerator / divisor, self.denominator / divisor)
     }
 }
 
+impl<N, D> PartialEq for ExchangeRate<N, D>
+where
+    N: CurrencyType,
+    D: CurrencyType,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.reduced() == other.reduced()
+    }
+}
+
+impl<N, D> Eq for ExchangeRate<N, D>
+where
+    N: CurrencyType,
+    D: CurrencyType,
+{
+}
+
 impl<'de, N, D> Deserialize<'de> for ExchangeRate<N, D>
 where
     N: CurrencyType,
@@ -48,8 +293,13 @@ where
     where
         De: Deserializer<'de>,
     {
-        let rate = f64::deserialize(deserializer)?;
-        Ok(ExchangeRate::new(rate))
+        let rate = Decimal::deserialize(deserializer)?;
+        ExchangeRate::from_decimal(rate).ok_or_else(|| {
+            de::Error::custom(format!(
+                "Exchange rate not positive or out of range: {}",
+                rate
+            ))
+        })
     }
 }
 
@@ -62,34 +312,41 @@ where
     where
         S: serde::Serializer,
     {
-        self.rate().serialize(serializer)
+        self.to_decimal()
+            .ok_or_else(|| ser::Error::custom("Exchange rate out of range"))?
+            .serialize(serializer)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::conversions::currency::{EUR, USD};
+    use crate::conversions::currency::{ETH, EUR, GBP, JPY, KWD, USD};
     use csv::Reader;
 
+    #[allow(clippy::upper_case_acronyms)]
     #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
     struct USC;
 
     impl CurrencyType for USC {
-        fn formatter(_: i64) -> String {
+        fn currency() -> crate::currency::Currency {
+            crate::currency::Currency::USD
+        }
+
+        fn formatter(_: i128) -> String {
             panic!("Not implemented")
         }
 
-        fn store_factor() -> f64 {
-            1.0
+        fn minor_units() -> u32 {
+            0
         }
     }
 
     #[test]
     fn test_exchange_rate() {
         let rate = ExchangeRate::<EUR, USD>::new(0.8);
-        assert_eq!(rate, ExchangeRate(0.8, PhantomData, PhantomData));
-        assert_eq!(rate.invert(), ExchangeRate(1.25, PhantomData, PhantomData));
+        assert_eq!(rate, ExchangeRate::new(0.8));
+        assert_eq!(rate.invert(), ExchangeRate::new(1.25));
     }
 
     #[test]
@@ -98,6 +355,53 @@ mod tests {
         let amount = Currency::<USD>::from(100);
 
         assert_eq!(rate.convert(amount), Currency::<USC>::from(10000));
+        assert_eq!(rate.rate(), 100.0);
+    }
+
+    #[test]
+    fn test_convert_different_minor_units() {
+        let rate = ExchangeRate::<JPY, EUR>::new(161.23);
+        assert_eq!(
+            rate.convert(Currency::<EUR>::try_from(10.01).unwrap()),
+            Currency::<JPY>::from(1614)
+        );
+
+        let rate = ExchangeRate::<KWD, USD>::new(0.3071);
+        assert_eq!(
+            rate.convert(Currency::<USD>::try_from(1234.56).unwrap()),
+            Currency::<KWD>::try_from(379.133).unwrap()
+        );
+
+        let rate = ExchangeRate::<EUR, ETH>::new(2345.67);
+        assert_eq!(
+            rate.convert(Currency::<ETH>::from_raw_amount(1_234_567_890_123_456_789)),
+            Currency::<EUR>::try_from(2895.89).unwrap()
+        );
+
+        let rate = ExchangeRate::<ETH, EUR>::new(0.0004);
+        assert_eq!(
+            rate.convert(Currency::<EUR>::try_from(0.01).unwrap()),
+            Currency::<ETH>::from_raw_amount(4_000_000_000_000)
+        );
+    }
+
+    #[test]
+    fn test_convert_rounding() {
+        let rate = ExchangeRate::<EUR, USD>::new(0.5);
+        let amount = Currency::<USD>::try_from(0.05).unwrap();
+
+        assert_eq!(
+            rate.convert(amount),
+            Currency::<EUR>::try_from(0.03).unwrap()
+        );
+        assert_eq!(
+            rate.convert_with(amount, RoundingMode::HalfEven),
+            Currency::<EUR>::try_from(0.02).unwrap()
+        );
+        assert_eq!(
+            rate.convert_with(amount, RoundingMode::TowardZero),
+            Currency::<EUR>::try_from(0.02).unwrap()
+        );
     }
 
     #[test]
@@ -109,7 +413,7 @@ mod tests {
             .unwrap()
             .unwrap();
 
-        assert_eq!(rate, ExchangeRate(0.8, PhantomData, PhantomData));
+        assert_eq!(rate, ExchangeRate::new(0.8));
     }
 
     #[test]
@@ -121,6 +425,156 @@ mod tests {
             .unwrap()
             .unwrap();
 
-        assert_eq!(rate, ExchangeRate(1.0, PhantomData, PhantomData));
+        assert_eq!(rate, ExchangeRate::new(100.0));
+    }
+
+    #[test]
+    fn test_rate_round_trip() {
+        let csv = "rate\n1.0800\n";
+        let rate: ExchangeRate<USD, EUR> = Reader::from_reader(csv.as_bytes())
+            .deserialize()
+            .next()
+            .unwrap()
+            .unwrap();
+
+        let mut writer = csv::Writer::from_writer(vec![]);
+        writer.serialize((rate,)).unwrap();
+        writer.serialize((rate.invert(),)).unwrap();
+        writer.serialize((rate.invert().invert(),)).unwrap();
+        assert_eq!(
+            String::from_utf8(writer.into_inner().unwrap()).unwrap(),
+            "1.0800\n0.925925925926\n1.0800\n"
+        );
+        assert_eq!(rate, ExchangeRate::new(1.08));
+    }
+
+    #[test]
+    fn test_to_decimal() {
+        let rate = ExchangeRate::<EUR, USD>::new(1.25);
+
+        assert_eq!(rate.to_decimal().unwrap().to_string(), "1.25");
+        assert_eq!(rate.invert().to_decimal().unwrap().to_string(), "0.8");
+        assert_eq!(
+            ExchangeRate::<EUR, USD>::new(1.0837)
+                .invert()
+                .to_decimal()
+                .unwrap()
+                .to_string(),
+            "0.92276460275"
+        );
+    }
+
+    #[test]
+    fn test_compose() {
+        let usd_per_eur = ExchangeRate::<USD, EUR>::new(1.0837);
+        let gbp_per_eur = ExchangeRate::<GBP, EUR>::new(0.8543);
+        let usd_per_gbp = usd_per_eur.compose(&gbp_per_eur.invert());
+
+        assert_eq!(
+            usd_per_gbp.to_decimal().unwrap().to_string(),
+            "1.268523937727"
+        );
+        assert_eq!(
+            usd_per_eur.compose(&ExchangeRate::<EUR, JPY>::new(0.0062)),
+            ExchangeRate::<USD, JPY>::new(0.00671894)
+        );
+        // 100.00 GBP * 1.0837 / 0.8543 = 126.8523... USD, rounded once.
+        assert_eq!(
+            usd_per_gbp.convert(Currency::<GBP>::from(100)),
+            Currency::<USD>::try_from(126.85).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_compose_reduces_on_overflow() {
+        let rate =
+            ExchangeRate::<EUR, EUR>::from_ratio(3 * 10i128.pow(30), 10i128.pow(30)).unwrap();
+
+        assert_eq!(rate.compose(&rate), ExchangeRate::new(9.0));
+    }
+
+    #[test]
+    fn test_from_ratio_not_positive() {
+        assert!(ExchangeRate::<EUR, USD>::from_ratio(0, 1).is_none());
+        assert!(ExchangeRate::<EUR, USD>::from_ratio(1, 0).is_none());
+        assert!(ExchangeRate::<EUR, USD>::from_ratio(-1, 2).is_none());
+        assert!(ExchangeRate::<EUR, USD>::from_ratio(1, -2).is_none());
+        assert!(ExchangeRate::<EUR, USD>::from_ratio(i128::MIN, -1).is_none());
+        assert_eq!(
+            ExchangeRate::<EUR, USD>::from_ratio(-1, -2),
+            Some(ExchangeRate::new(0.5))
+        );
+        assert_eq!(
+            ExchangeRate::<EUR, USD>::from_decimal("0.0000".parse().unwrap()),
+            None
+        );
+        assert_eq!(
+            ExchangeRate::<EUR, USD>::from_decimal("-1.08".parse().unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_midpoint() {
+        let bid = ExchangeRate::<USD, EUR>::new(1.0815);
+        let ask = ExchangeRate::<USD, EUR>::new(1.0875);
+
+        assert_eq!(bid.checked_midpoint(&ask), Some(ExchangeRate::new(1.0845)));
+        assert_eq!(
+            bid.checked_midpoint(&ExchangeRate::new(1.09)),
+            Some(ExchangeRate::new(1.08575))
+        );
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let friday = ExchangeRate::<USD, EUR>::new(1.08);
+        let monday = ExchangeRate::<USD, EUR>::new(1.11);
+
+        assert_eq!(
+            friday.checked_interpolate(&monday, 1, 3),
+            Some(ExchangeRate::new(1.09))
+        );
+        assert_eq!(
+            friday.checked_interpolate(&monday, 3, 3),
+            Some(ExchangeRate::new(1.11))
+        );
+        assert_eq!(friday.checked_interpolate(&monday, 1, 0), None);
+    }
+
+    #[test]
+    fn test_mean_and_round() {
+        let rates = [
+            ExchangeRate::<USD, EUR>::new(1.0837),
+            ExchangeRate::new(1.09),
+            ExchangeRate::new(1.0712),
+        ];
+        let mean = ExchangeRate::checked_mean(&rates).unwrap();
+
+        assert_eq!(mean.to_decimal().unwrap().to_string(), "1.081633333333");
+        assert_eq!(
+            mean.checked_round(4, RoundingMode::HalfUp)
+                .unwrap()
+                .to_decimal()
+                .unwrap()
+                .to_string(),
+            "1.0816"
+        );
+        assert_eq!(ExchangeRate::<USD, EUR>::checked_mean(&[]), None);
+    }
+
+    #[test]
+    fn test_convert_inverted() {
+        // 100.00 USD / 1.0837 = 92.27646027... EUR, rounded once.
+        let rate = ExchangeRate::<USD, EUR>::new(1.0837).invert();
+
+        assert_eq!(
+            rate.convert(Currency::<USD>::from(100)),
+            Currency::<EUR>::try_from(92.28).unwrap()
+        );
+        assert_eq!(
+            rate.convert_with(Currency::<USD>::from(100), RoundingMode::TowardZero),
+            Currency::<EUR>::try_from(92.27).unwrap()
+        );
     }
 }
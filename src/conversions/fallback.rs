@@ -0,0 +1,250 @@
+use std::sync::Arc;
+
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::conversions::{
+    calendar::BusinessCalendar, currency::CurrencyType, date_map::DateMap,
+    exchange_rate::ExchangeRate, rate_source::RateQuote,
+};
+
+/// Which rate to use for a date that has none, e.g. a weekend or a holiday.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum FallbackPolicy {
+    /// Only use the rate of the date itself.
+    #[default]
+    Exact,
+    /// Use the last rate published before the date.
+    Previous,
+    /// Use the first rate published after the date.
+    Next,
+    /// Use the closest rate, preferring the earlier one if two are equally close.
+    Nearest,
+    /// Interpolate linearly between the rates before and after the date.
+    Interpolate,
+}
+
+/// A [`FallbackPolicy`] with the maximum number of days that a rate may be away from the
+/// date it is used for. These are calendar days, or business days if a calendar is given.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Fallback {
+    policy: FallbackPolicy,
+    max_staleness: u32,
+    calendar: Option<Arc<BusinessCalendar>>,
+}
+
+/// A rate found for a date by a [`Fallback`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FoundRate<R> {
+    /// The rate of a single day, either the date itself or the one the policy fell back to.
+    On(NaiveDate, R),
+    /// The closest rates before and after the date, to interpolate between.
+    Between((NaiveDate, R), (NaiveDate, R)),
+}
+
+impl Fallback {
+    pub fn new(policy: FallbackPolicy, max_staleness: u32) -> Self {
+        Fallback {
+            policy,
+            max_staleness,
+            calendar: None,
+        }
+    }
+
+    /// Counts the staleness of rates in business days of `calendar`: the business days
+    /// between the rate and the date, and the date itself if it is one. Rates from before
+    /// a weekend or holiday are then not stale at all, while a business day without a rate
+    /// counts against the maximum.
+    pub fn with_calendar(self, calendar: Arc<BusinessCalendar>) -> Self {
+        Fallback {
+            calendar: Some(calendar),
+            ..self
+        }
+    }
+
+    pub fn policy(&self) -> FallbackPolicy {
+        self.policy
+    }
+
+    pub fn max_staleness(&self) -> u32 {
+        self.max_staleness
+    }
+
+    pub fn calendar(&self) -> Option<&BusinessCalendar> {
+        self.calendar.as_deref()
+    }
+
+    /// How many days the rate of `other` is away from `date`.
+    fn staleness(&self, date: &NaiveDate, other: &NaiveDate) -> i64 {
+        match &self.calendar {
+            Some(calendar) => {
+                let (from, to) = if other < date {
+                    (other, date)
+                } else {
+                    (date, other)
+                };
+                let between =
+                    calendar.business_days(from, to) - u32::from(calendar.is_business_day(to));
+                i64::from(between + u32::from(calendar.is_business_day(date)))
+            }
+            None => (*other - *date).num_days().abs(),
+        }
+    }
+
+    /// Looks up the rate for `date` in `rates`, falling back to the nearest other days as the
+    /// policy allows. The rate of the date itself is always preferred.
+    pub fn find<'a, R>(&self, date: &NaiveDate, rates: &'a DateMap<R>) -> Option<FoundRate<&'a R>> {
+        if let Some(rate) = rates.get(date) {
+            return Some(FoundRate::On(*date, rate));
+        }
+
+        let max_staleness = i64::from(self.max_staleness);
+        let within = |(other, rate): (&NaiveDate, &'a R)| {
+            (self.staleness(date, other) <= max_staleness).then_some((*other, rate))
+        };
+        let before = || rates.before(date).and_then(within);
+        let after = || rates.after(date).and_then(within);
+
+        let (date, rate) = match self.policy {
+            FallbackPolicy::Exact => None,
+            FallbackPolicy::Previous => before(),
+            FallbackPolicy::Next => after(),
+            FallbackPolicy::Nearest => match (before(), after()) {
+                (Some(before), Some(after)) if after.0 - *date < *date - before.0 => Some(after),
+                (before, after) => before.or(after),
+            },
+            FallbackPolicy::Interpolate => {
+                return Some(FoundRate::Between(before()?, after()?));
+            }
+        }?;
+        Some(FoundRate::On(date, rate))
+    }
+}
+
+impl<R> FoundRate<R> {
+    /// Applies `f` to the rates found, e.g. to pick one side of bid and ask rates.
+    pub fn try_map<S>(self, f: impl Fn(R) -> Option<S>) -> Option<FoundRate<S>> {
+        Some(match self {
+            FoundRate::On(date, rate) => FoundRate::On(date, f(rate)?),
+            FoundRate::Between((before, before_rate), (after, after_rate)) => {
+                FoundRate::Between((before, f(before_rate)?), (after, f(after_rate)?))
+            }
+        })
+    }
+}
+
+impl<R> FoundRate<&R>
+where
+    R: Copy,
+{
+    pub fn copied(self) -> FoundRate<R> {
+        match self {
+            FoundRate::On(date, rate) => FoundRate::On(date, *rate),
+            FoundRate::Between((before, before_rate), (after, after_rate)) => {
+                FoundRate::Between((before, *before_rate), (after, *after_rate))
+            }
+        }
+    }
+}
+
+impl<N, D> FoundRate<ExchangeRate<N, D>>
+where
+    N: CurrencyType,
+    D: CurrencyType,
+{
+    /// The rate to use for `date`. Rates between two days are weighted by the number of
+    /// calendar days to either of them. Returns `None` if interpolating overflows.
+    pub fn into_quote(self, date: &NaiveDate) -> Option<RateQuote<N, D>> {
+        match self {
+            FoundRate::On(rate_date, rate) => Some(RateQuote::direct(rate, rate_date)),
+            FoundRate::Between((before, before_rate), (after, after_rate)) => {
+                let rate = before_rate.checked_interpolate(
+                    &after_rate,
+                    (*date - before).num_days().into(),
+                    (after - before).num_days().into(),
+                )?;
+                Some(RateQuote::interpolated(rate, [before, after]))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversions::calendar::BuiltinCalendar;
+
+    /// Rates on Thursday, March 28th and Tuesday, April 2nd, around the Easter holidays.
+    fn rates() -> DateMap<u32> {
+        [(date(28), 28), (date(2), 2)].into_iter().collect()
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        match day {
+            28..=31 => NaiveDate::from_ymd_opt(2024, 3, day).unwrap(),
+            _ => NaiveDate::from_ymd_opt(2024, 4, day).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_find() {
+        let rates = rates();
+        let find = |policy, max_staleness, day| {
+            Fallback::new(policy, max_staleness)
+                .find(&date(day), &rates)
+                .map(FoundRate::copied)
+        };
+
+        assert_eq!(
+            find(FallbackPolicy::Exact, 5, 28),
+            Some(FoundRate::On(date(28), 28))
+        );
+        assert_eq!(find(FallbackPolicy::Exact, 5, 29), None);
+        assert_eq!(
+            find(FallbackPolicy::Previous, 5, 1),
+            Some(FoundRate::On(date(28), 28))
+        );
+        assert_eq!(find(FallbackPolicy::Previous, 3, 1), None);
+        assert_eq!(
+            find(FallbackPolicy::Next, 5, 29),
+            Some(FoundRate::On(date(2), 2))
+        );
+        assert_eq!(
+            find(FallbackPolicy::Nearest, 5, 31),
+            Some(FoundRate::On(date(2), 2))
+        );
+        assert_eq!(
+            find(FallbackPolicy::Nearest, 5, 30),
+            Some(FoundRate::On(date(28), 28))
+        );
+        assert_eq!(
+            find(FallbackPolicy::Interpolate, 5, 30),
+            Some(FoundRate::Between((date(28), 28), (date(2), 2)))
+        );
+        assert_eq!(find(FallbackPolicy::Interpolate, 2, 31), None);
+    }
+
+    #[test]
+    fn test_find_with_calendar() {
+        let rates = rates();
+        let target2 = Arc::new(BusinessCalendar::builtin(BuiltinCalendar::Target2));
+        let find = |policy, day| {
+            Fallback::new(policy, 0)
+                .with_calendar(target2.clone())
+                .find(&date(day), &rates)
+                .map(FoundRate::copied)
+        };
+
+        assert_eq!(
+            find(FallbackPolicy::Previous, 1),
+            Some(FoundRate::On(date(28), 28))
+        );
+        assert_eq!(
+            find(FallbackPolicy::Next, 30),
+            Some(FoundRate::On(date(2), 2))
+        );
+        assert_eq!(find(FallbackPolicy::Previous, 3), None);
+    }
+}
@@ -1,4 +1,23 @@
+pub mod allocation;
+pub mod bid_ask_rates;
+pub mod calendar;
+pub mod cross_exchange_rates;
 pub mod currency;
 pub mod daily_exchange_rates;
+pub mod date_map;
+pub mod decimal;
 pub mod exchange_rate;
+pub mod fallback;
+pub mod multi_currency_rates;
+pub mod period;
+pub mod period_exchange_rates;
+pub mod provenance;
+pub mod rate_provider;
+pub mod rate_series;
+pub mod rate_source;
+pub mod rate_store;
+pub mod rounding;
+pub mod timezone;
 pub mod transaction;
+pub mod validation;
+pub mod wide_exchange_rates;
@@ -0,0 +1,182 @@
+use std::{any::Any, collections::HashMap, marker::PhantomData};
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::{
+    conversions::{
+        currency::{dispatch, Currency, CurrencyDispatch, CurrencyType},
+        daily_exchange_rates::ConversionError,
+        rate_source::RateSource,
+        rounding::RoundingMode,
+        transaction::Transaction,
+    },
+    currency::{self, Money},
+};
+
+/// Exchange rates into `N` from any number of currencies, for converting amounts whose
+/// currency is only known at runtime, such as the proceeds of IBKR trades.
+pub struct MultiCurrencyRates<N>
+where
+    N: CurrencyType,
+{
+    tables: HashMap<currency::Currency, Box<dyn Any>>,
+    phantom: PhantomData<N>,
+}
+
+impl<N> MultiCurrencyRates<N>
+where
+    N: CurrencyType + for<'de> Deserialize<'de> + 'static,
+{
+    pub fn new() -> Self {
+        MultiCurrencyRates {
+            tables: HashMap::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Adds the rates for converting from `D`, replacing any previous rates for `D`.
+    pub fn insert<D>(&mut self, rates: impl RateSource<N, D> + 'static)
+    where
+        D: CurrencyType + for<'de> Deserialize<'de> + 'static,
+    {
+        let rates: Box<dyn RateSource<N, D>> = Box::new(rates);
+        self.tables.insert(D::currency(), Box::new(rates));
+    }
+
+    pub fn rates<D>(&self) -> Option<&dyn RateSource<N, D>>
+    where
+        D: CurrencyType + for<'de> Deserialize<'de> + 'static,
+    {
+        self.tables
+            .get(&D::currency())?
+            .downcast_ref::<Box<dyn RateSource<N, D>>>()
+            .map(Box::as_ref)
+    }
+
+    /// Converts `amount` with the rate of `date`, picking the rate table by the currency
+    /// of `amount`. Amounts that are already in `N` are returned unchanged.
+    pub fn convert(
+        &self,
+        date: NaiveDate,
+        amount: Money,
+        mode: RoundingMode,
+    ) -> Result<Transaction<N>, ConversionError> {
+        dispatch(
+            amount.currency(),
+            Convert {
+                rates: self,
+                date,
+                amount,
+                mode,
+            },
+        )?
+    }
+}
+
+impl<N> Default for MultiCurrencyRates<N>
+where
+    N: CurrencyType + for<'de> Deserialize<'de> + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Convert<'a, N>
+where
+    N: CurrencyType,
+{
+    rates: &'a MultiCurrencyRates<N>,
+    date: NaiveDate,
+    amount: Money,
+    mode: RoundingMode,
+}
+
+impl<N> CurrencyDispatch for Convert<'_, N>
+where
+    N: CurrencyType + for<'de> Deserialize<'de> + 'static,
+{
+    type Output = Result<Transaction<N>, ConversionError>;
+
+    fn call<D>(self) -> Self::Output
+    where
+        D: CurrencyType + for<'de> Deserialize<'de> + 'static,
+    {
+        if D::currency() == N::currency() {
+            let amount = Currency::<N>::from_decimal(self.amount.to_decimal(), self.mode)
+                .map_err(|_| ConversionError::AmountOverflow)?;
+            return Ok(Transaction::new(self.date, amount));
+        }
+
+        let amount = Currency::<D>::from_decimal(self.amount.to_decimal(), self.mode)
+            .map_err(|_| ConversionError::AmountOverflow)?;
+        self.rates
+            .rates::<D>()
+            .ok_or(ConversionError::MissingExchangeRate)?
+            .convert_with(&Transaction::new(self.date, amount), self.mode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversions::currency::{EUR, GBP, USD};
+    use crate::conversions::daily_exchange_rates::DailyExchangeRates;
+    use csv::Reader;
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 1, 2).unwrap()
+    }
+
+    fn rates() -> MultiCurrencyRates<EUR> {
+        let mut rates = MultiCurrencyRates::new();
+        rates.insert::<USD>(
+            DailyExchangeRates::read_from_csv(Reader::from_reader(
+                "date,rate\n2023-01-02,0.9\n".as_bytes(),
+            ))
+            .unwrap(),
+        );
+        rates.insert::<GBP>(
+            DailyExchangeRates::read_from_csv(Reader::from_reader(
+                "date,rate\n2023-01-02,1.2\n".as_bytes(),
+            ))
+            .unwrap(),
+        );
+        rates
+    }
+
+    #[test]
+    fn test_convert() {
+        let rates = rates();
+
+        assert_eq!(
+            rates.convert(date(), "USD 100".parse().unwrap(), RoundingMode::HalfUp),
+            Ok(Transaction::new(date(), Currency::from(90)))
+        );
+        assert_eq!(
+            rates.convert(date(), "GBP 100".parse().unwrap(), RoundingMode::HalfUp),
+            Ok(Transaction::new(date(), Currency::from(120)))
+        );
+        assert_eq!(
+            rates.convert(date(), "EUR 100".parse().unwrap(), RoundingMode::HalfUp),
+            Ok(Transaction::new(date(), Currency::from(100)))
+        );
+    }
+
+    #[test]
+    fn test_convert_errors() {
+        let rates = rates();
+
+        assert_eq!(
+            rates.convert(date(), "CHF 100".parse().unwrap(), RoundingMode::HalfUp),
+            Err(ConversionError::MissingExchangeRate)
+        );
+        assert_eq!(
+            rates.convert(date(), "NOK 100".parse().unwrap(), RoundingMode::HalfUp),
+            Err(ConversionError::UnsupportedCurrency(
+                currency::Currency::NOK
+            ))
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use chrono::{Datelike, NaiveDate};
+use clap::ValueEnum;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// The length of the periods that a rate table has one rate for.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PeriodKind {
+    Month,
+    Quarter,
+    Year,
+}
+
+/// A calendar month, quarter or year, written `2024-03`, `2024-Q1` and `2024`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Period {
+    Month(i32, u32),
+    Quarter(i32, u32),
+    Year(i32),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParsePeriodError(String);
+
+impl Display for ParsePeriodError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid period: {} (expected e.g. 2024-03, 2024-Q1 or 2024)",
+            self.0
+        )
+    }
+}
+
+impl Error for ParsePeriodError {}
+
+impl Period {
+    /// The period of `kind` that `date` falls into.
+    pub fn containing(kind: PeriodKind, date: &NaiveDate) -> Self {
+        match kind {
+            PeriodKind::Month => Period::Month(date.year(), date.month()),
+            PeriodKind::Quarter => Period::Quarter(date.year(), (date.month() - 1) / 3 + 1),
+            PeriodKind::Year => Period::Year(date.year()),
+        }
+    }
+
+    pub fn kind(&self) -> PeriodKind {
+        match self {
+            Period::Month(..) => PeriodKind::Month,
+            Period::Quarter(..) => PeriodKind::Quarter,
+            Period::Year(..) => PeriodKind::Year,
+        }
+    }
+
+    pub fn contains(&self, date: &NaiveDate) -> bool {
+        Period::containing(self.kind(), date) == *self
+    }
+}
+
+impl FromStr for Period {
+    type Err = ParsePeriodError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParsePeriodError(s.to_string());
+        let number = |digits: &str, len: usize| {
+            (digits.len() == len && digits.chars().all(|c| c.is_ascii_digit()))
+                .then(|| digits.parse().ok())
+                .flatten()
+                .ok_or_else(invalid)
+        };
+
+        let trimmed = s.trim();
+        let period = match trimmed.split_once('-') {
+            None => Period::Year(number(trimmed, 4)? as i32),
+            Some((year, quarter)) if quarter.starts_with(['Q', 'q']) => {
+                Period::Quarter(number(year, 4)? as i32, number(&quarter[1..], 1)?)
+            }
+            Some((year, month)) => Period::Month(number(year, 4)? as i32, number(month, 2)?),
+        };
+
+        match period {
+            Period::Month(_, 1..=12) | Period::Quarter(_, 1..=4) | Period::Year(_) => Ok(period),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Display for Period {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Period::Month(year, month) => write!(f, "{}-{:02}", year, month),
+            Period::Quarter(year, quarter) => write!(f, "{}-Q{}", year, quarter),
+            Period::Year(year) => write!(f, "{}", year),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Period {
+    fn deserialize<D>(deserializer: D) -> Result<Period, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let period = String::deserialize(deserializer)?;
+        period.parse().map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for Period {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("2024-03".parse(), Ok(Period::Month(2024, 3)));
+        assert_eq!("2024-Q4".parse(), Ok(Period::Quarter(2024, 4)));
+        assert_eq!("2024".parse(), Ok(Period::Year(2024)));
+        for invalid in ["2024-13", "2024-Q5", "24", "2024-3", "2024-Q", "March"] {
+            assert_eq!(
+                invalid.parse::<Period>(),
+                Err(ParsePeriodError(invalid.to_string()))
+            );
+        }
+        assert_eq!(Period::Quarter(2024, 1).to_string(), "2024-Q1");
+    }
+
+    #[test]
+    fn test_containing() {
+        let date = NaiveDate::from_ymd_opt(2024, 8, 15).unwrap();
+
+        assert_eq!(
+            Period::containing(PeriodKind::Month, &date),
+            Period::Month(2024, 8)
+        );
+        assert_eq!(
+            Period::containing(PeriodKind::Quarter, &date),
+            Period::Quarter(2024, 3)
+        );
+        assert!(Period::Year(2024).contains(&date));
+        assert!(!Period::Month(2024, 7).contains(&date));
+    }
+}
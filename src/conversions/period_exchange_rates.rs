@@ -0,0 +1,278 @@
+use std::{collections::HashMap, error::Error, fmt::Display, sync::Arc};
+
+use chrono::NaiveDate;
+use csv::Reader;
+use serde::Deserialize;
+
+use crate::conversions::{
+    currency::CurrencyType,
+    daily_exchange_rates::{ConversionError, DailyExchangeRates},
+    exchange_rate::ExchangeRate,
+    period::{Period, PeriodKind},
+    provenance::{Provenance, RateOrigin},
+    rate_source::{RateQuote, RateSource},
+};
+
+#[derive(Debug)]
+pub enum PeriodRatesError {
+    Csv(csv::Error),
+    /// The file mixes periods of different lengths, e.g. months and years.
+    MixedPeriods(Period, Period),
+    DuplicatePeriod(Period),
+    NoRates,
+}
+
+impl Display for PeriodRatesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Csv(error) => write!(f, "{}", error),
+            Self::MixedPeriods(first, other) => {
+                write!(f, "Periods {} and {} have different lengths", first, other)
+            }
+            Self::DuplicatePeriod(period) => write!(f, "Duplicate rate for {}", period),
+            Self::NoRates => write!(f, "No rates found"),
+        }
+    }
+}
+
+impl Error for PeriodRatesError {}
+
+impl From<csv::Error> for PeriodRatesError {
+    fn from(error: csv::Error) -> Self {
+        PeriodRatesError::Csv(error)
+    }
+}
+
+/// One rate per calendar month, quarter or year, such as the monthly rates the BMF
+/// publishes for German VAT or the IRS yearly average rates. Every date uses the rate of
+/// the period it falls into.
+#[derive(Debug, PartialEq)]
+pub struct PeriodExchangeRates<N, D>
+where
+    N: CurrencyType,
+    D: CurrencyType,
+{
+    kind: PeriodKind,
+    rates: HashMap<Period, ExchangeRate<N, D>>,
+    provenance: HashMap<Period, Provenance>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PeriodRate<N, D>
+where
+    N: CurrencyType,
+    D: CurrencyType,
+{
+    period: Period,
+    rate: ExchangeRate<N, D>,
+}
+
+impl<N, D> PeriodExchangeRates<N, D>
+where
+    N: CurrencyType + for<'de> Deserialize<'de>,
+    D: CurrencyType + for<'de> Deserialize<'de>,
+{
+    pub fn kind(&self) -> PeriodKind {
+        self.kind
+    }
+
+    /// Records that all rates came from `origin`, keeping the lines they were read from.
+    pub fn with_origin(mut self, origin: RateOrigin) -> Self {
+        let origin = Arc::new(origin);
+        self.provenance = self
+            .rates
+            .keys()
+            .map(|period| {
+                let provenance = match self.provenance.get(period) {
+                    Some(provenance) => provenance.with_origin(origin.clone()),
+                    None => Provenance::new(origin.clone(), None),
+                };
+                (*period, provenance)
+            })
+            .collect();
+        self
+    }
+
+    pub fn period_rate(&self, period: &Period) -> Option<&ExchangeRate<N, D>> {
+        self.rates.get(period)
+    }
+
+    /// The rates sorted by period.
+    pub fn periods(&self) -> Vec<(Period, ExchangeRate<N, D>)> {
+        let mut periods: Vec<_> = self
+            .rates
+            .iter()
+            .map(|(period, rate)| (*period, *rate))
+            .collect();
+        periods.sort_by_key(|(period, _)| *period);
+        periods
+    }
+
+    /// The same rates quoted the other way round, or `None` if any rate is zero.
+    pub fn checked_invert(&self) -> Option<PeriodExchangeRates<D, N>> {
+        Some(PeriodExchangeRates {
+            kind: self.kind,
+            rates: self
+                .rates
+                .iter()
+                .map(|(period, rate)| Some((*period, rate.checked_invert()?)))
+                .collect::<Option<_>>()?,
+            provenance: self.provenance.clone(),
+        })
+    }
+
+    /// Averages the daily rates of every period of `kind`, as the exact mean of the rates
+    /// published in it. Periods without any daily rate are left out. Returns `None` on
+    /// overflow.
+    pub fn average_of(daily: &DailyExchangeRates<N, D>, kind: PeriodKind) -> Option<Self> {
+        let mut by_period: HashMap<Period, Vec<ExchangeRate<N, D>>> = HashMap::new();
+        for (date, rate) in daily.rates() {
+            by_period
+                .entry(Period::containing(kind, date))
+                .or_default()
+                .push(*rate);
+        }
+
+        Some(PeriodExchangeRates {
+            kind,
+            rates: by_period
+                .into_iter()
+                .map(|(period, rates)| Some((period, ExchangeRate::checked_mean(&rates)?)))
+                .collect::<Option<_>>()?,
+            provenance: HashMap::new(),
+        })
+    }
+
+    /// Reads a CSV file with `period` and `rate` columns. All periods must have the same
+    /// length.
+    pub fn read_from_csv<R>(mut reader: Reader<R>) -> Result<Self, PeriodRatesError>
+    where
+        R: std::io::Read,
+    {
+        let headers = reader.headers()?.clone();
+        let mut first: Option<Period> = None;
+        let mut rates = HashMap::new();
+        let mut provenance = HashMap::new();
+
+        for record in reader.records() {
+            let record = record?;
+            let row: PeriodRate<N, D> = record.deserialize(Some(&headers))?;
+            match first {
+                Some(first) if first.kind() != row.period.kind() => {
+                    return Err(PeriodRatesError::MixedPeriods(first, row.period))
+                }
+                None => first = Some(row.period),
+                _ => {}
+            }
+            if rates.insert(row.period, row.rate).is_some() {
+                return Err(PeriodRatesError::DuplicatePeriod(row.period));
+            }
+            let line = record.position().map(|position| position.line());
+            provenance.insert(row.period, Provenance::new(Arc::default(), line));
+        }
+
+        Ok(PeriodExchangeRates {
+            kind: first.ok_or(PeriodRatesError::NoRates)?.kind(),
+            rates,
+            provenance,
+        })
+    }
+}
+
+impl<N, D> RateSource<N, D> for PeriodExchangeRates<N, D>
+where
+    N: CurrencyType + for<'de> Deserialize<'de>,
+    D: CurrencyType + for<'de> Deserialize<'de>,
+{
+    fn quote(&self, date: &NaiveDate) -> Result<RateQuote<N, D>, ConversionError> {
+        let period = Period::containing(self.kind, date);
+        let quote = self
+            .period_rate(&period)
+            .map(|rate| RateQuote::for_period(*rate, period))
+            .ok_or(ConversionError::MissingExchangeRate)?;
+        let provenance = self.provenance.get(&period).cloned().into_iter().collect();
+        Ok(quote.with_provenance(provenance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversions::currency::{Currency, EUR, USD};
+    use crate::conversions::transaction::Transaction;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_monthly_rates() {
+        let csv = "period,rate\n2024-01,1.0905\n2024-02,1.0795\n";
+        let rates: PeriodExchangeRates<USD, EUR> =
+            PeriodExchangeRates::read_from_csv(Reader::from_reader(csv.as_bytes())).unwrap();
+
+        assert_eq!(rates.kind(), PeriodKind::Month);
+        assert_eq!(
+            rates.convert(&Transaction::new(date(2, 29), Currency::<EUR>::from(100))),
+            Ok(Transaction::new(
+                date(2, 29),
+                Currency::try_from(107.95).unwrap()
+            ))
+        );
+        assert_eq!(
+            rates.quote(&date(1, 6)).unwrap().period(),
+            Some(Period::Month(2024, 1))
+        );
+        let rates = rates.with_origin(RateOrigin {
+            file: Some("bmf.csv".to_string()),
+            ..RateOrigin::default()
+        });
+        assert_eq!(
+            rates.quote(&date(2, 6)).unwrap().provenance()[0].to_string(),
+            "bmf.csv:3"
+        );
+        assert_eq!(
+            rates.quote(&date(3, 1)),
+            Err(ConversionError::MissingExchangeRate)
+        );
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let read = |csv: &str| {
+            PeriodExchangeRates::<USD, EUR>::read_from_csv(Reader::from_reader(csv.as_bytes()))
+        };
+
+        assert!(matches!(
+            read("period,rate\n2024-01,1.09\n2024,1.08\n"),
+            Err(PeriodRatesError::MixedPeriods(
+                Period::Month(2024, 1),
+                Period::Year(2024)
+            ))
+        ));
+        assert!(matches!(
+            read("period,rate\n2024,1.09\n2024,1.08\n"),
+            Err(PeriodRatesError::DuplicatePeriod(Period::Year(2024)))
+        ));
+        assert!(matches!(
+            read("period,rate\n"),
+            Err(PeriodRatesError::NoRates)
+        ));
+    }
+
+    #[test]
+    fn test_average_of() {
+        let csv = "date,rate\n2024-03-28,1.0811\n2024-04-02,1.0745\n2024-04-03,1.0766\n";
+        let daily: DailyExchangeRates<USD, EUR> =
+            DailyExchangeRates::read_from_csv(Reader::from_reader(csv.as_bytes())).unwrap();
+        let monthly = PeriodExchangeRates::average_of(&daily, PeriodKind::Month).unwrap();
+
+        assert_eq!(
+            monthly.periods(),
+            vec![
+                (Period::Month(2024, 3), ExchangeRate::new(1.0811)),
+                (Period::Month(2024, 4), ExchangeRate::new(1.07555)),
+            ]
+        );
+    }
+}
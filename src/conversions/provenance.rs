@@ -0,0 +1,88 @@
+use std::{fmt::Display, sync::Arc};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// The file or provider a set of rates was read from, and when they were retrieved. For a
+/// local file, the rates were retrieved when the file was last modified.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RateOrigin {
+    pub file: Option<String>,
+    pub provider: Option<String>,
+    pub retrieved: Option<DateTime<Utc>>,
+}
+
+/// Where a single rate came from: the origin of its file and the line it is on.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Provenance {
+    origin: Arc<RateOrigin>,
+    line: Option<u64>,
+}
+
+impl Provenance {
+    pub fn new(origin: Arc<RateOrigin>, line: Option<u64>) -> Self {
+        Provenance { origin, line }
+    }
+
+    pub fn origin(&self) -> &RateOrigin {
+        &self.origin
+    }
+
+    pub fn line(&self) -> Option<u64> {
+        self.line
+    }
+
+    /// The same line of a file from `origin`.
+    pub fn with_origin(&self, origin: Arc<RateOrigin>) -> Self {
+        Provenance {
+            origin,
+            line: self.line,
+        }
+    }
+}
+
+impl Display for Provenance {
+    /// E.g. `ECB, eurofxref-hist.csv:12, retrieved 2024-05-06T16:05:00Z`, leaving out what
+    /// is not known.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let location = match (&self.origin.file, self.line) {
+            (Some(file), Some(line)) => Some(format!("{}:{}", file, line)),
+            (Some(file), None) => Some(file.clone()),
+            (None, Some(line)) => Some(format!("line {}", line)),
+            (None, None) => None,
+        };
+        let retrieved = self.origin.retrieved.map(|retrieved| {
+            format!(
+                "retrieved {}",
+                retrieved.to_rfc3339_opts(SecondsFormat::Secs, true)
+            )
+        });
+
+        let parts: Vec<String> = [self.origin.provider.clone(), location, retrieved]
+            .into_iter()
+            .flatten()
+            .collect();
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let origin = RateOrigin {
+            file: Some("eurofxref-hist.csv".to_string()),
+            provider: Some("ECB".to_string()),
+            retrieved: "2024-05-06T16:05:00Z".parse().ok(),
+        };
+        assert_eq!(
+            Provenance::new(Arc::new(origin), Some(12)).to_string(),
+            "ECB, eurofxref-hist.csv:12, retrieved 2024-05-06T16:05:00Z"
+        );
+        assert_eq!(
+            Provenance::new(Arc::default(), Some(3)).to_string(),
+            "line 3"
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use std::{error::Error, fmt::Display};
+
+use chrono::NaiveDate;
+
+use crate::{
+    conversions::rate_store::{RateConflict, RateStore, StoredRate},
+    currency::CurrencyPair,
+};
+
+#[derive(Debug)]
+pub enum ProviderError {
+    /// The provider has no rates to convert between the currencies of the pair.
+    UnknownPair(CurrencyPair),
+    /// The rates could not be read or fetched.
+    Unavailable(Box<dyn Error>),
+    /// The provider gave different rates for the same pair and date.
+    Conflicts(Vec<RateConflict>),
+}
+
+impl Display for ProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownPair(pair) => write!(f, "No rates to convert {}", pair),
+            Self::Unavailable(error) => write!(f, "Rates unavailable: {}", error),
+            Self::Conflicts(conflicts) => {
+                write!(f, "Conflicting rates:")?;
+                for conflict in conflicts {
+                    write!(f, "\n{}", conflict)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for ProviderError {}
+
+/// Anything that can give daily rates for a range of dates, such as a file, a table in
+/// memory or a web service.
+pub trait RateProvider {
+    /// A short name for where the rates come from.
+    fn name(&self) -> &str;
+
+    /// The rates from `from` to `to`, both included, needed to convert between the
+    /// currencies of `pair`: those of the pair itself, quoted either way round, or those of
+    /// both currencies against a common one.
+    fn fetch(
+        &self,
+        pair: CurrencyPair,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<StoredRate>, ProviderError>;
+
+    /// The fetched rates in a [`RateStore`], which hands them out as daily rates of the pair
+    /// or as cross rates. Fails if the provider disagrees with itself about a rate rather
+    /// than converting with whichever came first.
+    fn fetch_store(
+        &self,
+        pair: CurrencyPair,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<RateStore, ProviderError> {
+        let mut store = RateStore::new();
+        let summary = store.merge(self.fetch(pair, from, to)?);
+        if !summary.conflicts.is_empty() {
+            return Err(ProviderError::Conflicts(summary.conflicts));
+        }
+        Ok(store)
+    }
+}
+
+/// A rate store is a provider of the rates it holds in memory.
+impl RateProvider for RateStore {
+    fn name(&self) -> &str {
+        "rate store"
+    }
+
+    fn fetch(
+        &self,
+        pair: CurrencyPair,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<StoredRate>, ProviderError> {
+        let stored = |pair: CurrencyPair| {
+            self.pair_rates(pair).is_some() || self.pair_rates(pair.invert()).is_some()
+        };
+        let pairs = if stored(pair) {
+            vec![pair]
+        } else {
+            let through = self
+                .common_currency(pair.base(), pair.quote())
+                .ok_or(ProviderError::UnknownPair(pair))?;
+            [pair.base(), pair.quote()]
+                .into_iter()
+                .map(|currency| CurrencyPair::new(through, currency))
+                .filter(|leg| stored(*leg))
+                .collect()
+        };
+
+        Ok(pairs
+            .into_iter()
+            .flat_map(|pair| self.range(pair, Some(from), Some(to)))
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        conversions::{
+            currency::{EUR, JPY, USD},
+            exchange_rate::ExchangeRate,
+            rate_source::RateTable,
+        },
+        currency::Currency,
+    };
+
+    fn rate(day: u32, pair: &str, rate: &str) -> StoredRate {
+        let pair: CurrencyPair = pair.parse().unwrap();
+        StoredRate {
+            date: NaiveDate::from_ymd_opt(2024, 5, day).unwrap(),
+            base: pair.base(),
+            quote: pair.quote(),
+            rate: rate.parse().unwrap(),
+            source: "ecb".to_string(),
+            retrieved: None,
+        }
+    }
+
+    #[test]
+    fn test_fetch_from_store() {
+        let mut store = RateStore::new();
+        store.merge([
+            rate(2, "EUR/USD", "1.0702"),
+            rate(3, "EUR/USD", "1.0773"),
+            rate(3, "EUR/JPY", "164.99"),
+            rate(3, "EUR/GBP", "0.8581"),
+        ]);
+        let from = rate(3, "EUR/USD", "1").date;
+
+        let fetched = store.fetch("USD/EUR".parse().unwrap(), from, from).unwrap();
+        assert_eq!(fetched, vec![rate(3, "EUR/USD", "1.0773")]);
+
+        let cross = store
+            .fetch_store("USD/JPY".parse().unwrap(), from, from)
+            .unwrap();
+        assert_eq!(cross.pairs().len(), 2);
+        assert_eq!(
+            cross.common_currency(Currency::USD, Currency::JPY),
+            Some(Currency::EUR)
+        );
+        assert!(cross.pair::<JPY, USD>().is_none());
+        assert_eq!(
+            cross.pair::<USD, EUR>().unwrap().day_rate(&from),
+            Some(&ExchangeRate::new(1.0773))
+        );
+
+        assert!(matches!(
+            store.fetch("USD/CHF".parse().unwrap(), from, from),
+            Err(ProviderError::UnknownPair(_))
+        ));
+    }
+
+    struct Listed(Vec<StoredRate>);
+
+    impl RateProvider for Listed {
+        fn name(&self) -> &str {
+            "listed"
+        }
+
+        fn fetch(
+            &self,
+            _pair: CurrencyPair,
+            _from: NaiveDate,
+            _to: NaiveDate,
+        ) -> Result<Vec<StoredRate>, ProviderError> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn test_fetch_store_conflicts() {
+        let provider = Listed(vec![
+            rate(2, "EUR/USD", "1.0702"),
+            rate(3, "EUR/USD", "1.0773"),
+            rate(3, "USD/EUR", "0.5"),
+        ]);
+        let from = rate(2, "EUR/USD", "1").date;
+        let to = rate(3, "EUR/USD", "1").date;
+
+        match provider.fetch_store("EUR/USD".parse().unwrap(), from, to) {
+            Err(ProviderError::Conflicts(conflicts)) => {
+                assert_eq!(conflicts.len(), 1);
+                assert_eq!(conflicts[0].stored, rate(3, "EUR/USD", "1.0773"));
+            }
+            other => panic!(
+                "expected a conflict, got {:?}",
+                other.map(|store| store.pairs())
+            ),
+        }
+    }
+}
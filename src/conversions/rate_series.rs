@@ -0,0 +1,219 @@
+use std::{collections::HashMap, error::Error, fmt::Display, sync::Arc};
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::{
+    conversions::{
+        currency::CurrencyType,
+        daily_exchange_rates::DailyExchangeRates,
+        date_map::DateMap,
+        decimal::Decimal,
+        exchange_rate::ExchangeRate,
+        provenance::{Provenance, RateOrigin},
+        validation::RateRow,
+    },
+    currency::CurrencyPair,
+};
+
+#[derive(Debug)]
+pub enum SeriesFileError {
+    Csv(csv::Error),
+    /// The file ends before the row that names its series.
+    MissingHeader,
+    InvalidDate {
+        line: u64,
+        date: String,
+    },
+    InvalidRate {
+        line: u64,
+        series: String,
+        rate: String,
+    },
+    /// The values are scaled by a unit multiplier other than one.
+    UnsupportedMultiplier {
+        series: String,
+        multiplier: String,
+    },
+}
+
+impl Display for SeriesFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Csv(error) => write!(f, "{}", error),
+            Self::MissingHeader => write!(f, "The file does not name its series"),
+            Self::InvalidDate { line, date } => {
+                write!(f, "Invalid date on line {}: {}", line, date)
+            }
+            Self::InvalidRate { line, series, rate } => {
+                write!(f, "Invalid {} rate on line {}: {}", series, line, rate)
+            }
+            Self::UnsupportedMultiplier { series, multiplier } => write!(
+                f,
+                "Series {} has unsupported unit multiplier {}",
+                series, multiplier
+            ),
+        }
+    }
+}
+
+impl Error for SeriesFileError {}
+
+impl From<csv::Error> for SeriesFileError {
+    fn from(error: csv::Error) -> Self {
+        SeriesFileError::Csv(error)
+    }
+}
+
+/// The daily rates of one currency pair as published by a statistics provider, such as the
+/// Bundesbank or the Federal Reserve, together with the metadata that describes the series.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateSeries {
+    id: String,
+    pair: CurrencyPair,
+    metadata: Vec<(String, String)>,
+    rates: HashMap<NaiveDate, Decimal>,
+    lines: HashMap<NaiveDate, u64>,
+    origin: Arc<RateOrigin>,
+}
+
+impl RateSeries {
+    pub fn new(id: String, pair: CurrencyPair, metadata: Vec<(String, String)>) -> Self {
+        RateSeries {
+            id,
+            pair,
+            metadata,
+            rates: HashMap::new(),
+            lines: HashMap::new(),
+            origin: Arc::default(),
+        }
+    }
+
+    /// Records that the rates came from `origin`.
+    pub fn with_origin(self, origin: RateOrigin) -> Self {
+        RateSeries {
+            origin: Arc::new(origin),
+            ..self
+        }
+    }
+
+    /// The provider's key of the series, e.g. `BBEX3.D.USD.EUR.BB.AC.000`.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// How the rates are quoted: each rate is the price of one base in the quote currency.
+    pub fn pair(&self) -> CurrencyPair {
+        self.pair
+    }
+
+    /// The metadata lines of the series, in the order of the file.
+    pub fn metadata(&self) -> &[(String, String)] {
+        &self.metadata
+    }
+
+    pub fn rates(&self) -> impl Iterator<Item = (&NaiveDate, &Decimal)> {
+        self.rates.iter()
+    }
+
+    pub fn rate(&self, date: &NaiveDate) -> Option<Decimal> {
+        self.rates.get(date).copied()
+    }
+
+    /// The rates as rows of a file in date order, to check them with
+    /// [`RateChecks::check_rates`](crate::conversions::validation::RateChecks::check_rates).
+    pub fn rows(&self) -> Vec<RateRow> {
+        let mut rows: Vec<_> = self
+            .rates
+            .iter()
+            .map(|(date, rate)| RateRow {
+                line: self.lines.get(date).copied().unwrap_or(0),
+                date: *date,
+                rate: rate.to_string(),
+            })
+            .collect();
+        rows.sort_by_key(|row| row.date);
+        rows
+    }
+
+    pub fn len(&self) -> usize {
+        self.rates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rates.is_empty()
+    }
+
+    /// Sets the rate of `date`, read from `line` of the file if given.
+    pub fn insert(&mut self, date: NaiveDate, rate: Decimal, line: Option<u64>) {
+        self.rates.insert(date, rate);
+        if let Some(line) = line {
+            self.lines.insert(date, line);
+        }
+    }
+
+    /// The rates for converting `D` to `N`, inverted if the series is quoted the other way
+    /// round. Returns `None` if the series is for another pair or a rate is out of range.
+    pub fn daily<N, D>(&self) -> Option<DailyExchangeRates<N, D>>
+    where
+        N: CurrencyType + for<'de> Deserialize<'de>,
+        D: CurrencyType + for<'de> Deserialize<'de>,
+    {
+        let one = Decimal::new(1, 0);
+        let expected = CurrencyPair::new(D::currency(), N::currency());
+        let rates: DateMap<_> = if self.pair == expected {
+            self.rates
+                .iter()
+                .map(|(date, rate)| Some((*date, ExchangeRate::from_quotient(*rate, one)?)))
+                .collect::<Option<_>>()?
+        } else if self.pair == expected.invert() {
+            self.rates
+                .iter()
+                .map(|(date, rate)| Some((*date, ExchangeRate::from_quotient(one, *rate)?)))
+                .collect::<Option<_>>()?
+        } else {
+            return None;
+        };
+        let provenance: Vec<_> = rates
+            .iter()
+            .map(|(date, _)| {
+                let line = self.lines.get(date).copied();
+                (*date, Provenance::new(self.origin.clone(), line))
+            })
+            .collect();
+
+        Some(DailyExchangeRates::new(rates).with_provenance(provenance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversions::{
+        currency::{Currency, EUR, JPY, USD},
+        rate_source::RateSource,
+        transaction::Transaction,
+    };
+
+    #[test]
+    fn test_daily() {
+        let date = NaiveDate::from_ymd_opt(2024, 5, 3).unwrap();
+        let mut series =
+            RateSeries::new("RXI_N.B.JA".to_string(), "USD/JPY".parse().unwrap(), vec![]);
+        series.insert(date, "153.04".parse().unwrap(), Some(8));
+
+        let jpy_per_usd = series.daily::<JPY, USD>().unwrap();
+        assert_eq!(
+            jpy_per_usd.convert(&Transaction::new(date, Currency::<USD>::from(10))),
+            Ok(Transaction::new(date, Currency::from(1530)))
+        );
+
+        let usd_per_jpy = series.daily::<USD, JPY>().unwrap();
+        assert_eq!(
+            usd_per_jpy.convert(&Transaction::new(date, Currency::<JPY>::from(15304))),
+            Ok(Transaction::new(date, Currency::from(100)))
+        );
+
+        assert_eq!(series.daily::<USD, EUR>(), None);
+    }
+}
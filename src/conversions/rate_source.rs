@@ -0,0 +1,235 @@
+use std::fmt::Display;
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::{
+    conversions::{
+        bid_ask_rates::RateSide,
+        currency::CurrencyType,
+        daily_exchange_rates::{ConversionError, DailyExchangeRates},
+        decimal::Decimal,
+        exchange_rate::ExchangeRate,
+        period::Period,
+        provenance::Provenance,
+        rounding::RoundingMode,
+        transaction::Transaction,
+    },
+    currency,
+};
+
+/// Anything that can look up the rate for converting `D` to `N` on a given date.
+pub trait RateSource<N, D>
+where
+    N: CurrencyType,
+    D: CurrencyType,
+{
+    fn quote(&self, date: &NaiveDate) -> Result<RateQuote<N, D>, ConversionError>;
+
+    /// The rate of `side`. Sources that publish a single rate per day return it for every
+    /// side, marked as the mid rate.
+    fn quote_side(
+        &self,
+        date: &NaiveDate,
+        _side: RateSide,
+    ) -> Result<RateQuote<N, D>, ConversionError> {
+        self.quote(date)
+    }
+
+    fn convert(&self, transaction: &Transaction<D>) -> Result<Transaction<N>, ConversionError> {
+        self.convert_with(transaction, RoundingMode::default())
+    }
+
+    /// Converts `transaction` with the rate of its date, rounding with `mode`.
+    fn convert_with(
+        &self,
+        transaction: &Transaction<D>,
+        mode: RoundingMode,
+    ) -> Result<Transaction<N>, ConversionError> {
+        let amount = self
+            .quote(transaction.date())?
+            .rate()
+            .checked_convert_with(transaction.amount(), mode)
+            .ok_or(ConversionError::AmountOverflow)?;
+
+        Ok(transaction.with_amount(amount))
+    }
+}
+
+impl<N, D, S> RateSource<N, D> for Box<S>
+where
+    N: CurrencyType,
+    D: CurrencyType,
+    S: RateSource<N, D> + ?Sized,
+{
+    fn quote(&self, date: &NaiveDate) -> Result<RateQuote<N, D>, ConversionError> {
+        self.as_ref().quote(date)
+    }
+
+    fn quote_side(
+        &self,
+        date: &NaiveDate,
+        side: RateSide,
+    ) -> Result<RateQuote<N, D>, ConversionError> {
+        self.as_ref().quote_side(date, side)
+    }
+}
+
+/// A table with rates of several pairs, such as a wide rates file or a rate store, that
+/// hands out the daily rates of single pairs.
+pub trait RateTable {
+    /// The daily rates for converting `D` to `N`, or `None` if the table cannot give them.
+    fn pair<N, D>(&self) -> Option<DailyExchangeRates<N, D>>
+    where
+        N: CurrencyType + for<'de> Deserialize<'de>,
+        D: CurrencyType + for<'de> Deserialize<'de>;
+}
+
+/// A rate returned by a [`RateSource`], together with how it was obtained.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateQuote<N, D>
+where
+    N: CurrencyType,
+    D: CurrencyType,
+{
+    rate: ExchangeRate<N, D>,
+    dates: Vec<NaiveDate>,
+    period: Option<Period>,
+    side: RateSide,
+    legs: Vec<RateLeg>,
+    provenance: Vec<Provenance>,
+}
+
+impl<N, D> RateQuote<N, D>
+where
+    N: CurrencyType,
+    D: CurrencyType,
+{
+    /// A rate that was published for `date`.
+    pub fn direct(rate: ExchangeRate<N, D>, date: NaiveDate) -> Self {
+        RateQuote {
+            rate,
+            dates: vec![date],
+            period: None,
+            side: RateSide::Mid,
+            legs: vec![],
+            provenance: vec![],
+        }
+    }
+
+    /// A rate that was interpolated between the rates published for `dates`.
+    pub fn interpolated(rate: ExchangeRate<N, D>, dates: [NaiveDate; 2]) -> Self {
+        RateQuote {
+            rate,
+            dates: dates.to_vec(),
+            period: None,
+            side: RateSide::Mid,
+            legs: vec![],
+            provenance: vec![],
+        }
+    }
+
+    /// A rate that was derived from the given published rates, which are of `dates`.
+    pub fn derived(rate: ExchangeRate<N, D>, legs: Vec<RateLeg>, dates: Vec<NaiveDate>) -> Self {
+        RateQuote {
+            rate,
+            dates,
+            period: None,
+            side: RateSide::Mid,
+            legs,
+            provenance: vec![],
+        }
+    }
+
+    /// A rate that was published for a whole `period`.
+    pub fn for_period(rate: ExchangeRate<N, D>, period: Period) -> Self {
+        RateQuote {
+            rate,
+            dates: vec![],
+            period: Some(period),
+            side: RateSide::Mid,
+            legs: vec![],
+            provenance: vec![],
+        }
+    }
+
+    pub fn with_side(self, side: RateSide) -> Self {
+        RateQuote { side, ..self }
+    }
+
+    /// Records where the published rates that the rate is based on came from.
+    pub fn with_provenance(self, provenance: Vec<Provenance>) -> Self {
+        RateQuote { provenance, ..self }
+    }
+
+    pub fn rate(&self) -> &ExchangeRate<N, D> {
+        &self.rate
+    }
+
+    /// The dates of the published daily rates that the rate is based on. This is the date
+    /// of the conversion unless the rate source fell back to another day.
+    pub fn dates(&self) -> &[NaiveDate] {
+        &self.dates
+    }
+
+    /// The period of the rate, if it is a monthly, quarterly or yearly rate.
+    pub fn period(&self) -> Option<Period> {
+        self.period
+    }
+
+    pub fn side(&self) -> RateSide {
+        self.side
+    }
+
+    /// The published rates the rate was derived from, or nothing for a direct rate.
+    pub fn legs(&self) -> &[RateLeg] {
+        &self.legs
+    }
+
+    /// Where the published rates came from, as far as the rate source knows.
+    pub fn provenance(&self) -> &[Provenance] {
+        &self.provenance
+    }
+}
+
+/// A published rate that a derived rate was computed from, e.g. `EUR/USD 1.0837` for the
+/// price of one EUR in USD.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RateLeg {
+    base: currency::Currency,
+    quote: currency::Currency,
+    rate: Decimal,
+}
+
+impl RateLeg {
+    /// Returns `None` if the rate cannot be written as a decimal.
+    pub fn new<N, D>(rate: &ExchangeRate<N, D>) -> Option<Self>
+    where
+        N: CurrencyType,
+        D: CurrencyType,
+    {
+        Some(RateLeg {
+            base: D::currency(),
+            quote: N::currency(),
+            rate: rate.to_decimal()?,
+        })
+    }
+
+    pub fn base(&self) -> currency::Currency {
+        self.base
+    }
+
+    pub fn quote(&self) -> currency::Currency {
+        self.quote
+    }
+
+    pub fn rate(&self) -> Decimal {
+        self.rate
+    }
+}
+
+impl Display for RateLeg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{} {}", self.base, self.quote, self.rate)
+    }
+}
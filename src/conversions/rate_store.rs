@@ -0,0 +1,554 @@
+use std::{collections::HashMap, error::Error, fmt::Display, ops::Bound, sync::Arc};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use csv::{Reader, Writer};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    conversions::{
+        calendar::BusinessCalendar,
+        currency::CurrencyType,
+        daily_exchange_rates::DailyExchangeRates,
+        date_map::DateMap,
+        decimal::{pow10, Decimal},
+        exchange_rate::ExchangeRate,
+        provenance::{Provenance, RateOrigin},
+        rate_source::RateTable,
+        rounding::RoundingMode,
+    },
+    currency::{Currency, CurrencyPair},
+};
+
+/// A rate as kept in a [`RateStore`]: the price of one base in the quote currency on a
+/// date, where it came from and when it was retrieved from there.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct StoredRate {
+    pub date: NaiveDate,
+    pub base: Currency,
+    pub quote: Currency,
+    #[serde(deserialize_with = "deserialize_rate")]
+    pub rate: Decimal,
+    pub source: String,
+    #[serde(default)]
+    pub retrieved: Option<DateTime<Utc>>,
+}
+
+impl StoredRate {
+    pub fn pair(&self) -> CurrencyPair {
+        CurrencyPair::new(self.base, self.quote)
+    }
+
+    pub fn provenance(&self) -> Provenance {
+        let origin = RateOrigin {
+            file: None,
+            provider: Some(self.source.clone()),
+            retrieved: self.retrieved,
+        };
+        Provenance::new(Arc::new(origin), None)
+    }
+}
+
+/// Reads a stored rate, which is never zero or negative.
+fn deserialize_rate<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let rate = Decimal::deserialize(deserializer)?;
+    if rate.mantissa() <= 0 {
+        return Err(serde::de::Error::custom(format!(
+            "rate {} is not positive",
+            rate
+        )));
+    }
+    Ok(rate)
+}
+
+/// Two sources that disagree about the rate of a pair on a date.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateConflict {
+    pub stored: StoredRate,
+    pub rejected: StoredRate,
+}
+
+impl Display for RateConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} on {}: {} from {} conflicts with {} from {}",
+            self.stored.pair(),
+            self.stored.date,
+            self.rejected.rate,
+            self.rejected.source,
+            self.stored.rate,
+            self.stored.source
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    Csv(csv::Error),
+    /// Rows with another rate for a pair and date than an earlier row, either way round.
+    Conflicts(Vec<RateConflict>),
+}
+
+impl Display for StoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Csv(error) => write!(f, "{}", error),
+            Self::Conflicts(conflicts) => {
+                write!(f, "Conflicting stored rates:")?;
+                for conflict in conflicts {
+                    write!(f, "\n{}", conflict)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for StoreError {}
+
+impl From<csv::Error> for StoreError {
+    fn from(error: csv::Error) -> Self {
+        StoreError::Csv(error)
+    }
+}
+
+/// What merging rates into a [`RateStore`] did.
+#[derive(Debug, Default, PartialEq)]
+pub struct MergeSummary {
+    pub added: usize,
+    /// Rates that were already stored with the same value, possibly from another source.
+    pub unchanged: usize,
+    /// Rates that were not stored because a different value is stored already.
+    pub conflicts: Vec<RateConflict>,
+}
+
+/// The first and last date a pair has rates for, and how many it has.
+#[derive(Debug, PartialEq)]
+pub struct PairCoverage {
+    pub pair: CurrencyPair,
+    pub first: NaiveDate,
+    pub last: NaiveDate,
+    pub rates: usize,
+    /// The number of business days in between without a rate, if checked against a
+    /// calendar.
+    pub missing: Option<usize>,
+    pub sources: Vec<String>,
+}
+
+/// Rates of any number of pairs collected from several sources, at most one per pair and
+/// date, whichever way round the pair is quoted. Every rate is kept as it was quoted by
+/// its source.
+#[derive(Debug, Default, PartialEq)]
+pub struct RateStore {
+    rates: HashMap<CurrencyPair, DateMap<StoredRate>>,
+}
+
+impl RateStore {
+    pub fn new() -> Self {
+        RateStore::default()
+    }
+
+    /// Adds `rate` unless a rate for its pair and date is stored already, either way round.
+    /// Returns whether it was added, or the conflict if the stored rate has another value.
+    /// A rate stored the other way round has the same value if its inverse, rounded to the
+    /// decimal places of `rate`, is `rate`.
+    pub fn insert(&mut self, rate: StoredRate) -> Result<bool, Box<RateConflict>> {
+        let conflict = |stored: &StoredRate, rate| {
+            Err(Box::new(RateConflict {
+                stored: stored.clone(),
+                rejected: rate,
+            }))
+        };
+
+        if let Some(stored) = self.get(rate.pair().invert(), &rate.date) {
+            return match inverse(stored.rate, rate.rate.scale()) {
+                Some(inverse) if inverse == rate.rate => Ok(false),
+                _ => conflict(stored, rate),
+            };
+        }
+
+        let day_rates = self.rates.entry(rate.pair()).or_default();
+        match day_rates.get(&rate.date) {
+            Some(stored) if stored.rate == rate.rate => Ok(false),
+            Some(stored) => conflict(stored, rate),
+            None => {
+                day_rates.insert(rate.date, rate);
+                Ok(true)
+            }
+        }
+    }
+
+    /// Adds all of `rates`, keeping the stored rate wherever they conflict.
+    pub fn merge(&mut self, rates: impl IntoIterator<Item = StoredRate>) -> MergeSummary {
+        let mut summary = MergeSummary::default();
+        for rate in rates {
+            match self.insert(rate) {
+                Ok(true) => summary.added += 1,
+                Ok(false) => summary.unchanged += 1,
+                Err(conflict) => summary.conflicts.push(*conflict),
+            }
+        }
+        summary
+    }
+
+    /// The pairs with rates, sorted.
+    pub fn pairs(&self) -> Vec<CurrencyPair> {
+        let mut pairs: Vec<_> = self.rates.keys().copied().collect();
+        pairs.sort_by_key(|pair| (pair.base().code(), pair.quote().code()));
+        pairs
+    }
+
+    /// All rates, in date order per pair.
+    pub fn rates(&self) -> impl Iterator<Item = &StoredRate> {
+        self.rates
+            .values()
+            .flat_map(|day_rates| day_rates.iter().map(|(_, rate)| rate))
+    }
+
+    /// The rates of `pair` by date, e.g. to find the gaps between them.
+    pub fn pair_rates(&self, pair: CurrencyPair) -> Option<&DateMap<StoredRate>> {
+        self.rates.get(&pair)
+    }
+
+    pub fn get(&self, pair: CurrencyPair, date: &NaiveDate) -> Option<&StoredRate> {
+        self.rates.get(&pair)?.get(date)
+    }
+
+    /// The rates of `pair` from `from` to `to`, both included, in date order. Rates stored
+    /// the other way round are included as they were stored.
+    pub fn range(
+        &self,
+        pair: CurrencyPair,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> impl Iterator<Item = &StoredRate> {
+        let mut rates: Vec<_> = self
+            .stored_range(pair, from, to)
+            .chain(self.stored_range(pair.invert(), from, to))
+            .collect();
+        rates.sort_by_key(|rate| rate.date);
+        rates.into_iter()
+    }
+
+    /// The rates stored as `pair` from `from` to `to`, both included, in date order.
+    fn stored_range(
+        &self,
+        pair: CurrencyPair,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> impl Iterator<Item = &StoredRate> {
+        let bounds = (
+            from.map_or(Bound::Unbounded, Bound::Included),
+            to.map_or(Bound::Unbounded, Bound::Included),
+        );
+        self.rates
+            .get(&pair)
+            .into_iter()
+            .flat_map(move |day_rates| day_rates.range(bounds).map(|(_, rate)| rate))
+    }
+
+    /// The coverage of every pair, sorted by pair, with the business days of `calendar`
+    /// that have no rate if given.
+    pub fn coverage(&self, calendar: Option<&BusinessCalendar>) -> Vec<PairCoverage> {
+        self.pairs()
+            .into_iter()
+            .filter_map(|pair| {
+                let rates = self.rates.get(&pair)?;
+                let mut sources: Vec<String> =
+                    rates.iter().map(|(_, rate)| rate.source.clone()).collect();
+                sources.sort();
+                sources.dedup();
+                Some(PairCoverage {
+                    pair,
+                    first: *rates.first()?.0,
+                    last: *rates.last()?.0,
+                    rates: rates.len(),
+                    missing: calendar.map(|calendar| calendar.missing_days(rates).len()),
+                    sources,
+                })
+            })
+            .collect()
+    }
+
+    /// A currency that both `a` and `b` are quoted against, either way round, to derive
+    /// cross rates through.
+    pub fn common_currency(&self, a: Currency, b: Currency) -> Option<Currency> {
+        let counterparts = |currency: Currency| {
+            self.pairs().into_iter().filter_map(move |pair| {
+                if pair.base() == currency {
+                    Some(pair.quote())
+                } else if pair.quote() == currency {
+                    Some(pair.base())
+                } else {
+                    None
+                }
+            })
+        };
+        let of_b: Vec<_> = counterparts(b).collect();
+        counterparts(a).find(|currency| of_b.contains(currency))
+    }
+
+    /// Reads the rates written by [`RateStore::write_to_csv`]. Fails with the conflicts if
+    /// a pair has different rates for a date, as [`RateStore::insert`] would report them.
+    pub fn read_from_csv<R>(mut reader: Reader<R>) -> Result<Self, StoreError>
+    where
+        R: std::io::Read,
+    {
+        let mut store = RateStore::new();
+        let mut conflicts = Vec::new();
+        for rate in reader.deserialize() {
+            if let Err(conflict) = store.insert(rate?) {
+                conflicts.push(*conflict);
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(StoreError::Conflicts(conflicts));
+        }
+        Ok(store)
+    }
+
+    /// Writes all rates sorted by pair and date.
+    pub fn write_to_csv<W>(&self, mut writer: Writer<W>) -> Result<(), csv::Error>
+    where
+        W: std::io::Write,
+    {
+        for pair in self.pairs() {
+            for rate in self.stored_range(pair, None, None) {
+                writer.serialize(rate)?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl RateTable for RateStore {
+    /// The rates of `D` in `N`, from the rates stored either way round.
+    fn pair<N, D>(&self) -> Option<DailyExchangeRates<N, D>>
+    where
+        N: CurrencyType + for<'de> Deserialize<'de>,
+        D: CurrencyType + for<'de> Deserialize<'de>,
+    {
+        let one = Decimal::new(1, 0);
+        let expected = CurrencyPair::new(D::currency(), N::currency());
+        if self
+            .pairs()
+            .iter()
+            .all(|pair| *pair != expected && *pair != expected.invert())
+        {
+            return None;
+        }
+
+        let stored: Vec<_> = self.range(expected, None, None).collect();
+        let rates: DateMap<_> = stored
+            .iter()
+            .map(|rate| {
+                let rate_value = if rate.pair() == expected {
+                    ExchangeRate::from_quotient(rate.rate, one)
+                } else {
+                    ExchangeRate::from_quotient(one, rate.rate)
+                };
+                Some((rate.date, rate_value?))
+            })
+            .collect::<Option<_>>()?;
+        let provenance = stored.iter().map(|rate| (rate.date, rate.provenance()));
+
+        Some(DailyExchangeRates::new(rates).with_provenance(provenance))
+    }
+}
+
+/// The inverse of `rate` rounded half up to `scale` decimal places, or `None` if `rate` is
+/// zero.
+fn inverse(rate: Decimal, scale: u32) -> Option<Decimal> {
+    if rate.mantissa() == 0 {
+        return None;
+    }
+    let numerator = pow10(scale.checked_add(rate.scale())?)?;
+    Some(Decimal::new(
+        RoundingMode::HalfUp.checked_divide(numerator, rate.mantissa())?,
+        scale,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversions::currency::{EUR, USD};
+
+    fn rate(day: u32, pair: &str, rate: &str, source: &str) -> StoredRate {
+        let pair: CurrencyPair = pair.parse().unwrap();
+        StoredRate {
+            date: NaiveDate::from_ymd_opt(2024, 5, day).unwrap(),
+            base: pair.base(),
+            quote: pair.quote(),
+            rate: rate.parse().unwrap(),
+            source: source.to_string(),
+            retrieved: None,
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut store = RateStore::new();
+        store.merge([rate(2, "EUR/USD", "1.0702", "ecb")]);
+
+        let summary = store.merge([
+            rate(2, "EUR/USD", "1.07020", "bundesbank"),
+            rate(3, "EUR/USD", "1.0773", "bundesbank"),
+            rate(2, "EUR/USD", "1.0703", "other"),
+        ]);
+
+        assert_eq!(summary.added, 1);
+        assert_eq!(summary.unchanged, 1);
+        assert_eq!(
+            summary.conflicts,
+            vec![RateConflict {
+                stored: rate(2, "EUR/USD", "1.0702", "ecb"),
+                rejected: rate(2, "EUR/USD", "1.0703", "other"),
+            }]
+        );
+        assert_eq!(
+            store.coverage(None),
+            vec![PairCoverage {
+                pair: "EUR/USD".parse().unwrap(),
+                first: rate(2, "EUR/USD", "1", "").date,
+                last: rate(3, "EUR/USD", "1", "").date,
+                rates: 2,
+                missing: None,
+                sources: vec!["bundesbank".to_string(), "ecb".to_string()],
+            }]
+        );
+
+        let (first, last) = (
+            rate(2, "EUR/USD", "1", "").date,
+            rate(3, "EUR/USD", "1", "").date,
+        );
+        let pair = "EUR/USD".parse().unwrap();
+        assert_eq!(store.range(pair, Some(first), Some(last)).count(), 2);
+        assert_eq!(store.range(pair, Some(last), Some(first)).count(), 0);
+    }
+
+    #[test]
+    fn test_merge_inverted() {
+        let mut store = RateStore::new();
+        store.merge([rate(2, "EUR/USD", "1.0702", "ecb")]);
+
+        let summary = store.merge([
+            rate(2, "USD/EUR", "0.9344", "fed"),
+            rate(2, "USD/EUR", "0.9400", "other"),
+            rate(3, "USD/EUR", "0.9283", "fed"),
+        ]);
+
+        assert_eq!(summary.added, 1);
+        assert_eq!(summary.unchanged, 1);
+        assert_eq!(
+            summary.conflicts,
+            vec![RateConflict {
+                stored: rate(2, "EUR/USD", "1.0702", "ecb"),
+                rejected: rate(2, "USD/EUR", "0.9400", "other"),
+            }]
+        );
+        assert_eq!(
+            store
+                .range("USD/EUR".parse().unwrap(), None, None)
+                .cloned()
+                .collect::<Vec<_>>(),
+            vec![
+                rate(2, "EUR/USD", "1.0702", "ecb"),
+                rate(3, "USD/EUR", "0.9283", "fed")
+            ]
+        );
+
+        let usd_per_eur = store.pair::<USD, EUR>().unwrap();
+        assert_eq!(
+            usd_per_eur.day_rate(&rate(2, "EUR/USD", "1", "").date),
+            Some(&ExchangeRate::new(1.0702))
+        );
+        assert_eq!(
+            usd_per_eur.day_rate(&rate(3, "EUR/USD", "1", "").date),
+            Some(&ExchangeRate::new(0.9283).invert())
+        );
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let mut store = RateStore::new();
+        store.merge([
+            rate(3, "USD/JPY", "153.04", "fed"),
+            rate(2, "EUR/USD", "1.0702", "ecb"),
+        ]);
+
+        let mut buffer = vec![];
+        store
+            .write_to_csv(Writer::from_writer(&mut buffer))
+            .unwrap();
+        let csv = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            csv,
+            "date,base,quote,rate,source,retrieved\n\
+             2024-05-02,EUR,USD,1.0702,ecb,\n\
+             2024-05-03,USD,JPY,153.04,fed,\n"
+        );
+
+        let read = RateStore::read_from_csv(Reader::from_reader(csv.as_bytes())).unwrap();
+        assert_eq!(read, store);
+
+        let without_retrieved = "date,base,quote,rate,source\n2024-05-02,EUR,USD,1.0702,ecb\n";
+        let read =
+            RateStore::read_from_csv(Reader::from_reader(without_retrieved.as_bytes())).unwrap();
+        assert_eq!(read.rates().count(), 1);
+    }
+
+    #[test]
+    fn test_read_invalid_csv() {
+        let read = |csv: &str| RateStore::read_from_csv(Reader::from_reader(csv.as_bytes()));
+
+        let zero = "date,base,quote,rate,source\n2024-05-02,EUR,USD,0,ecb\n";
+        assert!(matches!(read(zero), Err(StoreError::Csv(_))));
+        let negative = "date,base,quote,rate,source\n2024-05-02,EUR,USD,-1.07,ecb\n";
+        assert!(matches!(read(negative), Err(StoreError::Csv(_))));
+
+        let duplicates = "date,base,quote,rate,source\n\
+                          2024-05-02,EUR,USD,1.0702,ecb\n\
+                          2024-05-02,EUR,USD,1.0702,fed\n\
+                          2024-05-02,USD,EUR,0.8,fed\n";
+        match read(duplicates) {
+            Err(StoreError::Conflicts(conflicts)) => assert_eq!(
+                conflicts,
+                vec![RateConflict {
+                    stored: rate(2, "EUR/USD", "1.0702", "ecb"),
+                    rejected: rate(2, "USD/EUR", "0.8", "fed"),
+                }]
+            ),
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pair() {
+        let mut store = RateStore::new();
+        store.merge([
+            rate(2, "EUR/USD", "1.0702", "ecb"),
+            rate(2, "USD/JPY", "155.95", "fed"),
+        ]);
+
+        let eur_per_usd = store.pair::<EUR, USD>().unwrap();
+        assert_eq!(
+            eur_per_usd.day_rate(&rate(2, "EUR/USD", "1", "").date),
+            Some(&ExchangeRate::new(1.0702).invert())
+        );
+        assert_eq!(
+            eur_per_usd
+                .provenance(&rate(2, "EUR/USD", "1", "").date)
+                .map(ToString::to_string),
+            Some("ecb".to_string())
+        );
+        assert_eq!(
+            store.common_currency(Currency::EUR, Currency::JPY),
+            Some(Currency::USD)
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// How to round an amount that does not fit into the minor units of its currency.
+///
+/// The same mode is applied when parsing amounts, when converting them with an
+/// [`ExchangeRate`](crate::conversions::exchange_rate::ExchangeRate) and when writing them out.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoundingMode {
+    /// Round to the nearest value, ties to the even neighbour ("banker's rounding").
+    HalfEven,
+    /// Round to the nearest value, ties away from zero ("kaufmännisches Runden").
+    #[default]
+    HalfUp,
+    /// Drop the excess digits.
+    TowardZero,
+}
+
+impl RoundingMode {
+    /// Divides `numerator` by `denominator` and rounds the quotient to an integer. Returns
+    /// `None` if `denominator` is zero or the quotient overflows.
+    pub fn checked_divide(self, numerator: i128, denominator: i128) -> Option<i128> {
+        let quotient = numerator.checked_div(denominator)?;
+        let remainder = (numerator % denominator).unsigned_abs();
+
+        if remainder == 0 {
+            return Some(quotient);
+        }
+
+        let away_from_zero = if (numerator < 0) == (denominator < 0) {
+            quotient + 1
+        } else {
+            quotient - 1
+        };
+        let denominator = denominator.unsigned_abs();
+        Some(match (self, remainder.cmp(&(denominator - remainder))) {
+            (Self::TowardZero, _) => quotient,
+            (_, std::cmp::Ordering::Less) => quotient,
+            (_, std::cmp::Ordering::Greater) => away_from_zero,
+            (Self::HalfUp, std::cmp::Ordering::Equal) => away_from_zero,
+            (Self::HalfEven, std::cmp::Ordering::Equal) => {
+                if quotient % 2 == 0 {
+                    quotient
+                } else {
+                    away_from_zero
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_divide() {
+        let cases = [
+            // numerator, denominator, half-even, half-up, toward-zero
+            (25, 10, 2, 3, 2),
+            (35, 10, 4, 4, 3),
+            (-25, 10, -2, -3, -2),
+            (26, 10, 3, 3, 2),
+            (-24, 10, -2, -2, -2),
+            (7, -2, -4, -4, -3),
+            (20, 10, 2, 2, 2),
+        ];
+
+        for (numerator, denominator, half_even, half_up, toward_zero) in cases {
+            assert_eq!(
+                RoundingMode::HalfEven.checked_divide(numerator, denominator),
+                Some(half_even)
+            );
+            assert_eq!(
+                RoundingMode::HalfUp.checked_divide(numerator, denominator),
+                Some(half_up)
+            );
+            assert_eq!(
+                RoundingMode::TowardZero.checked_divide(numerator, denominator),
+                Some(toward_zero)
+            );
+        }
+    }
+
+    #[test]
+    fn test_divide_extremes() {
+        let mode = RoundingMode::HalfUp;
+        assert_eq!(mode.checked_divide(i128::MIN, -1), None);
+        assert_eq!(mode.checked_divide(1, 0), None);
+        assert_eq!(mode.checked_divide(i128::MIN, -2), Some(1 << 126));
+        assert_eq!(mode.checked_divide(i128::MIN, -3), Some(i128::MIN / -3 + 1));
+        assert_eq!(mode.checked_divide(i128::MIN, 3), Some(i128::MIN / 3 - 1));
+        assert_eq!(mode.checked_divide(i128::MAX, -1), Some(-i128::MAX));
+    }
+}
@@ -0,0 +1,283 @@
+use std::{fmt::Display, str::FromStr};
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, Offset,
+    TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A time zone: one of the IANA time zone database, with the rules it had at every date,
+/// or a fixed offset from UTC.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Zone(Rules);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Rules {
+    Named(Tz),
+    Fixed(FixedOffset),
+}
+
+impl Zone {
+    pub fn utc() -> Self {
+        Zone(Rules::Named(Tz::UTC))
+    }
+
+    pub fn fixed(offset: FixedOffset) -> Self {
+        Zone(Rules::Fixed(offset))
+    }
+
+    /// The offset from UTC at the instant `utc`.
+    pub fn offset(&self, utc: &DateTime<Utc>) -> FixedOffset {
+        match self.0 {
+            Rules::Named(tz) => tz.offset_from_utc_datetime(&utc.naive_utc()).fix(),
+            Rules::Fixed(offset) => offset,
+        }
+    }
+
+    /// The instant at which clocks in the zone show `local`. A time that occurs twice when
+    /// the clocks go back is taken the first time, and a time skipped when they go forward
+    /// is taken with the offset before, i.e. as the hour after.
+    pub fn instant(&self, local: &NaiveDateTime) -> DateTime<Utc> {
+        let tz = match self.0 {
+            Rules::Named(tz) => tz,
+            Rules::Fixed(offset) => return (*local - offset_duration(offset)).and_utc(),
+        };
+        match tz.from_local_datetime(local) {
+            LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => time.to_utc(),
+            LocalResult::None => {
+                let before = self.offset(&(*local - Duration::days(1)).and_utc());
+                (*local - offset_duration(before)).and_utc()
+            }
+        }
+    }
+
+    /// The time that clocks in the zone show at the instant `utc`.
+    pub fn local(&self, utc: &DateTime<Utc>) -> NaiveDateTime {
+        utc.naive_utc() + offset_duration(self.offset(utc))
+    }
+}
+
+fn offset_duration(offset: FixedOffset) -> Duration {
+    Duration::seconds(offset.local_minus_utc().into())
+}
+
+impl Display for Zone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Rules::Named(tz) => write!(f, "{}", tz.name()),
+            Rules::Fixed(offset) => write!(f, "{}", offset),
+        }
+    }
+}
+
+impl FromStr for Zone {
+    type Err = String;
+
+    /// Parses the name of a zone of the IANA time zone database, such as `Europe/Berlin`,
+    /// or an offset such as `+01:00`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(tz) = Tz::from_str_insensitive(s) {
+            return Ok(Zone(Rules::Named(tz)));
+        }
+        s.parse()
+            .map(Zone::fixed)
+            .map_err(|_| format!("Unknown time zone {}", s))
+    }
+}
+
+/// An instant, with the time zone it was recorded in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Timestamp {
+    utc: DateTime<Utc>,
+    zone: Zone,
+}
+
+impl Timestamp {
+    /// The instant at which clocks in `zone` show `local`.
+    pub fn new(local: NaiveDateTime, zone: Zone) -> Self {
+        Timestamp {
+            utc: zone.instant(&local),
+            zone,
+        }
+    }
+
+    pub fn from_utc(utc: DateTime<Utc>, zone: Zone) -> Self {
+        Timestamp { utc, zone }
+    }
+
+    /// Parses a date and time such as `2023-12-31 19:30:00` or `2023-12-31, 19:30:00`,
+    /// which is taken to be in `zone`, or one with an offset, such as
+    /// `2023-12-31T19:30:00-05:00`.
+    pub fn parse(text: &str, zone: Zone) -> Result<Self, String> {
+        if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+            return Ok(Timestamp::from_utc(
+                time.to_utc(),
+                Zone::fixed(*time.offset()),
+            ));
+        }
+        [
+            "%Y-%m-%d %H:%M:%S",
+            "%Y-%m-%dT%H:%M:%S",
+            "%Y-%m-%d, %H:%M:%S",
+            "%Y-%m-%d %H:%M",
+        ]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .map(|local| Timestamp::new(local, zone))
+        .ok_or_else(|| format!("Invalid date and time {}", text))
+    }
+
+    pub fn utc(&self) -> DateTime<Utc> {
+        self.utc
+    }
+
+    /// The zone the timestamp was recorded in.
+    pub fn zone(&self) -> Zone {
+        self.zone
+    }
+
+    /// The time as recorded, in its own zone.
+    pub fn local(&self) -> NaiveDateTime {
+        self.zone.local(&self.utc)
+    }
+
+    /// The calendar date in `zone`, e.g. the reporting time zone, which decides the rate
+    /// to convert with.
+    pub fn date_in(&self, zone: Zone) -> NaiveDate {
+        zone.local(&self.utc).date()
+    }
+
+    /// The year in `zone`, e.g. the tax year.
+    pub fn year_in(&self, zone: Zone) -> i32 {
+        self.date_in(zone).year()
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            self.local().format("%Y-%m-%d %H:%M:%S"),
+            self.zone
+        )
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = String;
+
+    /// Parses a timestamp as displayed, e.g. `2023-12-31 19:30:00 America/New_York`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (local, zone) = s
+            .rsplit_once(' ')
+            .ok_or_else(|| format!("Missing time zone in {}", s))?;
+        Timestamp::parse(local, zone.parse()?)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D>(deserializer: D) -> Result<Timestamp, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let timestamp = String::deserialize(deserializer)?;
+        timestamp.parse().map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_offsets() {
+        let new_york: Zone = "America/New_York".parse().unwrap();
+        let berlin: Zone = "europe/berlin".parse().unwrap();
+        let hours = |zone: Zone, time| zone.offset(&local(time).and_utc()).local_minus_utc() / 3600;
+
+        assert_eq!(hours(new_york, "2024-01-15 12:00"), -5);
+        assert_eq!(hours(new_york, "2024-03-10 06:59"), -5);
+        assert_eq!(hours(new_york, "2024-03-10 07:00"), -4);
+        assert_eq!(hours(new_york, "2024-11-03 05:59"), -4);
+        assert_eq!(hours(new_york, "2024-11-03 06:00"), -5);
+        assert_eq!(hours(berlin, "2024-03-31 00:59"), 1);
+        assert_eq!(hours(berlin, "2024-03-31 01:00"), 2);
+        assert_eq!(hours(berlin, "2024-10-27 01:00"), 1);
+        assert_eq!(hours("+05:30".parse().unwrap(), "2024-06-01 00:00"), 5);
+        // Daylight saving time started in April in the US before 2007.
+        assert_eq!(hours(new_york, "2006-03-20 23:45"), -5);
+        assert_eq!(
+            hours("Australia/Sydney".parse().unwrap(), "2024-01-15 12:00"),
+            11
+        );
+        assert!("Mars/Olympus_Mons".parse::<Zone>().is_err());
+    }
+
+    #[test]
+    fn test_instant() {
+        let new_york: Zone = "America/New_York".parse().unwrap();
+
+        // Skipped when the clocks go forward, so an hour later.
+        assert_eq!(
+            new_york.instant(&local("2024-03-10 02:30")),
+            local("2024-03-10 07:30").and_utc()
+        );
+        // Occurs twice when the clocks go back, the first time in daylight saving time.
+        assert_eq!(
+            new_york.instant(&local("2024-11-03 01:30")),
+            local("2024-11-03 05:30").and_utc()
+        );
+    }
+
+    #[test]
+    fn test_date_in() {
+        let new_york: Zone = "America/New_York".parse().unwrap();
+        let berlin: Zone = "Europe/Berlin".parse().unwrap();
+        let trade = Timestamp::new(local("2023-12-31 19:30"), new_york);
+
+        assert_eq!(trade.local(), local("2023-12-31 19:30"));
+        assert_eq!(trade.date_in(new_york), local("2023-12-31 00:00").date());
+        assert_eq!(trade.date_in(berlin), local("2024-01-01 00:00").date());
+        assert_eq!(trade.year_in(new_york), 2023);
+        assert_eq!(trade.year_in(berlin), 2024);
+        assert_eq!(trade.utc(), local("2024-01-01 00:30").and_utc());
+
+        let before_2007 = Timestamp::new(local("2006-03-20 18:45"), new_york);
+        assert_eq!(
+            before_2007.date_in(berlin),
+            local("2006-03-21 00:00").date()
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let new_york: Zone = "America/New_York".parse().unwrap();
+        let trade = Timestamp::parse("2023-12-31, 19:30:00", new_york).unwrap();
+        assert_eq!(trade.to_string(), "2023-12-31 19:30:00 America/New_York");
+        assert_eq!(trade.to_string().parse(), Ok(trade));
+
+        let with_offset = Timestamp::parse("2023-12-31T19:30:00-05:00", Zone::utc()).unwrap();
+        assert_eq!(with_offset.utc(), trade.utc());
+        assert_eq!(with_offset.to_string(), "2023-12-31 19:30:00 -05:00");
+        let in_utc = Timestamp::parse("2024-01-01T00:30:00Z", new_york).unwrap();
+        assert_eq!(in_utc.to_string(), "2024-01-01 00:30:00 +00:00");
+        assert_eq!(in_utc.to_string().parse(), Ok(in_utc));
+        assert_eq!(Zone::utc().to_string().parse(), Ok(Zone::utc()));
+        assert!(Timestamp::parse("2023-12-31", new_york).is_err());
+    }
+}
@@ -1,6 +1,9 @@
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display};
 
-use crate::conversions::currency::{Currency, CurrencyType};
+use crate::conversions::{
+    currency::{Currency, CurrencyType},
+    timezone::{Timestamp, Zone},
+};
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
@@ -11,6 +14,18 @@ where
 {
     date: NaiveDate,
     amount: Currency<T>,
+    /// When the transaction happened, if known, in the time zone it was recorded in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<Timestamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    category: Option<String>,
+    /// Anything else known about the transaction, such as a reference or counterparty.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
 }
 
 impl<T> Transaction<T>
@@ -18,7 +33,61 @@ where
     T: CurrencyType,
 {
     pub fn new(date: NaiveDate, amount: Currency<T>) -> Self {
-        Transaction { date, amount }
+        Transaction {
+            date,
+            amount,
+            timestamp: None,
+            id: None,
+            description: None,
+            category: None,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    /// A transaction at `timestamp`, dated by the calendar date in the `reporting` zone,
+    /// which decides the rate it is converted with and the year it belongs to.
+    pub fn at(timestamp: Timestamp, reporting: Zone, amount: Currency<T>) -> Self {
+        Transaction {
+            timestamp: Some(timestamp),
+            ..Transaction::new(timestamp.date_in(reporting), amount)
+        }
+    }
+
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn with_category(mut self, category: impl Into<String>) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// The same transaction with a different amount, such as the amount converted to
+    /// another currency.
+    pub fn with_amount<U>(&self, amount: Currency<U>) -> Transaction<U>
+    where
+        U: CurrencyType,
+    {
+        Transaction {
+            date: self.date,
+            amount,
+            timestamp: self.timestamp,
+            id: self.id.clone(),
+            description: self.description.clone(),
+            category: self.category.clone(),
+            metadata: self.metadata.clone(),
+        }
     }
 
     pub fn date(&self) -> &NaiveDate {
@@ -28,6 +97,26 @@ where
     pub fn amount(&self) -> Currency<T> {
         self.amount
     }
+
+    pub fn timestamp(&self) -> Option<&Timestamp> {
+        self.timestamp.as_ref()
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn category(&self) -> Option<&str> {
+        self.category.as_deref()
+    }
+
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
 }
 
 impl<T> Display for Transaction<T>
@@ -35,20 +124,65 @@ where
     T: CurrencyType,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}: {}", self.date.format("%Y-%m-%d"), self.amount)
+        write!(f, "{}: {}", self.date.format("%Y-%m-%d"), self.amount)?;
+        if let Some(description) = &self.description {
+            write!(f, " ({})", description)?;
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::conversions::currency::{Currency, EUR};
+    use crate::conversions::currency::{Currency, EUR, USD};
 
     #[test]
     fn test_transaction() {
         let date = NaiveDate::from_ymd_opt(2021, 1, 1).unwrap();
-        let amount: Currency<EUR> = 123.45.into();
+        let amount = Currency::<EUR>::try_from(123.45).unwrap();
         let transaction = Transaction::new(date, amount);
         assert_eq!(format!("{}", transaction), "2021-01-01: € 123.45");
     }
+
+    #[test]
+    fn test_with_amount() {
+        let date = NaiveDate::from_ymd_opt(2021, 1, 1).unwrap();
+        let transaction = Transaction::new(date, Currency::<EUR>::from(100))
+            .with_id("42")
+            .with_description("Rent")
+            .with_category("Housing")
+            .with_metadata("counterparty", "ACME GmbH");
+        assert_eq!(format!("{}", transaction), "2021-01-01: € 100.00 (Rent)");
+
+        let converted = transaction.with_amount(Currency::<USD>::from(108));
+        assert_eq!(converted.amount(), Currency::from(108));
+        assert_eq!(converted.id(), Some("42"));
+        assert_eq!(converted.description(), Some("Rent"));
+        assert_eq!(converted.category(), Some("Housing"));
+        assert_eq!(
+            converted.metadata().get("counterparty").map(String::as_str),
+            Some("ACME GmbH")
+        );
+    }
+
+    #[test]
+    fn test_at() {
+        let new_york: Zone = "America/New_York".parse().unwrap();
+        let berlin: Zone = "Europe/Berlin".parse().unwrap();
+        let timestamp = Timestamp::parse("2023-12-31 19:30:00", new_york).unwrap();
+
+        let transaction = Transaction::at(timestamp, berlin, Currency::<USD>::from(100));
+        assert_eq!(
+            transaction.date(),
+            &NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+        );
+        assert_eq!(transaction.timestamp(), Some(&timestamp));
+        assert_eq!(
+            transaction
+                .with_amount(Currency::<EUR>::from(90))
+                .timestamp(),
+            Some(&timestamp)
+        );
+    }
 }
@@ -0,0 +1,451 @@
+use std::{collections::HashMap, error::Error, fmt::Display, sync::Arc};
+
+use chrono::NaiveDate;
+use csv::Reader;
+use serde::Deserialize;
+
+use crate::conversions::{calendar::BusinessCalendar, date_map::DateMap, decimal::Decimal};
+
+/// A problem found in a file with one rate per day.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RateIssue {
+    DuplicateDate {
+        date: NaiveDate,
+        first_line: u64,
+        line: u64,
+    },
+    InvalidRate {
+        line: u64,
+        rate: String,
+    },
+    /// A rate like `NaN` or `inf`.
+    NonFiniteRate {
+        line: u64,
+        rate: String,
+    },
+    NonPositiveRate {
+        line: u64,
+        rate: Decimal,
+    },
+    /// A date out of the ascending or descending order of the dates before it.
+    Unsorted {
+        line: u64,
+        date: NaiveDate,
+        previous: NaiveDate,
+    },
+    /// A rate that differs from the one of the previous date by more than the threshold.
+    Jump {
+        line: u64,
+        date: NaiveDate,
+        previous: Decimal,
+        rate: Decimal,
+        percent: f64,
+    },
+    /// A business day of the calendar checked against that has no rate.
+    MissingBusinessDay {
+        date: NaiveDate,
+    },
+    /// A bid rate above the ask rate of the same day.
+    BidAboveAsk {
+        line: u64,
+        bid: Decimal,
+        ask: Decimal,
+    },
+}
+
+impl RateIssue {
+    /// Whether the file cannot be used with this issue. Unsorted dates, jumps and missing
+    /// business days are only suspicious.
+    pub fn is_error(&self) -> bool {
+        !matches!(
+            self,
+            RateIssue::Unsorted { .. }
+                | RateIssue::Jump { .. }
+                | RateIssue::MissingBusinessDay { .. }
+        )
+    }
+}
+
+impl Display for RateIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DuplicateDate {
+                date,
+                first_line,
+                line,
+            } => write!(
+                f,
+                "Line {}: duplicate rate for {}, first given on line {}",
+                line, date, first_line
+            ),
+            Self::InvalidRate { line, rate } => write!(f, "Line {}: invalid rate {}", line, rate),
+            Self::NonFiniteRate { line, rate } => {
+                write!(f, "Line {}: rate {} is not a finite number", line, rate)
+            }
+            Self::NonPositiveRate { line, rate } => {
+                write!(f, "Line {}: rate {} is not positive", line, rate)
+            }
+            Self::Unsorted {
+                line,
+                date,
+                previous,
+            } => write!(
+                f,
+                "Line {}: {} is out of order after {}",
+                line, date, previous
+            ),
+            Self::Jump {
+                line,
+                date,
+                previous,
+                rate,
+                percent,
+            } => write!(
+                f,
+                "Line {}: rate {} on {} differs from the previous rate {} by {:.2}%",
+                line, rate, date, previous, percent
+            ),
+            Self::MissingBusinessDay { date } => {
+                write!(f, "No rate for business day {}", date)
+            }
+            Self::BidAboveAsk { line, bid, ask } => {
+                write!(f, "Line {}: bid {} is above ask {}", line, bid, ask)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ValidationError {
+    Csv(csv::Error),
+    /// The issues that make the file unusable.
+    Invalid(Vec<RateIssue>),
+    /// A jump threshold that is not a positive number of percent.
+    InvalidMaxJump(f64),
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Csv(error) => write!(f, "{}", error),
+            Self::Invalid(issues) => {
+                write!(f, "Invalid rates:")?;
+                for issue in issues {
+                    write!(f, "\n{}", issue)?;
+                }
+                Ok(())
+            }
+            Self::InvalidMaxJump(max_jump) => write!(
+                f,
+                "The maximum jump must be a positive number of percent, not {}",
+                max_jump
+            ),
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+impl From<csv::Error> for ValidationError {
+    fn from(error: csv::Error) -> Self {
+        ValidationError::Csv(error)
+    }
+}
+
+/// A row of a file with `date` and `rate` columns, with the rate as written.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateRow {
+    pub line: u64,
+    pub date: NaiveDate,
+    pub rate: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawRow {
+    date: NaiveDate,
+    rate: String,
+}
+
+/// Reads the rows of a file with `date` and `rate` columns without parsing the rates.
+pub fn read_rows<R>(mut reader: Reader<R>) -> Result<Vec<RateRow>, csv::Error>
+where
+    R: std::io::Read,
+{
+    let mut rows = Vec::new();
+    let mut record = csv::StringRecord::new();
+    let headers = reader.headers()?.clone();
+
+    while reader.read_record(&mut record)? {
+        let line = record.position().map_or(0, |position| position.line());
+        let row: RawRow = record.deserialize(Some(&headers))?;
+        rows.push(RateRow {
+            line,
+            date: row.date,
+            rate: row.rate.trim().to_string(),
+        });
+    }
+    Ok(rows)
+}
+
+/// The checks to run on a file with one rate per day.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateChecks {
+    max_jump: Option<f64>,
+    calendar: Option<Arc<BusinessCalendar>>,
+}
+
+impl Default for RateChecks {
+    fn default() -> Self {
+        RateChecks {
+            max_jump: Some(10.0),
+            calendar: None,
+        }
+    }
+}
+
+impl RateChecks {
+    /// Reports day-over-day changes of more than `max_jump` percent, if given. Fails if
+    /// `max_jump` is not a finite, positive number.
+    pub fn new(max_jump: Option<f64>) -> Result<Self, ValidationError> {
+        match max_jump {
+            Some(max_jump) if !max_jump.is_finite() || max_jump <= 0.0 => {
+                Err(ValidationError::InvalidMaxJump(max_jump))
+            }
+            _ => Ok(RateChecks {
+                max_jump,
+                calendar: None,
+            }),
+        }
+    }
+
+    /// Also reports the business days of `calendar` without a rate, between the first and
+    /// the last date of the file.
+    pub fn with_calendar(self, calendar: Arc<BusinessCalendar>) -> Self {
+        RateChecks {
+            calendar: Some(calendar),
+            ..self
+        }
+    }
+
+    pub fn max_jump(&self) -> Option<f64> {
+        self.max_jump
+    }
+
+    /// All issues of `rows`, in the order of the file except for the jumps and then the
+    /// missing business days, which come last in the order of their dates.
+    pub fn check(&self, rows: &[RateRow]) -> Vec<RateIssue> {
+        let mut issues = Vec::new();
+        let mut first_lines: HashMap<NaiveDate, u64> = HashMap::new();
+        let mut rates: Vec<(&RateRow, Decimal)> = Vec::new();
+        let mut descending = None;
+
+        for (i, row) in rows.iter().enumerate() {
+            if let Some(&first_line) = first_lines.get(&row.date) {
+                issues.push(RateIssue::DuplicateDate {
+                    date: row.date,
+                    first_line,
+                    line: row.line,
+                });
+            } else {
+                first_lines.insert(row.date, row.line);
+            }
+
+            if let Some(previous) = i.checked_sub(1).map(|i| &rows[i]) {
+                if previous.date != row.date {
+                    let is_descending = row.date < previous.date;
+                    match descending {
+                        None => descending = Some(is_descending),
+                        Some(descending) if descending != is_descending => {
+                            issues.push(RateIssue::Unsorted {
+                                line: row.line,
+                                date: row.date,
+                                previous: previous.date,
+                            })
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+
+            match row.rate.parse::<Decimal>() {
+                Ok(rate) if rate.mantissa() <= 0 => issues.push(RateIssue::NonPositiveRate {
+                    line: row.line,
+                    rate,
+                }),
+                Ok(rate) => rates.push((row, rate)),
+                Err(_) if row.rate.parse::<f64>().is_ok_and(|rate| !rate.is_finite()) => issues
+                    .push(RateIssue::NonFiniteRate {
+                        line: row.line,
+                        rate: row.rate.clone(),
+                    }),
+                Err(_) => issues.push(RateIssue::InvalidRate {
+                    line: row.line,
+                    rate: row.rate.clone(),
+                }),
+            }
+        }
+
+        if let Some(max_jump) = self.max_jump {
+            rates.sort_by_key(|(row, _)| row.date);
+            rates.dedup_by_key(|(row, _)| row.date);
+            for pair in rates.windows(2) {
+                let [(_, previous), (row, rate)] = pair else {
+                    continue;
+                };
+                let percent = (rate.to_f64() / previous.to_f64() - 1.0) * 100.0;
+                if percent.abs() > max_jump {
+                    issues.push(RateIssue::Jump {
+                        line: row.line,
+                        date: row.date,
+                        previous: *previous,
+                        rate: *rate,
+                        percent,
+                    });
+                }
+            }
+        }
+
+        if let Some(calendar) = &self.calendar {
+            let dates: DateMap<()> = rows.iter().map(|row| (row.date, ())).collect();
+            issues.extend(
+                calendar
+                    .missing_days(&dates)
+                    .into_iter()
+                    .map(|date| RateIssue::MissingBusinessDay { date }),
+            );
+        }
+
+        issues
+    }
+
+    /// The issues of the rates in `rows` alone: invalid rates and jumps, but not duplicate
+    /// or unsorted dates. For the second and further rate columns of a file, whose dates
+    /// are checked with the first.
+    pub fn check_rates(&self, rows: &[RateRow]) -> Vec<RateIssue> {
+        let checks = RateChecks {
+            calendar: None,
+            ..self.clone()
+        };
+        checks
+            .check(rows)
+            .into_iter()
+            .filter(|issue| {
+                !matches!(
+                    issue,
+                    RateIssue::DuplicateDate { .. } | RateIssue::Unsorted { .. }
+                )
+            })
+            .collect()
+    }
+}
+
+/// Logs the issues that are only suspicious as warnings, and fails with the others.
+pub fn reject_errors(issues: Vec<RateIssue>) -> Result<(), ValidationError> {
+    let (errors, warnings): (Vec<_>, Vec<_>) = issues.into_iter().partition(RateIssue::is_error);
+    for warning in warnings {
+        log::warn!("{}", warning);
+    }
+    if !errors.is_empty() {
+        return Err(ValidationError::Invalid(errors));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(csv: &str) -> Vec<RateRow> {
+        read_rows(Reader::from_reader(csv.as_bytes())).unwrap()
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap()
+    }
+
+    #[test]
+    fn test_check() {
+        let rows = rows(
+            "date,rate
+2024-05-01,1.07
+2024-05-02,1.0702
+2024-05-02,1.0710
+2024-05-06,0
+2024-05-03,NaN
+2024-05-07,abc
+2024-05-08,1.30
+",
+        );
+
+        assert_eq!(
+            RateChecks::default().check(&rows),
+            vec![
+                RateIssue::DuplicateDate {
+                    date: date(2),
+                    first_line: 3,
+                    line: 4,
+                },
+                RateIssue::NonPositiveRate {
+                    line: 5,
+                    rate: "0".parse().unwrap(),
+                },
+                RateIssue::Unsorted {
+                    line: 6,
+                    date: date(3),
+                    previous: date(6),
+                },
+                RateIssue::NonFiniteRate {
+                    line: 6,
+                    rate: "NaN".to_string(),
+                },
+                RateIssue::InvalidRate {
+                    line: 7,
+                    rate: "abc".to_string(),
+                },
+                RateIssue::Jump {
+                    line: 8,
+                    date: date(8),
+                    previous: "1.0702".parse().unwrap(),
+                    rate: "1.30".parse().unwrap(),
+                    percent: (1.30 / 1.0702 - 1.0) * 100.0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_descending_dates() {
+        let descending = rows("date,rate\n2024-05-03,1.0773\n2024-05-02,1.0702\n");
+        assert_eq!(RateChecks::new(None).unwrap().check(&descending), vec![]);
+
+        let unsorted = rows("date,rate\n2024-05-03,1.0773\n2024-05-02,1.0702\n2024-05-06,1.0760\n");
+        assert!(matches!(
+            RateChecks::new(None).unwrap().check(&unsorted)[..],
+            [RateIssue::Unsorted { line: 4, .. }]
+        ));
+    }
+
+    #[test]
+    fn test_invalid_max_jump() {
+        for max_jump in [0.0, -5.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                RateChecks::new(Some(max_jump)),
+                Err(ValidationError::InvalidMaxJump(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_missing_business_days() {
+        let rows = rows("date,rate\n2024-05-03,1.0773\n2024-05-07,1.0755\n");
+        let calendar = BusinessCalendar::new("test".to_string(), []);
+
+        assert_eq!(
+            RateChecks::new(None)
+                .unwrap()
+                .with_calendar(Arc::new(calendar))
+                .check(&rows),
+            vec![RateIssue::MissingBusinessDay { date: date(6) }]
+        );
+    }
+}
@@ -0,0 +1,432 @@
+use std::{collections::HashMap, error::Error, fmt::Display, sync::Arc};
+
+use chrono::NaiveDate;
+use csv::Reader;
+use serde::Deserialize;
+
+use crate::{
+    conversions::{
+        currency::CurrencyType,
+        daily_exchange_rates::DailyExchangeRates,
+        date_map::DateMap,
+        decimal::Decimal,
+        exchange_rate::ExchangeRate,
+        provenance::{Provenance, RateOrigin},
+        rate_source::RateTable,
+        validation::{reject_errors, RateChecks, RateIssue, RateRow, ValidationError},
+    },
+    currency,
+};
+
+#[derive(Debug)]
+pub enum WideRatesError {
+    Csv(csv::Error),
+    MissingDateColumn,
+    InvalidDate {
+        line: u64,
+        date: String,
+    },
+    DuplicateDate {
+        date: NaiveDate,
+        first_line: u64,
+        line: u64,
+    },
+    InvalidRate {
+        line: u64,
+        currency: currency::Currency,
+        rate: String,
+    },
+    /// The issues that make the rates of a currency unusable.
+    Invalid {
+        currency: currency::Currency,
+        issues: Vec<RateIssue>,
+    },
+}
+
+impl Display for WideRatesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Csv(error) => write!(f, "{}", error),
+            Self::MissingDateColumn => write!(f, "The first column must be the date"),
+            Self::InvalidDate { line, date } => {
+                write!(f, "Invalid date on line {}: {}", line, date)
+            }
+            Self::DuplicateDate {
+                date,
+                first_line,
+                line,
+            } => write!(
+                f,
+                "Duplicate rates for {} on line {}, first given on line {}",
+                date, line, first_line
+            ),
+            Self::InvalidRate {
+                line,
+                currency,
+                rate,
+            } => write!(f, "Invalid {} rate on line {}: {}", currency, line, rate),
+            Self::Invalid { currency, issues } => {
+                write!(f, "Invalid {} rates:", currency)?;
+                for issue in issues {
+                    write!(f, "\n{}", issue)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for WideRatesError {}
+
+impl From<csv::Error> for WideRatesError {
+    fn from(error: csv::Error) -> Self {
+        WideRatesError::Csv(error)
+    }
+}
+
+/// Daily rates of many currencies against one base currency, as in the ECB's
+/// `eurofxref-hist.csv`: a `Date` column followed by one column per currency, each giving
+/// the price of one unit of the base currency in that currency.
+///
+/// Rates are kept exactly as published. Single pairs are handed out as
+/// [`DailyExchangeRates`] with [`RateTable::pair`].
+#[derive(Debug, PartialEq)]
+pub struct WideExchangeRates {
+    base: currency::Currency,
+    rates: HashMap<currency::Currency, HashMap<NaiveDate, Decimal>>,
+    lines: HashMap<NaiveDate, u64>,
+    origin: Arc<RateOrigin>,
+}
+
+impl WideExchangeRates {
+    /// An empty table of rates against `base`.
+    pub fn new(base: currency::Currency) -> Self {
+        WideExchangeRates {
+            base,
+            rates: HashMap::new(),
+            lines: HashMap::new(),
+            origin: Arc::default(),
+        }
+    }
+
+    /// Records that the rates came from `origin`.
+    pub fn with_origin(self, origin: RateOrigin) -> Self {
+        WideExchangeRates {
+            origin: Arc::new(origin),
+            ..self
+        }
+    }
+
+    /// Sets the price of one unit of the base currency in `currency` on `date`.
+    pub fn insert(&mut self, currency: currency::Currency, date: NaiveDate, rate: Decimal) {
+        self.rates.entry(currency).or_default().insert(date, rate);
+    }
+
+    pub fn base(&self) -> currency::Currency {
+        self.base
+    }
+
+    /// The currencies quoted against the base, sorted by code.
+    pub fn currencies(&self) -> Vec<currency::Currency> {
+        let mut currencies: Vec<_> = self.rates.keys().copied().collect();
+        currencies.sort_by_key(|currency| currency.code());
+        currencies
+    }
+
+    /// All rates, as the currency, the date and the price of one unit of the base currency
+    /// in it.
+    pub fn rates(&self) -> impl Iterator<Item = (currency::Currency, NaiveDate, Decimal)> + '_ {
+        self.rates.iter().flat_map(|(currency, rates)| {
+            rates
+                .iter()
+                .map(move |(date, rate)| (*currency, *date, *rate))
+        })
+    }
+
+    /// The price of one unit of the base currency in `currency` on `date`.
+    pub fn rate(&self, currency: currency::Currency, date: &NaiveDate) -> Option<Decimal> {
+        self.rates.get(&currency)?.get(date).copied()
+    }
+
+    /// Reads the wide format, quoted against `base`, checking the rates of every currency
+    /// with the default [`RateChecks`].
+    pub fn read_from_csv<R>(
+        reader: Reader<R>,
+        base: currency::Currency,
+    ) -> Result<Self, WideRatesError>
+    where
+        R: std::io::Read,
+    {
+        Self::read_checked(reader, base, &RateChecks::default())
+    }
+
+    /// Reads the wide format, quoted against `base`. Empty cells and `N/A` are gaps, as are
+    /// the empty columns that a trailing comma leaves. Columns of currencies we do not know,
+    /// such as ones that have been replaced by the EUR, are skipped with a warning. The
+    /// rates of every currency are checked with `checks`.
+    pub fn read_checked<R>(
+        mut reader: Reader<R>,
+        base: currency::Currency,
+        checks: &RateChecks,
+    ) -> Result<Self, WideRatesError>
+    where
+        R: std::io::Read,
+    {
+        let headers = reader.headers()?.clone();
+        if !headers
+            .get(0)
+            .is_some_and(|header| header.trim().eq_ignore_ascii_case("date"))
+        {
+            return Err(WideRatesError::MissingDateColumn);
+        }
+
+        let columns: Vec<(usize, currency::Currency)> = headers
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(_, header)| !header.trim().is_empty())
+            .filter_map(|(i, header)| match header.parse() {
+                Ok(currency) => Some((i, currency)),
+                Err(_) => {
+                    log::warn!("Skipping rates of unknown currency {}", header.trim());
+                    None
+                }
+            })
+            .collect();
+
+        let mut lines = HashMap::new();
+        let mut rows: HashMap<currency::Currency, Vec<RateRow>> = HashMap::new();
+        let mut rates: HashMap<currency::Currency, HashMap<NaiveDate, Decimal>> = columns
+            .iter()
+            .map(|(_, currency)| (*currency, HashMap::new()))
+            .collect();
+
+        for record in reader.records() {
+            let record = record?;
+            let line = record.position().map_or(0, |position| position.line());
+            let date = record.get(0).unwrap_or_default().trim();
+            let date = date
+                .parse::<NaiveDate>()
+                .map_err(|_| WideRatesError::InvalidDate {
+                    line,
+                    date: date.to_string(),
+                })?;
+            if let Some(&first_line) = lines.get(&date) {
+                return Err(WideRatesError::DuplicateDate {
+                    date,
+                    first_line,
+                    line,
+                });
+            }
+            lines.insert(date, line);
+
+            for (i, currency) in &columns {
+                let rate = record.get(*i).unwrap_or_default().trim();
+                if rate.is_empty() || rate.eq_ignore_ascii_case("N/A") {
+                    continue;
+                }
+                let rate = rate
+                    .parse::<Decimal>()
+                    .map_err(|_| WideRatesError::InvalidRate {
+                        line,
+                        currency: *currency,
+                        rate: rate.to_string(),
+                    })?;
+                rates.entry(*currency).or_default().insert(date, rate);
+                rows.entry(*currency).or_default().push(RateRow {
+                    line,
+                    date,
+                    rate: rate.to_string(),
+                });
+            }
+        }
+
+        for (_, currency) in &columns {
+            let issues = checks.check_rates(rows.get(currency).map_or(&[], Vec::as_slice));
+            if let Err(ValidationError::Invalid(issues)) = reject_errors(issues) {
+                return Err(WideRatesError::Invalid {
+                    currency: *currency,
+                    issues,
+                });
+            }
+        }
+
+        Ok(WideExchangeRates {
+            base,
+            rates,
+            lines,
+            origin: Arc::default(),
+        })
+    }
+}
+
+impl RateTable for WideExchangeRates {
+    /// The daily rates for converting `D` to `N`. Pairs against the base currency are
+    /// taken as published or inverted, any other pair is the cross rate through the base
+    /// on the dates that both currencies have a rate. Returns `None` if either currency is
+    /// not in the table or a rate is out of range.
+    fn pair<N, D>(&self) -> Option<DailyExchangeRates<N, D>>
+    where
+        N: CurrencyType + for<'de> Deserialize<'de>,
+        D: CurrencyType + for<'de> Deserialize<'de>,
+    {
+        let one = Decimal::new(1, 0);
+        let rates: DateMap<_> = if D::currency() == self.base {
+            self.rates
+                .get(&N::currency())?
+                .iter()
+                .map(|(date, to_n)| Some((*date, ExchangeRate::from_quotient(*to_n, one)?)))
+                .collect::<Option<_>>()?
+        } else if N::currency() == self.base {
+            self.rates
+                .get(&D::currency())?
+                .iter()
+                .map(|(date, to_d)| Some((*date, ExchangeRate::from_quotient(one, *to_d)?)))
+                .collect::<Option<_>>()?
+        } else {
+            let to_d = self.rates.get(&D::currency())?;
+            self.rates
+                .get(&N::currency())?
+                .iter()
+                .filter_map(|(date, to_n)| Some((*date, *to_n, *to_d.get(date)?)))
+                .map(|(date, to_n, to_d)| Some((date, ExchangeRate::from_quotient(to_n, to_d)?)))
+                .collect::<Option<_>>()?
+        };
+        let provenance: Vec<_> = rates
+            .iter()
+            .map(|(date, _)| {
+                let line = self.lines.get(date).copied();
+                (*date, Provenance::new(self.origin.clone(), line))
+            })
+            .collect();
+
+        Some(DailyExchangeRates::new(rates).with_provenance(provenance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversions::{
+        currency::{Currency, EUR, GBP, JPY, USD},
+        rate_source::RateSource,
+        transaction::Transaction,
+    };
+
+    const CSV: &str = "Date,USD,JPY,CYP,BGN,
+2024-05-03,1.0773,164.99,N/A,1.9558,
+2024-05-02,1.0702,165.08,N/A,,
+";
+
+    fn rates() -> WideExchangeRates {
+        WideExchangeRates::read_from_csv(
+            Reader::from_reader(CSV.as_bytes()),
+            currency::Currency::EUR,
+        )
+        .unwrap()
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap()
+    }
+
+    #[test]
+    fn test_read_from_csv() {
+        let rates = rates();
+
+        assert_eq!(
+            rates.currencies(),
+            vec![
+                currency::Currency::BGN,
+                currency::Currency::JPY,
+                currency::Currency::USD
+            ]
+        );
+        assert_eq!(
+            rates.rate(currency::Currency::USD, &date(3)),
+            Some("1.0773".parse().unwrap())
+        );
+        assert_eq!(rates.rate(currency::Currency::BGN, &date(2)), None);
+    }
+
+    #[test]
+    fn test_pair() {
+        let rates = rates();
+
+        let usd_per_eur = rates.pair::<USD, EUR>().unwrap();
+        assert_eq!(
+            usd_per_eur.day_rate(&date(3)),
+            Some(&ExchangeRate::new(1.0773))
+        );
+
+        let eur_per_usd = rates.pair::<EUR, USD>().unwrap();
+        assert_eq!(
+            eur_per_usd.day_rate(&date(3)),
+            Some(&ExchangeRate::new(1.0773).invert())
+        );
+
+        // 100.00 USD * 164.99 / 1.0773 = 15315.13 JPY
+        let jpy_per_usd = rates.pair::<JPY, USD>().unwrap();
+        assert_eq!(
+            jpy_per_usd.convert(&Transaction::new(date(3), Currency::<USD>::from(100))),
+            Ok(Transaction::new(date(3), Currency::from(15315)))
+        );
+
+        assert_eq!(rates.pair::<USD, GBP>(), None);
+    }
+
+    #[test]
+    fn test_invalid_rate() {
+        let csv = "Date,USD\n2024-05-03,1,0773\n";
+
+        assert!(matches!(
+            WideExchangeRates::read_from_csv(
+                Reader::from_reader(csv.as_bytes()),
+                currency::Currency::EUR
+            ),
+            Err(WideRatesError::Csv(_))
+        ));
+
+        let csv = "Date,USD\n2024-05-03,abc\n";
+        assert!(matches!(
+            WideExchangeRates::read_from_csv(
+                Reader::from_reader(csv.as_bytes()),
+                currency::Currency::EUR
+            ),
+            Err(WideRatesError::InvalidRate { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_checked_rates() {
+        let csv = "Date,USD,JPY\n2024-05-02,1.0702,164.99\n2024-05-03,0,165.20\n";
+
+        assert!(matches!(
+            WideExchangeRates::read_from_csv(
+                Reader::from_reader(csv.as_bytes()),
+                currency::Currency::EUR
+            ),
+            Err(WideRatesError::Invalid {
+                currency: currency::Currency::USD,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_date() {
+        let csv = "Date,USD\n2024-05-03,1.0773\n2024-05-02,1.0702\n2024-05-03,1.0780\n";
+
+        assert!(matches!(
+            WideExchangeRates::read_from_csv(
+                Reader::from_reader(csv.as_bytes()),
+                currency::Currency::EUR
+            ),
+            Err(WideRatesError::DuplicateDate {
+                first_line: 2,
+                line: 4,
+                ..
+            })
+        ));
+    }
+}
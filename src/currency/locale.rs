@@ -0,0 +1,487 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use serde::Deserializer;
+
+use super::{Currency, CurrencyError};
+use crate::conversions::decimal::{self, Decimal, ParseDecimalError};
+
+/// Where the currency symbol goes relative to the number.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SymbolPosition {
+    /// `$1,234.56`, or `CHF 1'234.56` with spacing.
+    Before,
+    /// `1.234,56 €`
+    After,
+}
+
+/// How negative amounts are written.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NegativeStyle {
+    /// `-1.234,56 €`
+    LeadingMinus,
+    /// `1.234,56- €`, common on bank statements.
+    TrailingMinus,
+    /// `($1,234.56)`, common in accounting.
+    Parentheses,
+}
+
+/// The conventions for writing money amounts in a region, e.g. `1.234,56 €` in de-DE and
+/// `€1,234.56` in en-GB.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Locale {
+    tag: &'static str,
+    decimal_separator: char,
+    grouping_separator: Option<char>,
+    symbol_position: SymbolPosition,
+    symbol_spacing: bool,
+    negative_style: NegativeStyle,
+}
+
+/// Characters that are dropped as thousands separators when parsing.
+const GROUPING_CHARS: [char; 5] = [' ', '\u{a0}', '\u{202f}', '\'', '’'];
+
+impl Locale {
+    pub const EN_US: Locale = Locale {
+        tag: "en-US",
+        decimal_separator: '.',
+        grouping_separator: Some(','),
+        symbol_position: SymbolPosition::Before,
+        symbol_spacing: false,
+        negative_style: NegativeStyle::LeadingMinus,
+    };
+
+    pub const EN_GB: Locale = Locale {
+        tag: "en-GB",
+        ..Locale::EN_US
+    };
+
+    pub const DE_DE: Locale = Locale {
+        tag: "de-DE",
+        decimal_separator: ',',
+        grouping_separator: Some('.'),
+        symbol_position: SymbolPosition::After,
+        symbol_spacing: true,
+        negative_style: NegativeStyle::LeadingMinus,
+    };
+
+    pub const DE_AT: Locale = Locale {
+        tag: "de-AT",
+        grouping_separator: Some('\u{a0}'),
+        symbol_position: SymbolPosition::Before,
+        ..Locale::DE_DE
+    };
+
+    pub const DE_CH: Locale = Locale {
+        tag: "de-CH",
+        decimal_separator: '.',
+        grouping_separator: Some('’'),
+        symbol_position: SymbolPosition::Before,
+        symbol_spacing: true,
+        negative_style: NegativeStyle::LeadingMinus,
+    };
+
+    pub const FR_FR: Locale = Locale {
+        tag: "fr-FR",
+        decimal_separator: ',',
+        grouping_separator: Some('\u{202f}'),
+        symbol_position: SymbolPosition::After,
+        symbol_spacing: true,
+        negative_style: NegativeStyle::LeadingMinus,
+    };
+
+    pub const FR_CH: Locale = Locale {
+        tag: "fr-CH",
+        ..Locale::FR_FR
+    };
+
+    pub const JA_JP: Locale = Locale {
+        tag: "ja-JP",
+        ..Locale::EN_US
+    };
+
+    pub const SV_SE: Locale = Locale {
+        tag: "sv-SE",
+        grouping_separator: Some('\u{a0}'),
+        ..Locale::FR_FR
+    };
+
+    /// All built-in locales.
+    pub fn all() -> &'static [Locale] {
+        &[
+            Locale::DE_AT,
+            Locale::DE_CH,
+            Locale::DE_DE,
+            Locale::EN_GB,
+            Locale::EN_US,
+            Locale::FR_CH,
+            Locale::FR_FR,
+            Locale::JA_JP,
+            Locale::SV_SE,
+        ]
+    }
+
+    /// The BCP 47 language tag, e.g. `"de-DE"`.
+    pub fn tag(&self) -> &'static str {
+        self.tag
+    }
+
+    pub fn with_negative_style(self, negative_style: NegativeStyle) -> Self {
+        Locale {
+            negative_style,
+            ..self
+        }
+    }
+
+    /// Formats a number with the locale's separators but without a currency symbol.
+    pub fn format_number(&self, amount: Decimal) -> String {
+        let digits = Decimal::new(amount.mantissa().abs(), amount.scale()).to_string();
+        let (integer, fraction) = digits.split_once('.').unwrap_or((&digits, ""));
+
+        let mut number = String::new();
+        for (i, digit) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                number.extend(self.grouping_separator);
+            }
+            number.push(digit);
+        }
+        if !fraction.is_empty() {
+            number.push(self.decimal_separator);
+            number.push_str(fraction);
+        }
+
+        if amount.mantissa() >= 0 {
+            return number;
+        }
+        match self.negative_style {
+            NegativeStyle::LeadingMinus => format!("-{}", number),
+            NegativeStyle::TrailingMinus => format!("{}-", number),
+            NegativeStyle::Parentheses => format!("({})", number),
+        }
+    }
+
+    /// Formats an amount in `currency`, e.g. `1.234,56 €` for de-DE.
+    pub fn format(&self, amount: Decimal, currency: Currency) -> String {
+        let spacing = if self.symbol_spacing { " " } else { "" };
+        let number = self.format_number(Decimal::new(amount.mantissa().abs(), amount.scale()));
+        let unsigned = match self.symbol_position {
+            SymbolPosition::Before => format!("{}{}{}", currency.symbol(), spacing, number),
+            SymbolPosition::After => format!("{}{}{}", number, spacing, currency.symbol()),
+        };
+
+        if amount.mantissa() >= 0 {
+            return unsigned;
+        }
+        match self.negative_style {
+            NegativeStyle::LeadingMinus => format!("-{}", unsigned),
+            NegativeStyle::TrailingMinus => match self.symbol_position {
+                SymbolPosition::Before => format!("{}-", unsigned),
+                SymbolPosition::After => format!("{}-{}{}", number, spacing, currency.symbol()),
+            },
+            NegativeStyle::Parentheses => format!("({})", unsigned),
+        }
+    }
+
+    /// Parses an amount written in this locale. Currency symbols or codes around the
+    /// number are ignored. Thousands separators must separate groups of three digits.
+    pub fn parse(&self, text: &str) -> Result<Decimal, ParseDecimalError> {
+        let (negative, number) = split_sign_and_symbol(text)?;
+        let number = parse_number(
+            number,
+            Some(self.decimal_separator),
+            self.grouping_separator,
+        )
+        .ok_or_else(|| invalid(text))?;
+        with_sign(negative, number)
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.tag)
+    }
+}
+
+impl FromStr for Locale {
+    type Err = CurrencyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tag = s.trim().replace('_', "-");
+        Locale::all()
+            .iter()
+            .find(|locale| locale.tag.eq_ignore_ascii_case(&tag))
+            .copied()
+            .ok_or_else(|| CurrencyError::UnknownLocale(s.to_string()))
+    }
+}
+
+/// Parses an amount whose locale is not known, e.g. `"$1,234.56"`, `"1.234,56 €"`,
+/// `"(12.50)"` or `"1'234.56"`.
+///
+/// If both `.` and `,` occur, the last one is the decimal separator, and a separator that
+/// occurs several times is a thousands separator. A single separator followed by exactly
+/// three digits after a valid first group of one to three digits, as in `"1,234"` or
+/// `"1.234"`, could be either, so such amounts are rejected as ambiguous. Use
+/// [`Locale::parse`] or [`parse_amount_for`] for them.
+pub fn parse_amount(text: &str) -> Result<Decimal, ParseDecimalError> {
+    parse_any_locale(text, false)
+}
+
+/// Parses an amount whose locale is not known like [`parse_amount`], for a currency with
+/// `minor_units` decimal places. For a currency with three or more, such as the KWD, a
+/// single separator followed by three digits is a decimal separator.
+pub fn parse_amount_for(text: &str, minor_units: u32) -> Result<Decimal, ParseDecimalError> {
+    parse_any_locale(text, minor_units >= 3)
+}
+
+fn parse_any_locale(text: &str, three_decimals: bool) -> Result<Decimal, ParseDecimalError> {
+    let (negative, number) = split_sign_and_symbol(text)?;
+    let count = |separator| number.matches(separator).count();
+
+    let (decimal_separator, grouping_separator) = match (number.rfind('.'), number.rfind(',')) {
+        (Some(dot), Some(comma)) if dot > comma => (Some('.'), Some(',')),
+        (Some(_), Some(_)) => (Some(','), Some('.')),
+        (Some(_), None) if count('.') > 1 => (None, Some('.')),
+        (None, Some(_)) if count(',') > 1 => (None, Some(',')),
+        (Some(position), None) | (None, Some(position)) => {
+            let separator = number[position..].chars().next();
+            let integer = &number[..position];
+            let first_group = (1..=3).contains(&integer.len())
+                && integer.chars().all(|c| c.is_ascii_digit())
+                && !integer.starts_with('0');
+            let decimals = number.len() - position - 1;
+            if decimals == 3 && first_group && !three_decimals {
+                return Err(ParseDecimalError::ambiguous(text));
+            }
+            (separator, None)
+        }
+        (None, None) => (None, None),
+    };
+
+    let number =
+        parse_number(number, decimal_separator, grouping_separator).ok_or_else(|| invalid(text))?;
+    with_sign(negative, number)
+}
+
+/// Parses an unsigned number with the given separators. Besides `grouping_separator`,
+/// spaces and apostrophes are accepted as thousands separators, but only between groups
+/// of three digits.
+fn parse_number(
+    number: &str,
+    decimal_separator: Option<char>,
+    grouping_separator: Option<char>,
+) -> Option<Decimal> {
+    let (integer, fraction) = match decimal_separator {
+        Some(separator) => number.split_once(separator).unwrap_or((number, "")),
+        None => (number, ""),
+    };
+    let is_grouping = |c: char| Some(c) == grouping_separator || GROUPING_CHARS.contains(&c);
+    let groups: Vec<&str> = integer.split(is_grouping).collect();
+
+    let grouped = groups.len() > 1;
+    let valid_groups = groups
+        .iter()
+        .enumerate()
+        .all(|(i, group)| match (i, grouped) {
+            (_, false) => true,
+            (0, true) => (1..=3).contains(&group.len()),
+            (_, true) => group.len() == 3,
+        });
+    if !valid_groups || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let digits = groups.concat();
+    let number = if decimal_separator.is_some_and(|separator| number.contains(separator)) {
+        format!("{}.{}", digits, fraction)
+    } else {
+        digits
+    };
+    number.parse().ok()
+}
+
+/// Deserializes an amount with [`parse_amount`], for use with `#[serde(deserialize_with)]`.
+pub fn deserialize_amount<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    decimal::deserialize_with(deserializer, parse_amount)
+}
+
+/// Deserializes an amount with [`parse_amount_for`].
+pub fn deserialize_amount_for<'de, D>(
+    deserializer: D,
+    minor_units: u32,
+) -> Result<Decimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    decimal::deserialize_with(deserializer, |text| parse_amount_for(text, minor_units))
+}
+
+fn invalid(text: &str) -> ParseDecimalError {
+    ParseDecimalError::new(text)
+}
+
+fn with_sign(negative: bool, amount: Decimal) -> Result<Decimal, ParseDecimalError> {
+    if negative {
+        Ok(Decimal::new(-amount.mantissa(), amount.scale()))
+    } else {
+        Ok(amount)
+    }
+}
+
+/// Splits `text` into its sign and the number itself, dropping any currency symbol or
+/// code in front of or after the number.
+fn split_sign_and_symbol(text: &str) -> Result<(bool, &str), ParseDecimalError> {
+    let is_number_char = |c: char| c.is_ascii_digit() || c == '.' || c == ',';
+    let start = text.find(is_number_char).ok_or_else(|| invalid(text))?;
+    let end = text
+        .rfind(|c: char| c.is_ascii_digit())
+        .ok_or_else(|| invalid(text))?
+        + 1;
+    if start >= end {
+        return Err(invalid(text));
+    }
+
+    let (prefix, number, suffix) = (&text[..start], &text[start..end], &text[end..]);
+    let is_minus = |c: char| c == '-' || c == '−';
+    let parentheses = prefix.contains('(') && suffix.contains(')');
+    let minus_count = prefix
+        .chars()
+        .chain(suffix.chars())
+        .filter(|c| is_minus(*c))
+        .count();
+
+    if minus_count > 1 || parentheses && minus_count > 0 {
+        return Err(invalid(text));
+    }
+
+    Ok((parentheses || minus_count == 1, number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(text: &str) -> Decimal {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_format() {
+        let amount = decimal("-1234567.89");
+
+        assert_eq!(
+            Locale::EN_US.format(amount, Currency::USD),
+            "-$1,234,567.89"
+        );
+        assert_eq!(
+            Locale::DE_DE.format(amount, Currency::EUR),
+            "-1.234.567,89 €"
+        );
+        assert_eq!(
+            Locale::DE_CH.format(amount, Currency::CHF),
+            "-CHF 1’234’567.89"
+        );
+        assert_eq!(
+            Locale::FR_CH.format(decimal("1234.5"), Currency::CHF),
+            "1\u{202f}234,5 CHF"
+        );
+        assert_eq!(
+            Locale::JA_JP.format(decimal("1500"), Currency::JPY),
+            "¥1,500"
+        );
+    }
+
+    #[test]
+    fn test_format_negative_styles() {
+        let amount = decimal("-1234.56");
+
+        assert_eq!(
+            Locale::EN_US
+                .with_negative_style(NegativeStyle::Parentheses)
+                .format(amount, Currency::USD),
+            "($1,234.56)"
+        );
+        assert_eq!(
+            Locale::DE_DE
+                .with_negative_style(NegativeStyle::TrailingMinus)
+                .format(amount, Currency::EUR),
+            "1.234,56- €"
+        );
+        assert_eq!(
+            Locale::DE_DE
+                .with_negative_style(NegativeStyle::TrailingMinus)
+                .format_number(amount),
+            "1.234,56-"
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Locale::DE_DE.parse("-1.234,56 €"), Ok(decimal("-1234.56")));
+        assert_eq!(Locale::DE_DE.parse("1.234,56-"), Ok(decimal("-1234.56")));
+        assert_eq!(Locale::EN_US.parse("($1,234.56)"), Ok(decimal("-1234.56")));
+        assert_eq!(Locale::DE_CH.parse("CHF 1’234.56"), Ok(decimal("1234.56")));
+        assert_eq!(Locale::DE_DE.parse("1,234"), Ok(decimal("1.234")));
+        assert_eq!(Locale::DE_DE.parse("1.234"), Ok(decimal("1234")));
+        assert_eq!(Locale::EN_US.parse("1,234"), Ok(decimal("1234")));
+        assert_eq!(Locale::DE_DE.parse("1 234,5"), Ok(decimal("1234.5")));
+        assert!(Locale::DE_DE.parse("1.23").is_err());
+        assert!(Locale::EN_US.parse("1,234,5").is_err());
+        assert!(Locale::EN_US.parse("1.234,56").is_err());
+        assert!(Locale::DE_DE.parse("€").is_err());
+        assert!(Locale::DE_DE.parse("--1,00").is_err());
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        let cases = [
+            ("$1,234.56", "1234.56"),
+            ("1.234,56", "1234.56"),
+            ("1.234.567", "1234567"),
+            ("1,23", "1.23"),
+            ("1,2345", "1.2345"),
+            ("0.125", "0.125"),
+            ("-12.50 EUR", "-12.50"),
+            ("(12.50)", "-12.50"),
+            ("12,50-", "-12.50"),
+            ("1'234.56", "1234.56"),
+            ("1 234,56 €", "1234.56"),
+            ("123.45", "123.45"),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(parse_amount(text), Ok(decimal(expected)), "{}", text);
+        }
+        for ambiguous in ["1.234", "1,234", "-12.500 EUR"] {
+            assert!(
+                parse_amount(ambiguous).is_err_and(|error| error.is_ambiguous()),
+                "{}",
+                ambiguous
+            );
+        }
+        for decimal_text in ["1000.000", "0.125", "1 000.500"] {
+            assert!(parse_amount(decimal_text).is_ok(), "{}", decimal_text);
+        }
+        assert_eq!(parse_amount_for("12.500", 3), Ok(decimal("12.500")));
+        assert_eq!(parse_amount_for("1,234", 3), Ok(decimal("1.234")));
+        assert!(parse_amount_for("1.234", 2).is_err_and(|error| error.is_ambiguous()));
+        assert!(parse_amount("1.234,56.7").is_err());
+        assert!(parse_amount("12,34.56").is_err());
+        assert!(parse_amount("abc").is_err());
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("de-DE".parse(), Ok(Locale::DE_DE));
+        assert_eq!("fr_CH".parse(), Ok(Locale::FR_CH));
+        assert_eq!(
+            "xx-XX".parse::<Locale>(),
+            Err(CurrencyError::UnknownLocale("xx-XX".to_string()))
+        );
+    }
+}
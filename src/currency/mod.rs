@@ -0,0 +1,194 @@
+pub mod locale;
+mod money;
+mod pair;
+mod registry;
+
+use std::{
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+    str::FromStr,
+};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+pub use locale::Locale;
+pub use money::Money;
+pub use pair::CurrencyPair;
+
+/// A currency from the ISO 4217 registry, or one of the few crypto currencies we hold.
+///
+/// Every currency is a `const` on this type (`Currency::EUR`, `Currency::JPY`, ...), so
+/// values can only be obtained from the registry and unknown codes are rejected.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct Currency(&'static CurrencyInfo);
+
+#[derive(Debug, Eq, Hash, PartialEq)]
+struct CurrencyInfo {
+    code: &'static str,
+    numeric: Option<u16>,
+    minor_units: u8,
+    symbol: &'static str,
+    name: &'static str,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CurrencyError {
+    UnknownCurrency(String),
+    UnknownNumericCode(u16),
+    InvalidAmount(String),
+    TooManyDecimals { amount: String, minor_units: u8 },
+    AmountOverflow(String),
+    UnknownLocale(String),
+    CurrencyMismatch { expected: Currency, found: Currency },
+    InvalidCurrencyPair(String),
+}
+
+impl Display for CurrencyError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownCurrency(code) => write!(f, "Unknown currency: {}", code),
+            Self::UnknownNumericCode(numeric) => {
+                write!(f, "Unknown numeric currency code: {:03}", numeric)
+            }
+            Self::InvalidAmount(amount) => write!(f, "Invalid amount: {}", amount),
+            Self::TooManyDecimals {
+                amount,
+                minor_units,
+            } => write!(
+                f,
+                "Amount {} has more than {} decimal places",
+                amount, minor_units
+            ),
+            Self::AmountOverflow(amount) => write!(f, "Amount out of range: {}", amount),
+            Self::UnknownLocale(locale) => write!(f, "Unknown locale: {}", locale),
+            Self::CurrencyMismatch { expected, found } => {
+                write!(f, "Expected an amount in {}, found {}", expected, found)
+            }
+            Self::InvalidCurrencyPair(pair) => {
+                write!(f, "Invalid currency pair: {} (expected e.g. EUR/USD)", pair)
+            }
+        }
+    }
+}
+
+impl Error for CurrencyError {}
+
+impl Currency {
+    /// All known currencies, sorted by alphabetic code.
+    pub fn all() -> &'static [Currency] {
+        registry::CURRENCIES
+    }
+
+    pub fn from_code(code: &str) -> Result<Self, CurrencyError> {
+        let code = code.trim();
+        Self::all()
+            .iter()
+            .find(|currency| currency.code().eq_ignore_ascii_case(code))
+            .copied()
+            .ok_or_else(|| CurrencyError::UnknownCurrency(code.to_string()))
+    }
+
+    pub fn from_numeric(numeric: u16) -> Result<Self, CurrencyError> {
+        Self::all()
+            .iter()
+            .find(|currency| currency.numeric() == Some(numeric))
+            .copied()
+            .ok_or(CurrencyError::UnknownNumericCode(numeric))
+    }
+
+    /// The three letter alphabetic code, e.g. `"EUR"`.
+    pub fn code(&self) -> &'static str {
+        self.0.code
+    }
+
+    /// The three digit numeric code, e.g. `978` for EUR. Crypto currencies have none.
+    pub fn numeric(&self) -> Option<u16> {
+        self.0.numeric
+    }
+
+    /// The number of digits after the decimal separator, e.g. `2` for EUR and `0` for JPY.
+    pub fn minor_units(&self) -> u8 {
+        self.0.minor_units
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        self.0.symbol
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.0.name
+    }
+}
+
+impl FromStr for Currency {
+    type Err = CurrencyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Currency::from_code(s)
+    }
+}
+
+impl Debug for Currency {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Currency({})", self.code())
+    }
+}
+
+impl Display for Currency {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D>(deserializer: D) -> Result<Currency, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let code = String::deserialize(deserializer)?;
+        code.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("USD".parse(), Ok(Currency::USD));
+        assert_eq!("EUR".parse(), Ok(Currency::EUR));
+        assert_eq!("JPY".parse(), Ok(Currency::JPY));
+        assert_eq!(
+            "XYZ".parse::<Currency>(),
+            Err(CurrencyError::UnknownCurrency("XYZ".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_registry() {
+        assert_eq!(Currency::GBP.numeric(), Some(826));
+        assert_eq!(Currency::BTC.numeric(), None);
+        assert_eq!(Currency::ETH.minor_units(), 18);
+        assert_eq!(Currency::GBP.symbol(), "£");
+        assert_eq!(Currency::JPY.minor_units(), 0);
+        assert_eq!(Currency::KWD.minor_units(), 3);
+        assert_eq!(Currency::from_numeric(752), Ok(Currency::SEK));
+        assert_eq!(
+            Currency::from_numeric(1),
+            Err(CurrencyError::UnknownNumericCode(1))
+        );
+        assert!(Currency::all()
+            .windows(2)
+            .all(|pair| pair[0].code() < pair[1].code()));
+    }
+}
@@ -0,0 +1,171 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use super::{Currency, CurrencyError, Locale};
+use crate::conversions::{decimal::Decimal, rounding::RoundingMode};
+
+/// An amount of money in a currency that is only known at runtime.
+///
+/// The amount is stored in minor units of the currency, e.g. cents for EUR and yen for JPY.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Money {
+    amount: i128,
+    currency: Currency,
+}
+
+impl Money {
+    pub fn from_minor_units(amount: i128, currency: Currency) -> Self {
+        Money { amount, currency }
+    }
+
+    /// Parses a decimal amount such as `"-1234.5"` in the given currency.
+    ///
+    /// Amounts with more decimal places than the currency's minor units are rejected.
+    pub fn parse(amount: &str, currency: Currency) -> Result<Self, CurrencyError> {
+        let decimal = amount
+            .parse::<Decimal>()
+            .map_err(|_| CurrencyError::InvalidAmount(amount.to_string()))?;
+
+        if decimal.normalize().scale() > currency.minor_units().into() {
+            return Err(CurrencyError::TooManyDecimals {
+                amount: amount.to_string(),
+                minor_units: currency.minor_units(),
+            });
+        }
+
+        Money::from_decimal(decimal, currency, RoundingMode::default())
+    }
+
+    /// Converts an exact decimal amount, rounding it with `mode` if it has more decimal
+    /// places than the currency.
+    pub fn from_decimal(
+        amount: Decimal,
+        currency: Currency,
+        mode: RoundingMode,
+    ) -> Result<Self, CurrencyError> {
+        amount
+            .rescale(currency.minor_units().into(), mode)
+            .map(|minor| Money::from_minor_units(minor, currency))
+            .ok_or_else(|| CurrencyError::AmountOverflow(amount.to_string()))
+    }
+
+    pub fn to_decimal(&self) -> Decimal {
+        Decimal::new(self.amount, self.currency.minor_units().into())
+    }
+
+    /// The amount in minor units of the currency.
+    pub fn amount(&self) -> i128 {
+        self.amount
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    /// Formats the amount following the conventions of `locale`, e.g. `1.234,56 €`.
+    pub fn format(&self, locale: &Locale) -> String {
+        locale.format(self.to_decimal(), self.currency)
+    }
+}
+
+impl FromStr for Money {
+    type Err = CurrencyError;
+
+    /// Parses strings like `"GBP 12.50"` or `"12.50 GBP"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (first, second) = s
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| CurrencyError::InvalidAmount(s.to_string()))?;
+        let (first, second) = (first.trim(), second.trim());
+
+        match Currency::from_code(first) {
+            Ok(currency) => Money::parse(second, currency),
+            Err(_) => Money::parse(first, Currency::from_code(second)?),
+        }
+    }
+}
+
+impl Display for Money {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.currency.symbol(), self.to_decimal())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Money::parse("0.29", Currency::EUR),
+            Ok(Money::from_minor_units(29, Currency::EUR))
+        );
+        assert_eq!(
+            Money::parse("-1234.5", Currency::GBP),
+            Ok(Money::from_minor_units(-123450, Currency::GBP))
+        );
+        assert_eq!(
+            Money::parse("1500", Currency::JPY),
+            Ok(Money::from_minor_units(1500, Currency::JPY))
+        );
+        assert_eq!(
+            Money::parse("1.234", Currency::KWD),
+            Ok(Money::from_minor_units(1234, Currency::KWD))
+        );
+        assert_eq!(
+            Money::parse("1.5", Currency::JPY),
+            Err(CurrencyError::TooManyDecimals {
+                amount: "1.5".to_string(),
+                minor_units: 0
+            })
+        );
+        assert_eq!(
+            Money::parse("12a", Currency::EUR),
+            Err(CurrencyError::InvalidAmount("12a".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            "CHF 12.50".parse(),
+            Ok(Money::from_minor_units(1250, Currency::CHF))
+        );
+        assert_eq!(
+            "12.50 CAD".parse(),
+            Ok(Money::from_minor_units(1250, Currency::CAD))
+        );
+        assert_eq!(
+            "12.50 ABC".parse::<Money>(),
+            Err(CurrencyError::UnknownCurrency("ABC".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            Money::from_minor_units(-123456, Currency::EUR).format(&Locale::DE_DE),
+            "-1.234,56 €"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            Money::from_minor_units(-123450, Currency::GBP).to_string(),
+            "£ -1234.50"
+        );
+        assert_eq!(
+            Money::from_minor_units(1500, Currency::JPY).to_string(),
+            "¥ 1500"
+        );
+        assert_eq!(
+            Money::from_minor_units(5, Currency::SEK).to_string(),
+            "kr 0.05"
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use crate::currency::{Currency, CurrencyError};
+
+/// The currencies of a quoted rate, written `BASE/QUOTE` as in the market convention:
+/// `EUR/USD 1.0837` means that one EUR costs 1.0837 USD.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CurrencyPair {
+    base: Currency,
+    quote: Currency,
+}
+
+impl CurrencyPair {
+    pub fn new(base: Currency, quote: Currency) -> Self {
+        CurrencyPair { base, quote }
+    }
+
+    pub fn base(&self) -> Currency {
+        self.base
+    }
+
+    pub fn quote(&self) -> Currency {
+        self.quote
+    }
+
+    /// The same currencies quoted the other way round.
+    pub fn invert(&self) -> Self {
+        CurrencyPair::new(self.quote, self.base)
+    }
+}
+
+impl FromStr for CurrencyPair {
+    type Err = CurrencyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (base, quote) = s
+            .split_once('/')
+            .ok_or_else(|| CurrencyError::InvalidCurrencyPair(s.to_string()))?;
+        let pair = CurrencyPair::new(base.parse()?, quote.parse()?);
+
+        if pair.base == pair.quote {
+            return Err(CurrencyError::InvalidCurrencyPair(s.to_string()));
+        }
+        Ok(pair)
+    }
+}
+
+impl Display for CurrencyPair {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.base, self.quote)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            "EUR/USD".parse(),
+            Ok(CurrencyPair::new(Currency::EUR, Currency::USD))
+        );
+        assert_eq!(
+            " chf / jpy "
+                .parse::<CurrencyPair>()
+                .map(|pair| pair.invert()),
+            Ok(CurrencyPair::new(Currency::JPY, Currency::CHF))
+        );
+        assert_eq!(
+            "EURUSD".parse::<CurrencyPair>(),
+            Err(CurrencyError::InvalidCurrencyPair("EURUSD".to_string()))
+        );
+        assert_eq!(
+            "EUR/EUR".parse::<CurrencyPair>(),
+            Err(CurrencyError::InvalidCurrencyPair("EUR/EUR".to_string()))
+        );
+        assert_eq!(
+            "EUR/XYZ".parse::<CurrencyPair>(),
+            Err(CurrencyError::UnknownCurrency("XYZ".to_string()))
+        );
+    }
+}
@@ -0,0 +1,38 @@
+use std::error::Error;
+
+use serde::Deserialize;
+
+use crate::conversions::{
+    currency::CurrencyType, exchange_rate::ExchangeRate,
+    period_exchange_rates::PeriodExchangeRates, rounding::RoundingMode,
+};
+
+/// Writes period rates to a CSV file with `period` and `BASE/QUOTE` columns, so that the
+/// file declares its own pair. Rates are rounded to `decimals` places with `mode` if given.
+pub fn write_period_rates<N, D>(
+    output_path: &str,
+    rates: &PeriodExchangeRates<N, D>,
+    decimals: Option<u32>,
+    mode: RoundingMode,
+) -> Result<(), Box<dyn Error>>
+where
+    N: CurrencyType + for<'de> Deserialize<'de>,
+    D: CurrencyType + for<'de> Deserialize<'de>,
+{
+    let mut writer = csv::Writer::from_path(output_path)?;
+    let pair = format!("{}/{}", D::currency(), N::currency());
+    writer.write_record(["period", pair.as_str()])?;
+
+    for (period, rate) in rates.periods() {
+        let rate: ExchangeRate<N, D> = match decimals {
+            Some(decimals) => rate
+                .checked_round(decimals, mode)
+                .ok_or("Average rate out of range")?,
+            None => rate,
+        };
+        writer.serialize((period, rate))?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
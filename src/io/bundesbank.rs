@@ -0,0 +1,204 @@
+use std::io::Read;
+
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, StringRecord};
+
+use crate::{
+    conversions::rate_series::{RateSeries, SeriesFileError},
+    currency::{Currency, CurrencyPair},
+};
+
+/// The provider named as the origin of the rates read from these files.
+pub const PROVIDER: &str = "Deutsche Bundesbank";
+
+/// Reads a time series export of the Bundesbank, in English or German. The first row names
+/// the series, e.g. `BBEX3.D.USD.EUR.BB.AC.000` for the USD price of one EUR, and is
+/// followed by metadata rows such as the unit and the last update, then by one row per day.
+///
+/// German exports separate fields with `;` and use decimal commas. Days without a value are
+/// marked with `.` and are left out, as are the `_FLAGS` columns. Series whose key does not
+/// name two currencies we know are skipped with a warning.
+pub fn read_bundesbank<R>(mut reader: R) -> Result<Vec<RateSeries>, SeriesFileError>
+where
+    R: Read,
+{
+    let mut text = String::new();
+    reader
+        .read_to_string(&mut text)
+        .map_err(|error| SeriesFileError::Csv(error.into()))?;
+    let first_line = text.lines().next().unwrap_or_default();
+    let german = first_line.matches(';').count() > first_line.matches(',').count();
+
+    let mut records = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(if german { b';' } else { b',' })
+        .from_reader(text.as_bytes())
+        .into_records();
+    let header = records.next().ok_or(SeriesFileError::MissingHeader)??;
+
+    let mut metadata: Vec<StringRecord> = Vec::new();
+    let mut series: Option<Vec<(usize, RateSeries)>> = None;
+
+    for record in records {
+        let record = record?;
+        let line = record.position().map_or(0, |position| position.line());
+        let first = record.get(0).unwrap_or_default().trim();
+        let Ok(date) = first.parse::<NaiveDate>() else {
+            if series.is_some() {
+                return Err(SeriesFileError::InvalidDate {
+                    line,
+                    date: first.to_string(),
+                });
+            }
+            metadata.push(record);
+            continue;
+        };
+
+        let series = match &mut series {
+            Some(series) => series,
+            None => series.insert(columns(&header, &metadata)?),
+        };
+        for (i, series) in series.iter_mut() {
+            let value = record.get(*i).unwrap_or_default().trim();
+            if value.is_empty() || value == "." {
+                continue;
+            }
+            let number = if german {
+                value.replace('.', "").replace(',', ".")
+            } else {
+                value.replace(',', "")
+            };
+            let rate = number.parse().map_err(|_| SeriesFileError::InvalidRate {
+                line,
+                series: series.id().to_string(),
+                rate: value.to_string(),
+            })?;
+            series.insert(date, rate, Some(line));
+        }
+    }
+
+    Ok(match series {
+        Some(series) => series.into_iter().map(|(_, series)| series).collect(),
+        None => columns(&header, &metadata)?
+            .into_iter()
+            .map(|(_, series)| series)
+            .collect(),
+    })
+}
+
+/// The series in the columns of the file, with their metadata.
+fn columns(
+    header: &StringRecord,
+    metadata: &[StringRecord],
+) -> Result<Vec<(usize, RateSeries)>, SeriesFileError> {
+    let mut columns = Vec::new();
+
+    for (i, id) in header.iter().enumerate().skip(1) {
+        let id = id.trim();
+        if id.is_empty() || id.ends_with("_FLAGS") {
+            continue;
+        }
+        let Some(pair) = series_pair(id) else {
+            log::warn!("Skipping series {}, which is not an exchange rate", id);
+            continue;
+        };
+
+        let metadata: Vec<(String, String)> = metadata
+            .iter()
+            .map(|row| {
+                let key = match row.get(0).unwrap_or_default().trim() {
+                    "" => "title",
+                    key => key,
+                };
+                let value = row.get(i).unwrap_or_default().trim();
+                (key.to_string(), value.to_string())
+            })
+            .collect();
+
+        let multiplier = metadata.iter().find(|(key, _)| {
+            key.eq_ignore_ascii_case("unit multiplier") || key == "Einheitenmultiplikator"
+        });
+        if let Some((_, multiplier)) = multiplier {
+            if !["one", "Eins", "1"].contains(&multiplier.as_str()) {
+                return Err(SeriesFileError::UnsupportedMultiplier {
+                    series: id.to_string(),
+                    multiplier: multiplier.clone(),
+                });
+            }
+        }
+
+        columns.push((i, RateSeries::new(id.to_string(), pair, metadata)));
+    }
+
+    Ok(columns)
+}
+
+/// The pair of a series key like `BBEX3.D.USD.EUR.BB.AC.000`, which gives the price of one
+/// EUR in USD.
+fn series_pair(id: &str) -> Option<CurrencyPair> {
+    let mut parts = id.split('.').skip(2);
+    let quote: Currency = parts.next()?.parse().ok()?;
+    let base: Currency = parts.next()?.parse().ok()?;
+    (base != quote).then(|| CurrencyPair::new(base, quote))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap()
+    }
+
+    #[test]
+    fn test_read_english() {
+        let csv = r#",BBEX3.D.USD.EUR.BB.AC.000,BBEX3.D.USD.EUR.BB.AC.000_FLAGS
+,"Euro foreign exchange reference rate of the ECB / EUR 1 = USD ... / United States",
+unit,USD,
+unit multiplier,one,
+last update,2024-05-06 14:15:00,
+2024-05-02,1.0702,
+2024-05-03,1.0773,
+2024-05-04,.,No value available
+"#;
+        let series = read_bundesbank(csv.as_bytes()).unwrap();
+
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].id(), "BBEX3.D.USD.EUR.BB.AC.000");
+        assert_eq!(series[0].pair(), "EUR/USD".parse().unwrap());
+        assert_eq!(series[0].metadata()[1], ("unit".into(), "USD".into()));
+        assert_eq!(series[0].rate(&date(3)), Some("1.0773".parse().unwrap()));
+        assert_eq!(series[0].rate(&date(4)), None);
+    }
+
+    #[test]
+    fn test_read_german() {
+        let csv = ";BBEX3.D.JPY.EUR.BB.AC.000;BBEX3.D.JPY.EUR.BB.AC.000_FLAGS
+Einheit;JPY;
+Einheitenmultiplikator;Eins;
+2024-05-02;165,08;
+2024-05-04;.;Kein Wert vorhanden
+";
+        let series = read_bundesbank(csv.as_bytes()).unwrap();
+
+        assert_eq!(series[0].pair(), "EUR/JPY".parse().unwrap());
+        assert_eq!(series[0].rate(&date(2)), Some("165.08".parse().unwrap()));
+        assert_eq!(series[0].len(), 1);
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let csv = ",BBEX3.D.USD.EUR.BB.AC.000\nunit multiplier,hundred\n2024-05-02,1.0702\n";
+        assert!(matches!(
+            read_bundesbank(csv.as_bytes()),
+            Err(SeriesFileError::UnsupportedMultiplier { .. })
+        ));
+
+        let csv = ",BBEX3.D.USD.EUR.BB.AC.000\n2024-05-02,n/a\n";
+        assert!(matches!(
+            read_bundesbank(csv.as_bytes()),
+            Err(SeriesFileError::InvalidRate { line: 2, .. })
+        ));
+    }
+}
@@ -1,79 +1,671 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{Read, Write},
+};
+
 use chrono::NaiveDate;
-use csv::Reader;
+use csv::{Reader, StringRecord, WriterBuilder};
 use serde::{Deserialize, Serialize};
 
 use crate::conversions::{
+    bid_ask_rates::{RateSide, SidePolicy},
+    calendar::Settlement,
     currency::{Currency, CurrencyType},
-    daily_exchange_rates::{ConversionError, DailyExchangeRates},
+    daily_exchange_rates::ConversionError,
     exchange_rate::ExchangeRate,
+    rate_source::{RateQuote, RateSource},
+    rounding::RoundingMode,
+    timezone::{Timestamp, Zone},
     transaction::Transaction,
 };
+use crate::currency::{locale, Locale};
+
+/// The input columns that are read into the fields of a [`Transaction`]. Any other
+/// columns are kept as its metadata.
+const COLUMNS: [&str; 5] = ["date", "amount", "id", "description", "category"];
+
+/// The columns of an [`OutputLine`], which are followed by one column for each input column
+/// kept as metadata.
+const OUTPUT_COLUMNS: [&str; 12] = [
+    "date",
+    "from_amount",
+    "exchange_rate",
+    "rate_side",
+    "rate_legs",
+    "rate_date",
+    "rate_source",
+    "to_amount",
+    "id",
+    "description",
+    "category",
+    "timestamp",
+];
+
+/// The columns appended to the input columns when they are kept.
+const APPENDED_COLUMNS: [&str; 6] = [
+    "exchange_rate",
+    "rate_side",
+    "rate_legs",
+    "rate_date",
+    "rate_source",
+    "to_amount",
+];
+
+/// How the input columns are read and written.
+#[derive(Clone, Debug)]
+pub struct Columns {
+    /// The column with the amounts to convert.
+    pub amount: String,
+    /// Whether to write every input column unchanged, followed by the conversion, instead
+    /// of the transaction fields.
+    pub keep: bool,
+    /// The locale of the amounts. If not given, it is inferred from each amount, and
+    /// amounts that could be read either way are rejected.
+    pub locale: Option<Locale>,
+    /// The time zone of dates with a time but without an offset.
+    pub timezone: Zone,
+    /// The time zone whose calendar dates decide the rates that transactions with a time
+    /// are converted with.
+    pub reporting_timezone: Zone,
+}
+
+impl Default for Columns {
+    fn default() -> Self {
+        Columns {
+            amount: "amount".to_string(),
+            keep: false,
+            locale: None,
+            timezone: Zone::utc(),
+            reporting_timezone: Zone::utc(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct InputLine {
+    /// A date, or a date and time.
+    date: String,
+    /// Parsed with the locale of [`Columns`].
+    amount: String,
+    id: Option<String>,
+    description: Option<String>,
+    category: Option<String>,
+}
 
 #[derive(Debug, Serialize)]
-struct OutputLine<'a, N, D>
+struct OutputLine<N, D>
 where
     N: CurrencyType + Serialize,
     D: CurrencyType + Serialize,
 {
     date: NaiveDate,
     from_amount: Currency<D>,
-    exchange_rate: Option<&'a ExchangeRate<N, D>>,
+    exchange_rate: Option<ExchangeRate<N, D>>,
+    rate_side: Option<RateSide>,
+    /// The published rates a cross rate was derived from, separated by `; `.
+    rate_legs: String,
+    /// The dates of the rates used, separated by `; `, or the period of a periodic rate.
+    rate_date: String,
+    /// Where the rates used came from, separated by `; `.
+    rate_source: String,
     to_amount: Result<Currency<N>, ConversionError>,
+    id: Option<String>,
+    description: Option<String>,
+    category: Option<String>,
+    timestamp: Option<Timestamp>,
+    /// The values of the input columns kept as metadata, one column each.
+    metadata: Vec<String>,
 }
 
-impl<'a, N, D> OutputLine<'a, N, D>
+/// The columns of an [`OutputLine`] that describe the conversion, in the order of
+/// [`APPENDED_COLUMNS`].
+#[derive(Debug, Serialize)]
+struct Conversion<'a, N, D>
+where
+    N: CurrencyType + Serialize,
+    D: CurrencyType + Serialize,
+{
+    exchange_rate: &'a Option<ExchangeRate<N, D>>,
+    rate_side: &'a Option<RateSide>,
+    rate_legs: &'a str,
+    rate_date: &'a str,
+    rate_source: &'a str,
+    to_amount: &'a Result<Currency<N>, ConversionError>,
+}
+
+impl<N, D> OutputLine<N, D>
 where
     N: CurrencyType + Serialize,
     D: CurrencyType + Serialize,
 {
     fn from_transactions(
         from_transaction: Transaction<D>,
-        rate: Option<&'a ExchangeRate<N, D>>,
+        quote: Option<&RateQuote<N, D>>,
         to_transaction: Result<Transaction<N>, ConversionError>,
+        metadata: Vec<String>,
     ) -> Self {
         if let Ok(to_transaction) = &to_transaction {
             assert_eq!(from_transaction.date(), to_transaction.date());
         }
 
         OutputLine {
-            date: from_transaction.date().clone(),
+            date: *from_transaction.date(),
             from_amount: from_transaction.amount(),
-            exchange_rate: rate.clone(),
+            exchange_rate: quote.map(|quote| *quote.rate()),
+            rate_side: quote.map(RateQuote::side),
+            rate_legs: quote
+                .map(|quote| {
+                    quote
+                        .legs()
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("; ")
+                })
+                .unwrap_or_default(),
+            rate_date: quote
+                .map(|quote| match quote.period() {
+                    Some(period) => period.to_string(),
+                    None => quote
+                        .dates()
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("; "),
+                })
+                .unwrap_or_default(),
+            rate_source: quote
+                .map(|quote| {
+                    quote
+                        .provenance()
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("; ")
+                })
+                .unwrap_or_default(),
             to_amount: to_transaction.map(|t| t.amount()),
+            id: from_transaction.id().map(str::to_string),
+            description: from_transaction.description().map(str::to_string),
+            category: from_transaction.category().map(str::to_string),
+            timestamp: from_transaction.timestamp().copied(),
+            metadata,
+        }
+    }
+
+    fn conversion(&self) -> Conversion<'_, N, D> {
+        Conversion {
+            exchange_rate: &self.exchange_rate,
+            rate_side: &self.rate_side,
+            rate_legs: &self.rate_legs,
+            rate_date: &self.rate_date,
+            rate_source: &self.rate_source,
+            to_amount: &self.to_amount,
         }
     }
 }
 
+/// Converts the transactions of the input file with the rates of their dates, or of their
+/// settlement dates if `settlement` is given, and writes them to the output file. Returns
+/// the lines of the rows that were copied without a conversion because they could not be
+/// read, which only happens when the columns are kept.
 pub fn convert<N, D>(
     input_path: &str,
     output_path: &str,
-    rates: DailyExchangeRates<N, D>,
-) -> Result<(), csv::Error>
+    rates: impl RateSource<N, D>,
+    rounding: RoundingMode,
+    sides: SidePolicy,
+    settlement: Option<&Settlement>,
+    columns: &Columns,
+) -> Result<Vec<u64>, Box<dyn Error>>
 where
     N: CurrencyType + for<'de> Deserialize<'de>,
     D: CurrencyType + for<'de> Deserialize<'de>,
 {
-    let mut writer = csv::Writer::from_path(output_path)?;
-
-    Reader::from_path(input_path)?
-        .deserialize()
-        .map(|result| {
-            let transaction = result?;
-            let to_transaction = rates.convert(&transaction);
-            let transaction_date = transaction.date().clone();
-            Ok(OutputLine::from_transactions(
-                transaction,
-                rates.day_rate(&transaction_date),
-                to_transaction,
-            ))
-        })
-        .collect::<Result<Vec<_>, csv::Error>>()?
-        .iter()
-        .map(|line| {
+    write_converted(
+        Reader::from_path(input_path)?,
+        std::fs::File::create(output_path)?,
+        rates,
+        rounding,
+        sides,
+        settlement,
+        columns,
+    )
+}
+
+/// The first and last date that the transactions of the input file need rates for: their
+/// dates, or their settlement dates if `settlement` is given. `None` if it has none.
+pub fn rate_dates<D>(
+    input_path: &str,
+    settlement: Option<&Settlement>,
+    columns: &Columns,
+) -> Result<Option<(NaiveDate, NaiveDate)>, Box<dyn Error>>
+where
+    D: CurrencyType,
+{
+    let input = read_transactions::<_, D>(Reader::from_path(input_path)?, columns)?;
+    let mut range = None;
+    for record in input.records {
+        let transaction = match record? {
+            (_, Ok(transaction)) => transaction,
+            // Kept columns pass the records that cannot be read through unconverted.
+            (_, Err(_)) if columns.keep => continue,
+            (_, Err(error)) => return Err(error),
+        };
+        let date = match settlement {
+            Some(settlement) => settlement.date(transaction.date()),
+            None => Some(*transaction.date()),
+        };
+        range = match (range, date) {
+            (Some((first, last)), Some(date)) => Some((date.min(first), date.max(last))),
+            (None, Some(date)) => Some((date, date)),
+            (range, None) => range,
+        };
+    }
+    Ok(range)
+}
+
+fn write_converted<R, W, N, D>(
+    reader: Reader<R>,
+    output: W,
+    rates: impl RateSource<N, D>,
+    rounding: RoundingMode,
+    sides: SidePolicy,
+    settlement: Option<&Settlement>,
+    columns: &Columns,
+) -> Result<Vec<u64>, Box<dyn Error>>
+where
+    R: Read,
+    W: Write,
+    N: CurrencyType + for<'de> Deserialize<'de>,
+    D: CurrencyType + for<'de> Deserialize<'de>,
+{
+    let input = read_transactions::<_, D>(reader, columns)?;
+
+    let mut writer = WriterBuilder::new().has_headers(false).from_writer(output);
+    if columns.keep {
+        if let Some(column) = input
+            .headers
+            .iter()
+            .find(|header| APPENDED_COLUMNS.contains(&header.trim()))
+        {
+            return Err(format!(
+                "The input already has a {} column, which the conversion would repeat",
+                column.trim()
+            )
+            .into());
+        }
+        writer.write_record(input.headers.iter().chain(APPENDED_COLUMNS))?;
+    } else {
+        let metadata = input.metadata.iter().map(|&i| &input.headers[i]);
+        writer.write_record(OUTPUT_COLUMNS.into_iter().chain(metadata))?;
+    }
+
+    let mut unconverted = vec![];
+    for record in input.records {
+        let (record, transaction) = record?;
+        let transaction = match transaction {
+            Ok(transaction) => transaction,
+            Err(error) if columns.keep => {
+                log::warn!("{}, leaving its conversion empty", error);
+                writer.write_record(record.iter().chain([""; APPENDED_COLUMNS.len()]))?;
+                unconverted.push(record.position().map_or(0, |position| position.line()));
+                continue;
+            }
+            Err(error) => return Err(error),
+        };
+        let side = sides.side(transaction.amount());
+        let rate_date = match settlement {
+            Some(settlement) => settlement.date(transaction.date()),
+            None => Some(*transaction.date()),
+        };
+        let quote = rate_date
+            .ok_or(ConversionError::MissingExchangeRate)
+            .and_then(|date| rates.quote_side(&date, side));
+        let to_transaction = quote.clone().and_then(|quote| {
+            quote
+                .rate()
+                .checked_convert_with(transaction.amount(), rounding)
+                .map(|amount| transaction.with_amount(amount))
+                .ok_or(ConversionError::AmountOverflow)
+        });
+        let metadata = input
+            .metadata
+            .iter()
+            .map(|&i| record.get(i).unwrap_or_default().to_string())
+            .collect();
+        let line = OutputLine::from_transactions(
+            transaction,
+            quote.as_ref().ok(),
+            to_transaction,
+            metadata,
+        );
+
+        if columns.keep {
+            for field in &record {
+                writer.write_field(field)?;
+            }
+            writer.serialize(line.conversion())?;
+        } else {
             writer.serialize(line)?;
-            Ok(())
+        }
+    }
+    writer.flush()?;
+
+    Ok(unconverted)
+}
+
+/// An input record with the transaction read from it, or why it could not be read.
+type TransactionRecord<D> =
+    Result<(StringRecord, Result<Transaction<D>, Box<dyn Error>>), csv::Error>;
+
+/// The input file, with the transactions read from its records one at a time.
+struct Input<I> {
+    headers: StringRecord,
+    /// The positions of the columns kept as metadata.
+    metadata: Vec<usize>,
+    records: I,
+}
+
+/// Reads transactions with the columns `date` and the amount column, and optionally `id`,
+/// `description` and `category`. The non-empty values of other columns are kept as
+/// metadata.
+fn read_transactions<'a, R, D>(
+    mut reader: Reader<R>,
+    columns: &'a Columns,
+) -> Result<Input<impl Iterator<Item = TransactionRecord<D>> + 'a>, Box<dyn Error>>
+where
+    R: Read + 'a,
+    D: CurrencyType,
+{
+    let headers = reader.headers()?.clone();
+    let position = |column: &str| headers.iter().position(|header| header.trim() == column);
+    let positions: Vec<Option<usize>> = COLUMNS
+        .iter()
+        .map(|column| match *column {
+            "amount" => position(&columns.amount),
+            column => position(column),
         })
-        .collect::<Result<Vec<_>, csv::Error>>()?;
+        .collect();
+    for (column, position) in [("date", positions[0]), (&columns.amount, positions[1])] {
+        if position.is_none() {
+            return Err(format!("The input has no {} column", column).into());
+        }
+    }
+    let metadata: Vec<usize> = (0..headers.len())
+        .filter(|i| !positions.contains(&Some(*i)))
+        .collect();
+
+    let fields = StringRecord::from(COLUMNS.to_vec());
+    let metadata_columns: Vec<(usize, String)> = metadata
+        .iter()
+        .map(|&i| (i, headers[i].to_string()))
+        .collect();
+    let records = reader.into_records().map(move |record| {
+        let record = record?;
+        let transaction =
+            read_transaction(&record, &fields, &positions, &metadata_columns, columns);
+        Ok((record, transaction))
+    });
 
-    Ok(())
+    Ok(Input {
+        headers,
+        metadata,
+        records,
+    })
+}
+
+/// Reads the transaction of a record, whose `positions` of the [`COLUMNS`] are named by
+/// `fields`, with the values of the `metadata` columns as its metadata.
+fn read_transaction<D>(
+    record: &StringRecord,
+    fields: &StringRecord,
+    positions: &[Option<usize>],
+    metadata: &[(usize, String)],
+    columns: &Columns,
+) -> Result<Transaction<D>, Box<dyn Error>>
+where
+    D: CurrencyType,
+{
+    let mut values: StringRecord = positions
+        .iter()
+        .map(|position| position.and_then(|i| record.get(i)).unwrap_or(""))
+        .collect();
+    values.set_position(record.position().cloned());
+    let line: InputLine = values.deserialize(Some(fields))?;
+
+    let line_error = |error: &dyn Display| {
+        let line = record.position().map_or(0, |position| position.line());
+        format!("Line {}: {}", line, error)
+    };
+    let amount = match &columns.locale {
+        Some(locale) => locale.parse(&line.amount),
+        None => locale::parse_amount_for(&line.amount, D::minor_units()),
+    }
+    .map_err(|error| line_error(&error))?;
+    let amount = Currency::from_decimal_exact(amount).map_err(|error| line_error(&error))?;
+    let mut transaction = match NaiveDate::parse_from_str(&line.date, "%Y-%m-%d") {
+        Ok(date) => Transaction::new(date, amount),
+        Err(_) => Transaction::at(
+            Timestamp::parse(&line.date, columns.timezone).map_err(|error| line_error(&error))?,
+            columns.reporting_timezone,
+            amount,
+        ),
+    };
+    if let Some(id) = line.id {
+        transaction = transaction.with_id(id);
+    }
+    if let Some(description) = line.description {
+        transaction = transaction.with_description(description);
+    }
+    if let Some(category) = line.category {
+        transaction = transaction.with_category(category);
+    }
+    for (i, column) in metadata {
+        if let Some(value) = record.get(*i).filter(|value| !value.is_empty()) {
+            transaction = transaction.with_metadata(column, value);
+        }
+    }
+    Ok(transaction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversions::{
+        currency::{EUR, USD},
+        daily_exchange_rates::DailyExchangeRates,
+    };
+
+    fn read(data: &str, columns: &Columns) -> Result<Vec<Transaction<EUR>>, Box<dyn Error>> {
+        read_transactions(Reader::from_reader(data.as_bytes()), columns)?
+            .records
+            .map(|record| record?.1)
+            .collect()
+    }
+
+    #[test]
+    fn test_read_transactions() {
+        let data = "\
+date, amount,id,description,category,reference,counterparty
+2024-05-03,-12.50,tx-1,Lunch,Food,RF18 5390 0754 7034,
+2024-05-06,100,,,,,ACME GmbH
+";
+        let transactions = read(data, &Columns::default()).unwrap();
+
+        assert_eq!(
+            transactions[0],
+            Transaction::new(
+                NaiveDate::from_ymd_opt(2024, 5, 3).unwrap(),
+                Currency::try_from(-12.5).unwrap()
+            )
+            .with_id("tx-1")
+            .with_description("Lunch")
+            .with_category("Food")
+            .with_metadata("reference", "RF18 5390 0754 7034")
+        );
+        assert_eq!(transactions[1].id(), None);
+
+        let error = read(
+            "date,amount\n2024-05-03,1.005\n",
+            &Columns {
+                locale: Some(Locale::EN_US),
+                ..Columns::default()
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Line 2: Amount 1.005 has more than 2 decimal places"
+        );
+
+        let input = "date,amount\n2024-05-03,1.234\n2024-05-04,\"-1.234,5\"\n";
+        let error = read(input, &Columns::default()).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Line 2: Ambiguous amount 1.234"));
+        let german = read(
+            input,
+            &Columns {
+                locale: Some(Locale::DE_DE),
+                ..Columns::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(german[0].amount(), Currency::try_from(1234.0).unwrap());
+        assert_eq!(german[1].amount(), Currency::try_from(-1234.5).unwrap());
+        assert_eq!(transactions[1].description(), None);
+        assert_eq!(
+            transactions[1]
+                .metadata()
+                .get("counterparty")
+                .map(String::as_str),
+            Some("ACME GmbH")
+        );
+    }
+
+    #[test]
+    fn test_keep_columns() {
+        let data = "\
+date,reference,gross,amount
+2024-05-03,R-1,100,99.50
+2024-05-04,R-2,50,49.75
+2024-05-05,R-3,n/a,0
+";
+        let columns = Columns {
+            amount: "gross".to_string(),
+            keep: true,
+            ..Columns::default()
+        };
+        let convert = |data: &str| {
+            let rates: DailyExchangeRates<USD, EUR> = DailyExchangeRates::new([(
+                NaiveDate::from_ymd_opt(2024, 5, 3).unwrap(),
+                ExchangeRate::new(1.0773),
+            )]);
+            let mut output = vec![];
+            write_converted(
+                Reader::from_reader(data.as_bytes()),
+                &mut output,
+                rates,
+                RoundingMode::default(),
+                SidePolicy::Mid,
+                None,
+                &columns,
+            )
+            .map(|unconverted| (String::from_utf8(output).unwrap(), unconverted))
+        };
+
+        let (output, unconverted) = convert(data).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines[0],
+            "date,reference,gross,amount,exchange_rate,rate_side,rate_legs,rate_date,rate_source,to_amount"
+        );
+        assert_eq!(
+            lines[1],
+            "2024-05-03,R-1,100,99.50,1.0773,mid,,2024-05-03,,107.73"
+        );
+        assert!(lines[2].starts_with("2024-05-04,R-2,50,49.75,,,,,,"));
+        assert_eq!(lines[3], "2024-05-05,R-3,n/a,0,,,,,,");
+        assert_eq!(unconverted, vec![4]);
+
+        let error = convert("date,gross,to_amount\n2024-05-03,100,107.73\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The input already has a to_amount column, which the conversion would repeat"
+        );
+
+        let missing = Columns {
+            amount: "net".to_string(),
+            ..Columns::default()
+        };
+        assert!(read(data, &missing).is_err());
+    }
+
+    #[test]
+    fn test_metadata_columns() {
+        let data = "\
+date,note,amount,id,reference
+2024-05-03,\"a=b; c\",99.50,tx-1,R-1
+2024-05-04,,49.75,,R-2
+";
+        let rates: DailyExchangeRates<USD, EUR> = DailyExchangeRates::new([(
+            NaiveDate::from_ymd_opt(2024, 5, 3).unwrap(),
+            ExchangeRate::new(1.0773),
+        )]);
+
+        let mut output = vec![];
+        write_converted(
+            Reader::from_reader(data.as_bytes()),
+            &mut output,
+            rates,
+            RoundingMode::default(),
+            SidePolicy::Mid,
+            None,
+            &Columns::default(),
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines[0],
+            "date,from_amount,exchange_rate,rate_side,rate_legs,rate_date,rate_source,to_amount,id,description,category,timestamp,note,reference"
+        );
+        assert_eq!(
+            lines[1],
+            "2024-05-03,99.50,1.0773,mid,,2024-05-03,,107.19,tx-1,,,,a=b; c,R-1"
+        );
+        assert!(lines[2].ends_with(",,,,,,R-2"));
+    }
+
+    #[test]
+    fn test_reporting_timezone() {
+        let data = "\
+date,amount
+2023-12-31 19:30:00,100
+2023-12-31T12:00:00+09:00,100
+2023-12-31,100
+";
+        let columns = Columns {
+            timezone: "America/New_York".parse().unwrap(),
+            reporting_timezone: "Europe/Berlin".parse().unwrap(),
+            ..Columns::default()
+        };
+        let transactions = read(data, &columns).unwrap();
+
+        let date = |day| NaiveDate::from_ymd_opt(2023, 12, day).unwrap();
+        assert_eq!(
+            transactions[0].date(),
+            &NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+        );
+        assert_eq!(
+            transactions[0].timestamp().map(ToString::to_string),
+            Some("2023-12-31 19:30:00 America/New_York".to_string())
+        );
+        assert_eq!(transactions[1].date(), &date(31));
+        assert_eq!(transactions[2].date(), &date(31));
+        assert_eq!(transactions[2].timestamp(), None);
+    }
 }
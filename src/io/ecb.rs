@@ -0,0 +1,383 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::Display,
+    io::{Read, Seek},
+};
+
+use chrono::NaiveDate;
+use csv::Reader;
+use roxmltree::{Document, Node};
+use zip::ZipArchive;
+
+use crate::{
+    conversions::wide_exchange_rates::{WideExchangeRates, WideRatesError},
+    currency::Currency,
+};
+
+/// The provider named as the origin of the rates read from these files.
+pub const PROVIDER: &str = "ECB";
+
+#[derive(Debug)]
+pub enum EcbError {
+    Io(std::io::Error),
+    Zip(zip::result::ZipError),
+    Xml(roxmltree::Error),
+    Csv(WideRatesError),
+    /// The zip archive contains neither a CSV nor an XML file.
+    NoRatesFile,
+    InvalidDate(String),
+    InvalidRate {
+        currency: Currency,
+        rate: String,
+    },
+    /// The rates are quoted against another currency than the expected base.
+    WrongBase {
+        base: Currency,
+        expected: Currency,
+    },
+}
+
+impl Display for EcbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Zip(error) => write!(f, "{}", error),
+            Self::Xml(error) => write!(f, "{}", error),
+            Self::Csv(error) => write!(f, "{}", error),
+            Self::NoRatesFile => write!(f, "The archive contains no CSV or XML file"),
+            Self::InvalidDate(date) => write!(f, "Invalid date: {}", date),
+            Self::InvalidRate { currency, rate } => {
+                write!(f, "Invalid {} rate: {}", currency, rate)
+            }
+            Self::WrongBase { base, expected } => {
+                write!(f, "The rates are quoted against {}, not {}", base, expected)
+            }
+        }
+    }
+}
+
+impl Error for EcbError {}
+
+impl From<std::io::Error> for EcbError {
+    fn from(error: std::io::Error) -> Self {
+        EcbError::Io(error)
+    }
+}
+
+impl From<zip::result::ZipError> for EcbError {
+    fn from(error: zip::result::ZipError) -> Self {
+        EcbError::Zip(error)
+    }
+}
+
+impl From<roxmltree::Error> for EcbError {
+    fn from(error: roxmltree::Error) -> Self {
+        EcbError::Xml(error)
+    }
+}
+
+impl From<WideRatesError> for EcbError {
+    fn from(error: WideRatesError) -> Self {
+        EcbError::Csv(error)
+    }
+}
+
+/// Reads the ECB reference rates from a zip archive like `eurofxref-hist.zip`, which holds
+/// a single CSV or XML file.
+pub fn read_ecb_zip<R>(reader: R, base: Currency) -> Result<WideExchangeRates, EcbError>
+where
+    R: Read + Seek,
+{
+    let mut archive = ZipArchive::new(reader)?;
+    let name = archive
+        .file_names()
+        .find(|name| {
+            let name = name.to_ascii_lowercase();
+            name.ends_with(".csv") || name.ends_with(".xml")
+        })
+        .ok_or(EcbError::NoRatesFile)?
+        .to_string();
+    let file = archive.by_name(&name)?;
+
+    if name.to_ascii_lowercase().ends_with(".csv") {
+        Ok(WideExchangeRates::read_from_csv(
+            Reader::from_reader(file),
+            base,
+        )?)
+    } else {
+        read_sdmx(file, base)
+    }
+}
+
+/// Reads daily rates from XML in one of the formats the ECB publishes:
+///
+/// - the reference rates files like `eurofxref-hist.xml`, with nested `Cube` elements,
+/// - SDMX-ML generic data, with `SeriesKey` and `ObsValue` elements, and
+/// - SDMX-ML structure specific data, with `Series` and `Obs` attributes,
+///
+/// all of them quoted against `base`. Series with other than daily rates are skipped with a
+/// warning. Observations without a value are gaps.
+pub fn read_sdmx<R>(mut reader: R, base: Currency) -> Result<WideExchangeRates, EcbError>
+where
+    R: Read,
+{
+    let mut xml = String::new();
+    reader.read_to_string(&mut xml)?;
+    let document = Document::parse(&xml)?;
+    let mut rates = WideExchangeRates::new(base);
+
+    for node in document.descendants().filter(|node| node.is_element()) {
+        match node.tag_name().name() {
+            "Cube" if node.has_attribute("time") => read_cube(node, base, &mut rates)?,
+            "Series" => read_series(node, base, &mut rates)?,
+            _ => {}
+        }
+    }
+
+    Ok(rates)
+}
+
+/// A day of reference rates, which are always quoted against the EUR.
+fn read_cube(day: Node, base: Currency, rates: &mut WideExchangeRates) -> Result<(), EcbError> {
+    if base != Currency::EUR {
+        return Err(EcbError::WrongBase {
+            base: Currency::EUR,
+            expected: base,
+        });
+    }
+    let date = parse_date(day.attribute("time").unwrap_or_default())?;
+
+    for cube in day
+        .children()
+        .filter(|node| node.tag_name().name() == "Cube")
+    {
+        let (Some(currency), Some(rate)) = (cube.attribute("currency"), cube.attribute("rate"))
+        else {
+            continue;
+        };
+        insert(rates, currency, date, rate)?;
+    }
+    Ok(())
+}
+
+fn read_series(
+    series: Node,
+    base: Currency,
+    rates: &mut WideExchangeRates,
+) -> Result<(), EcbError> {
+    let key = series_key(series);
+    if let Some(freq) = key.get("FREQ").filter(|freq| **freq != "D") {
+        log::warn!(
+            "Skipping {} series with frequency {}",
+            key.get("CURRENCY").unwrap_or(&"unknown"),
+            freq
+        );
+        return Ok(());
+    }
+    if let Some(denom) = key.get("CURRENCY_DENOM") {
+        match denom.parse::<Currency>() {
+            Ok(denom) if denom == base => {}
+            Ok(denom) => {
+                return Err(EcbError::WrongBase {
+                    base: denom,
+                    expected: base,
+                })
+            }
+            Err(_) => {
+                log::warn!("Skipping rates against unknown currency {}", denom);
+                return Ok(());
+            }
+        }
+    }
+    let Some(currency) = key.get("CURRENCY") else {
+        return Ok(());
+    };
+
+    for obs in series
+        .children()
+        .filter(|node| node.tag_name().name() == "Obs")
+    {
+        let date = obs
+            .attribute("TIME_PERIOD")
+            .or_else(|| child_value(obs, "ObsDimension"))
+            .unwrap_or_default();
+        let date = parse_date(date)?;
+        if let Some(rate) = obs
+            .attribute("OBS_VALUE")
+            .or_else(|| child_value(obs, "ObsValue"))
+        {
+            insert(rates, currency, date, rate)?;
+        }
+    }
+    Ok(())
+}
+
+/// The dimensions of a series, from its attributes or from the `Value` elements of its
+/// `SeriesKey`.
+fn series_key<'a>(series: Node<'a, '_>) -> HashMap<&'a str, &'a str> {
+    let mut key: HashMap<_, _> = series
+        .attributes()
+        .map(|attribute| (attribute.name(), attribute.value()))
+        .collect();
+    let values = series
+        .children()
+        .filter(|node| node.tag_name().name() == "SeriesKey")
+        .flat_map(|series_key| series_key.children())
+        .filter(|node| node.tag_name().name() == "Value");
+    for value in values {
+        if let (Some(id), Some(value)) = (value.attribute("id"), value.attribute("value")) {
+            key.insert(id, value);
+        }
+    }
+    key
+}
+
+fn child_value<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|child| child.tag_name().name() == name)?
+        .attribute("value")
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, EcbError> {
+    date.trim()
+        .parse()
+        .map_err(|_| EcbError::InvalidDate(date.to_string()))
+}
+
+/// Adds a rate, skipping currencies we do not know and the `NaN` that marks a missing
+/// observation.
+fn insert(
+    rates: &mut WideExchangeRates,
+    currency: &str,
+    date: NaiveDate,
+    rate: &str,
+) -> Result<(), EcbError> {
+    let Ok(currency) = currency.parse() else {
+        log::warn!("Skipping rates of unknown currency {}", currency);
+        return Ok(());
+    };
+    let rate = rate.trim();
+    if rate.is_empty() || rate.eq_ignore_ascii_case("NaN") {
+        return Ok(());
+    }
+    let rate = rate.parse().map_err(|_| EcbError::InvalidRate {
+        currency,
+        rate: rate.to_string(),
+    })?;
+    rates.insert(currency, date, rate);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::Cursor};
+
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap()
+    }
+
+    #[test]
+    fn test_read_cube() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gesmes:Envelope xmlns:gesmes="http://www.gesmes.org/xml/2002-08-01" xmlns="http://www.ecb.int/vocabulary/2002-08-01/eurofxref">
+  <gesmes:subject>Reference rates</gesmes:subject>
+  <Cube>
+    <Cube time="2024-05-03">
+      <Cube currency="USD" rate="1.0773"/>
+      <Cube currency="JPY" rate="164.99"/>
+    </Cube>
+    <Cube time="2024-05-02">
+      <Cube currency="USD" rate="1.0702"/>
+    </Cube>
+  </Cube>
+</gesmes:Envelope>"#;
+        let rates = read_sdmx(xml.as_bytes(), Currency::EUR).unwrap();
+
+        assert_eq!(rates.currencies(), vec![Currency::JPY, Currency::USD]);
+        assert_eq!(
+            rates.rate(Currency::USD, &date(2)),
+            Some("1.0702".parse().unwrap())
+        );
+        assert!(matches!(
+            read_sdmx(xml.as_bytes(), Currency::USD),
+            Err(EcbError::WrongBase { .. })
+        ));
+    }
+
+    #[test]
+    fn test_read_generic_series() {
+        let xml = r#"<message:GenericData xmlns:message="http://www.sdmx.org/resources/sdmxml/schemas/v2_1/message" xmlns:generic="http://www.sdmx.org/resources/sdmxml/schemas/v2_1/data/generic">
+  <message:DataSet>
+    <generic:Series>
+      <generic:SeriesKey>
+        <generic:Value id="FREQ" value="D"/>
+        <generic:Value id="CURRENCY" value="USD"/>
+        <generic:Value id="CURRENCY_DENOM" value="EUR"/>
+      </generic:SeriesKey>
+      <generic:Obs>
+        <generic:ObsDimension value="2024-05-02"/>
+        <generic:ObsValue value="1.0702"/>
+      </generic:Obs>
+      <generic:Obs>
+        <generic:ObsDimension value="2024-05-03"/>
+        <generic:ObsValue value="NaN"/>
+      </generic:Obs>
+    </generic:Series>
+    <generic:Series>
+      <generic:SeriesKey>
+        <generic:Value id="FREQ" value="M"/>
+        <generic:Value id="CURRENCY" value="JPY"/>
+        <generic:Value id="CURRENCY_DENOM" value="EUR"/>
+      </generic:SeriesKey>
+      <generic:Obs>
+        <generic:ObsDimension value="2024-05"/>
+        <generic:ObsValue value="166.0"/>
+      </generic:Obs>
+    </generic:Series>
+  </message:DataSet>
+</message:GenericData>"#;
+        let rates = read_sdmx(xml.as_bytes(), Currency::EUR).unwrap();
+
+        assert_eq!(rates.currencies(), vec![Currency::USD]);
+        assert_eq!(
+            rates.rate(Currency::USD, &date(2)),
+            Some("1.0702".parse().unwrap())
+        );
+        assert_eq!(rates.rate(Currency::USD, &date(3)), None);
+    }
+
+    #[test]
+    fn test_read_structure_specific_series() {
+        let xml = r#"<message:StructureSpecificData xmlns:message="http://www.sdmx.org/resources/sdmxml/schemas/v2_1/message">
+  <message:DataSet>
+    <Series FREQ="D" CURRENCY="JPY" CURRENCY_DENOM="EUR" EXR_TYPE="SP00">
+      <Obs TIME_PERIOD="2024-05-03" OBS_VALUE="164.99"/>
+    </Series>
+  </message:DataSet>
+</message:StructureSpecificData>"#;
+        let rates = read_sdmx(xml.as_bytes(), Currency::EUR).unwrap();
+
+        assert_eq!(
+            rates.rate(Currency::JPY, &date(3)),
+            Some("164.99".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_read_ecb_zip() {
+        let file = File::open("test_files/eurofxref-hist.zip").unwrap();
+        let rates = read_ecb_zip(file, Currency::EUR).unwrap();
+
+        assert_eq!(
+            rates.rate(Currency::JPY, &date(3)),
+            Some("164.99".parse().unwrap())
+        );
+        assert!(matches!(
+            read_ecb_zip(Cursor::new(b"not a zip".to_vec()), Currency::EUR),
+            Err(EcbError::Zip(_))
+        ));
+    }
+}
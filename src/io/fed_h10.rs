@@ -0,0 +1,166 @@
+use std::io::Read;
+
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, StringRecord};
+
+use crate::{
+    conversions::rate_series::{RateSeries, SeriesFileError},
+    currency::{Currency, CurrencyPair},
+};
+
+/// The provider named as the origin of the rates read from these files.
+pub const PROVIDER: &str = "Federal Reserve H.10";
+
+/// Reads a download of the Federal Reserve's H.10 release from its Data Download Program.
+/// Metadata rows such as `Series Description`, `Unit:` and `Currency:` precede a
+/// `Time Period` row with the series identifiers, which is followed by one row per day.
+///
+/// Most series give the price of one USD, but some, like the EUR, the price in USD: the
+/// unit `Currency:_Per_EUR` with currency `USD` is quoted as EUR/USD. Days without a value
+/// are marked with `ND` and are left out. Series of currencies we do not know are skipped
+/// with a warning.
+pub fn read_fed_h10<R>(reader: R) -> Result<Vec<RateSeries>, SeriesFileError>
+where
+    R: Read,
+{
+    let mut records = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(reader)
+        .into_records();
+
+    let mut metadata = Vec::new();
+    let header = loop {
+        let record = records.next().ok_or(SeriesFileError::MissingHeader)??;
+        if record.get(0).unwrap_or_default().trim() == "Time Period" {
+            break record;
+        }
+        metadata.push(record);
+    };
+    let mut series = columns(&header, &metadata)?;
+
+    for record in records {
+        let record = record?;
+        let line = record.position().map_or(0, |position| position.line());
+        let date = record.get(0).unwrap_or_default().trim();
+        let date = date
+            .parse::<NaiveDate>()
+            .map_err(|_| SeriesFileError::InvalidDate {
+                line,
+                date: date.to_string(),
+            })?;
+
+        for (i, series) in series.iter_mut() {
+            let value = record.get(*i).unwrap_or_default().trim();
+            if value.is_empty() || value == "ND" {
+                continue;
+            }
+            let rate = value.parse().map_err(|_| SeriesFileError::InvalidRate {
+                line,
+                series: series.id().to_string(),
+                rate: value.to_string(),
+            })?;
+            series.insert(date, rate, Some(line));
+        }
+    }
+
+    Ok(series.into_iter().map(|(_, series)| series).collect())
+}
+
+/// The series in the columns of the file, with their metadata.
+fn columns(
+    header: &StringRecord,
+    metadata: &[StringRecord],
+) -> Result<Vec<(usize, RateSeries)>, SeriesFileError> {
+    let mut columns = Vec::new();
+
+    for (i, id) in header.iter().enumerate().skip(1) {
+        let id = id.trim();
+        let metadata: Vec<(String, String)> = metadata
+            .iter()
+            .map(|row| {
+                let key = row.get(0).unwrap_or_default().trim().trim_end_matches(':');
+                let value = row.get(i).unwrap_or_default().trim();
+                (key.to_string(), value.to_string())
+            })
+            .collect();
+        let value = |key: &str| {
+            metadata
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, value)| value.as_str())
+        };
+
+        let Some(pair) = series_pair(value("Unit"), value("Currency")) else {
+            log::warn!("Skipping series {} of an unknown currency pair", id);
+            continue;
+        };
+        if let Some(multiplier) = value("Multiplier").filter(|multiplier| *multiplier != "1") {
+            return Err(SeriesFileError::UnsupportedMultiplier {
+                series: id.to_string(),
+                multiplier: multiplier.to_string(),
+            });
+        }
+
+        columns.push((i, RateSeries::new(id.to_string(), pair, metadata)));
+    }
+
+    Ok(columns)
+}
+
+/// The pair of a series with unit `Currency:_Per_<BASE>` and currency `<QUOTE>`.
+fn series_pair(unit: Option<&str>, currency: Option<&str>) -> Option<CurrencyPair> {
+    let (_, base) = unit?.split_once("_Per_")?;
+    let base: Currency = base.parse().ok()?;
+    let quote: Currency = currency?.parse().ok()?;
+    (base != quote).then(|| CurrencyPair::new(base, quote))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = r#""Series Description","Spot exchange rate, U.S. dollars to euro","Japanese yen to one U.S. dollar","Gold"
+"Unit:","Currency:_Per_EUR","Currency:_Per_USD","Ounces"
+"Multiplier:","1","1","1"
+"Currency:","USD","JPY","NA"
+"Unique Identifier: ","H10/H10/RXI$US_N.B.EU","H10/H10/RXI_N.B.JA","H10/H10/GOLD"
+"Time Period","RXI$US_N.B.EU","RXI_N.B.JA","GOLD"
+2024-05-02,1.0702,155.95,1
+2024-05-03,ND,153.04,1
+"#;
+
+    #[test]
+    fn test_read_fed_h10() {
+        let date = |day| NaiveDate::from_ymd_opt(2024, 5, day).unwrap();
+        let series = read_fed_h10(CSV.as_bytes()).unwrap();
+
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].id(), "RXI$US_N.B.EU");
+        assert_eq!(series[0].pair(), "EUR/USD".parse().unwrap());
+        assert_eq!(series[0].rate(&date(3)), None);
+        assert_eq!(series[1].pair(), "USD/JPY".parse().unwrap());
+        assert_eq!(series[1].rate(&date(3)), Some("153.04".parse().unwrap()));
+        assert_eq!(
+            series[1].metadata()[0],
+            (
+                "Series Description".into(),
+                "Japanese yen to one U.S. dollar".into()
+            )
+        );
+    }
+
+    #[test]
+    fn test_missing_header() {
+        assert!(matches!(
+            read_fed_h10(
+                CSV.lines()
+                    .take(5)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+                    .as_bytes()
+            ),
+            Err(SeriesFileError::MissingHeader)
+        ));
+    }
+}
@@ -1,3 +1,9 @@
+pub mod average_rates;
+pub mod bundesbank;
 pub mod convert_transactions;
+pub mod ecb;
+pub mod fed_h10;
+pub mod providers;
+pub mod rates;
 pub mod read_ibkr_trades;
 pub mod utils;
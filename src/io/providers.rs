@@ -0,0 +1,444 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use chrono::{NaiveDate, SubsecRound, Utc};
+use csv::{Reader, Writer};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    conversions::{
+        rate_provider::{ProviderError, RateProvider},
+        rate_store::{RateStore, StoredRate},
+        validation::RateChecks,
+    },
+    currency::{Currency, CurrencyPair},
+    io::{ecb, utils::read_stored_rates},
+};
+
+/// The ECB data API's exchange rates dataset.
+pub const ECB_API: &str = "https://data-api.ecb.europa.eu/service/data/EXR";
+
+/// The rates of a local file in any format with one rate per day.
+pub struct FileProvider {
+    path: String,
+    pair: Option<CurrencyPair>,
+    base: Currency,
+}
+
+impl FileProvider {
+    /// `pair` declares the quote direction of files with a single pair that do not declare
+    /// it themselves, and `base` the currency that files with one column per currency are
+    /// quoted against.
+    pub fn new(path: String, pair: Option<CurrencyPair>, base: Currency) -> Self {
+        FileProvider { path, pair, base }
+    }
+}
+
+impl RateProvider for FileProvider {
+    fn name(&self) -> &str {
+        &self.path
+    }
+
+    fn fetch(
+        &self,
+        pair: CurrencyPair,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<StoredRate>, ProviderError> {
+        let mut store = RateStore::new();
+        store.merge(
+            read_stored_rates(
+                &self.path,
+                self.pair,
+                self.base,
+                &self.path,
+                &RateChecks::default(),
+            )
+            .map_err(ProviderError::Unavailable)?,
+        );
+        store.fetch(pair, from, to)
+    }
+}
+
+/// The ECB reference rates from the ECB data API, which quotes every currency against the
+/// EUR. Pairs without the EUR are fetched as the rates of both currencies.
+pub struct EcbProvider {
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl Default for EcbProvider {
+    fn default() -> Self {
+        EcbProvider::new(ECB_API.to_string())
+    }
+}
+
+impl EcbProvider {
+    /// A provider for the dataset at `url`, such as [`ECB_API`] or a mirror of it.
+    pub fn new(url: String) -> Self {
+        EcbProvider {
+            url,
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+}
+
+impl RateProvider for EcbProvider {
+    fn name(&self) -> &str {
+        ecb::PROVIDER
+    }
+
+    fn fetch(
+        &self,
+        pair: CurrencyPair,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<StoredRate>, ProviderError> {
+        let currencies: Vec<String> = [pair.base(), pair.quote()]
+            .into_iter()
+            .filter(|currency| *currency != Currency::EUR)
+            .map(|currency| currency.to_string())
+            .collect();
+        if currencies.is_empty() {
+            return Err(ProviderError::UnknownPair(pair));
+        }
+        let url = format!(
+            "{}/D.{}.EUR.SP00.A?startPeriod={}&endPeriod={}&format=genericdata",
+            self.url.trim_end_matches('/'),
+            currencies.join("+"),
+            from,
+            to
+        );
+
+        let retrieved = Utc::now().trunc_subsecs(0);
+        let rates = match self.agent.get(&url).call() {
+            Ok(response) => ecb::read_sdmx(response.into_reader(), Currency::EUR)
+                .map_err(|error| ProviderError::Unavailable(Box::new(error)))?,
+            // The API answers with Not Found if there are no rates in the range.
+            Err(ureq::Error::Status(404, _)) => return Ok(vec![]),
+            Err(error) => return Err(ProviderError::Unavailable(Box::new(error))),
+        };
+
+        let mut rates: Vec<StoredRate> = rates
+            .rates()
+            .filter(|(currency, date, _)| {
+                [pair.base(), pair.quote()].contains(currency) && (from..=to).contains(date)
+            })
+            .map(|(currency, date, rate)| StoredRate {
+                date,
+                base: Currency::EUR,
+                quote: currency,
+                rate,
+                source: ecb::PROVIDER.to_string(),
+                retrieved: Some(retrieved),
+            })
+            .collect();
+        rates.sort_by_key(|rate| (rate.date, rate.quote.code()));
+        Ok(rates)
+    }
+}
+
+/// Keeps the rates fetched by another provider in a directory, in one rate store file per
+/// pair and a file listing the ranges of dates fetched for it. Requests for dates that had
+/// passed when they were fetched are answered from the cache. Otherwise the dates that are
+/// not cached yet are fetched and merged into it, falling back to the cache if that fails.
+pub struct CachedProvider<P> {
+    provider: P,
+    dir: PathBuf,
+}
+
+/// A range of dates fetched on `retrieved`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+struct FetchedRange {
+    from: NaiveDate,
+    to: NaiveDate,
+    retrieved: NaiveDate,
+}
+
+impl FetchedRange {
+    /// The last date whose rates cannot change anymore, as it had passed when they were
+    /// fetched.
+    fn last_final(&self) -> Option<NaiveDate> {
+        self.to.min(self.retrieved.pred_opt()?).into()
+    }
+}
+
+impl<P> CachedProvider<P>
+where
+    P: RateProvider,
+{
+    pub fn new(provider: P, dir: PathBuf) -> Self {
+        CachedProvider { provider, dir }
+    }
+
+    /// The rate store file and the file with the fetched ranges of `pair`, shared with its
+    /// inverse.
+    fn paths(&self, pair: CurrencyPair) -> (PathBuf, PathBuf) {
+        let (first, second) = if pair.base().to_string() <= pair.quote().to_string() {
+            (pair.base(), pair.quote())
+        } else {
+            (pair.quote(), pair.base())
+        };
+        let name: String = self
+            .provider
+            .name()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let stem = format!("{}-{}{}", name, first, second);
+        (
+            self.dir.join(format!("{}.csv", stem)),
+            self.dir.join(format!("{}-ranges.csv", stem)),
+        )
+    }
+
+    fn read(&self, pair: CurrencyPair) -> Option<(RateStore, Vec<FetchedRange>)> {
+        let (rates_path, ranges_path) = self.paths(pair);
+        let store = RateStore::read_from_csv(Reader::from_path(rates_path).ok()?).ok()?;
+        let ranges = Reader::from_path(ranges_path)
+            .ok()?
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .ok()?;
+        Some((store, ranges))
+    }
+
+    fn write(
+        &self,
+        pair: CurrencyPair,
+        store: &RateStore,
+        ranges: &[FetchedRange],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        fs::create_dir_all(&self.dir)?;
+        let (rates_path, ranges_path) = self.paths(pair);
+        store.write_to_csv(Writer::from_path(rates_path)?)?;
+        let mut writer = Writer::from_path(ranges_path)?;
+        for range in ranges {
+            writer.serialize(range)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// The first and last date from `from` to `to` that `ranges` do not cover for good, if any.
+fn uncovered(
+    ranges: &[FetchedRange],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Option<(NaiveDate, NaiveDate)> {
+    let covered = |date: &NaiveDate| {
+        ranges.iter().any(|range| {
+            range.from <= *date && range.last_final().is_some_and(|last| *date <= last)
+        })
+    };
+    let first = from
+        .iter_days()
+        .take_while(|date| *date <= to)
+        .find(|date| !covered(date))?;
+    let last = to
+        .iter_days()
+        .rev()
+        .take_while(|date| *date >= first)
+        .find(|date| !covered(date))?;
+    Some((first, last))
+}
+
+impl<P> RateProvider for CachedProvider<P>
+where
+    P: RateProvider,
+{
+    fn name(&self) -> &str {
+        self.provider.name()
+    }
+
+    fn fetch(
+        &self,
+        pair: CurrencyPair,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<StoredRate>, ProviderError> {
+        let cached = self.read(pair);
+        let (mut store, mut ranges) = cached.unwrap_or_default();
+        let Some((first, last)) = uncovered(&ranges, from, to) else {
+            return store.fetch(pair, from, to);
+        };
+
+        match self.provider.fetch(pair, first, last) {
+            Ok(rates) => {
+                for conflict in store.merge(rates).conflicts {
+                    log::warn!("{}", conflict);
+                }
+                ranges.push(FetchedRange {
+                    from: first,
+                    to: last,
+                    retrieved: Utc::now().date_naive(),
+                });
+                if let Err(error) = self.write(pair, &store, &ranges) {
+                    log::warn!("Could not cache rates in {}: {}", self.dir.display(), error);
+                }
+                store.fetch(pair, from, to)
+            }
+            Err(error) if !ranges.is_empty() => {
+                log::warn!("{}, using the cached rates", error);
+                store.fetch(pair, from, to)
+            }
+            Err(error) => Err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::{self, JoinHandle};
+
+    use super::*;
+
+    const XML: &str = r#"<message:GenericData xmlns:message="http://www.sdmx.org/resources/sdmxml/schemas/v2_1/message" xmlns:generic="http://www.sdmx.org/resources/sdmxml/schemas/v2_1/data/generic">
+  <message:DataSet>
+    <generic:Series>
+      <generic:SeriesKey>
+        <generic:Value id="FREQ" value="D"/>
+        <generic:Value id="CURRENCY" value="USD"/>
+        <generic:Value id="CURRENCY_DENOM" value="EUR"/>
+      </generic:SeriesKey>
+      <generic:Obs>
+        <generic:ObsDimension value="2024-05-03"/>
+        <generic:ObsValue value="1.0773"/>
+      </generic:Obs>
+    </generic:Series>
+    <generic:Series>
+      <generic:SeriesKey>
+        <generic:Value id="FREQ" value="D"/>
+        <generic:Value id="CURRENCY" value="JPY"/>
+        <generic:Value id="CURRENCY_DENOM" value="EUR"/>
+      </generic:SeriesKey>
+      <generic:Obs>
+        <generic:ObsDimension value="2024-05-03"/>
+        <generic:ObsValue value="164.99"/>
+      </generic:Obs>
+    </generic:Series>
+  </message:DataSet>
+</message:GenericData>"#;
+
+    /// Answers `requests` requests with `status` and `body` on a local port. Returns the URL
+    /// of the server and a handle that gives the URLs requested.
+    fn serve(
+        requests: usize,
+        status: u16,
+        body: &'static str,
+    ) -> (String, JoinHandle<Vec<String>>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/EXR", server.server_addr().to_ip().unwrap());
+        let handle = thread::spawn(move || {
+            let mut urls = vec![];
+            for request in server.incoming_requests().take(requests) {
+                urls.push(request.url().to_string());
+                let response = tiny_http::Response::from_string(body).with_status_code(status);
+                request.respond(response).unwrap();
+            }
+            urls
+        });
+        (url, handle)
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap()
+    }
+
+    #[test]
+    fn test_ecb_provider() {
+        let (url, handle) = serve(2, 200, XML);
+        let provider = EcbProvider::new(url);
+
+        let store = provider
+            .fetch_store("USD/JPY".parse().unwrap(), date(1), date(3))
+            .unwrap();
+        assert_eq!(
+            store
+                .get("EUR/JPY".parse().unwrap(), &date(3))
+                .map(|rate| rate.rate),
+            Some("164.99".parse().unwrap())
+        );
+        assert_eq!(
+            store.common_currency(Currency::USD, Currency::JPY),
+            Some(Currency::EUR)
+        );
+
+        let rates = provider
+            .fetch("EUR/USD".parse().unwrap(), date(1), date(2))
+            .unwrap();
+        assert_eq!(rates, vec![]);
+
+        assert_eq!(
+            handle.join().unwrap(),
+            vec![
+                "/EXR/D.USD+JPY.EUR.SP00.A?startPeriod=2024-05-01&endPeriod=2024-05-03&format=genericdata",
+                "/EXR/D.USD.EUR.SP00.A?startPeriod=2024-05-01&endPeriod=2024-05-02&format=genericdata",
+            ]
+        );
+    }
+
+    /// A directory for a test, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("t-utils-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_cached_provider() {
+        let dir = TempDir::new("cache");
+        let pair = "EUR/USD".parse().unwrap();
+
+        let (url, handle) = serve(1, 200, XML);
+        let provider = CachedProvider::new(EcbProvider::new(url), dir.0.clone());
+        let fetched = provider.fetch(pair, date(1), date(3)).unwrap();
+        assert_eq!(handle.join().unwrap().len(), 1);
+
+        let offline =
+            CachedProvider::new(EcbProvider::new("http://127.0.0.1:9".into()), dir.0.clone());
+        assert_eq!(offline.fetch(pair, date(1), date(3)).unwrap(), fetched);
+        assert_eq!(offline.fetch(pair, date(3), date(3)).unwrap(), fetched);
+        assert_eq!(
+            offline
+                .fetch("USD/EUR".parse().unwrap(), date(1), date(3))
+                .unwrap(),
+            fetched
+        );
+        assert!(matches!(
+            offline.fetch("EUR/JPY".parse().unwrap(), date(1), date(3)),
+            Err(ProviderError::Unavailable(_))
+        ));
+
+        let (url, handle) = serve(1, 200, XML);
+        let extended = CachedProvider::new(EcbProvider::new(url), dir.0.clone());
+        assert_eq!(extended.fetch(pair, date(2), date(6)).unwrap(), fetched);
+        assert_eq!(
+            handle.join().unwrap(),
+            vec!["/EXR/D.USD.EUR.SP00.A?startPeriod=2024-05-04&endPeriod=2024-05-06&format=genericdata"]
+        );
+    }
+
+    #[test]
+    fn test_not_found() {
+        let (url, handle) = serve(1, 404, "No results found.");
+        let rates = EcbProvider::new(url)
+            .fetch("EUR/USD".parse().unwrap(), date(4), date(5))
+            .unwrap();
+
+        assert_eq!(rates, vec![]);
+        handle.join().unwrap();
+    }
+}
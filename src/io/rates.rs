@@ -0,0 +1,102 @@
+use std::{error::Error, io::Write, ops::Bound};
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::conversions::{
+    calendar::{BusinessCalendar, DayKind},
+    currency::CurrencyType,
+    daily_exchange_rates::DailyExchangeRates,
+    date_map::Gap,
+    rate_store::{PairCoverage, StoredRate},
+};
+
+/// Writes daily rates from `from` to `to`, both included, to a CSV file with `date` and
+/// `BASE/QUOTE` columns, so that the file declares its own pair.
+pub fn write_daily_rates<N, D>(
+    output_path: &str,
+    rates: &DailyExchangeRates<N, D>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<(), Box<dyn Error>>
+where
+    N: CurrencyType + for<'de> Deserialize<'de>,
+    D: CurrencyType + for<'de> Deserialize<'de>,
+{
+    let mut writer = csv::Writer::from_path(output_path)?;
+    let pair = format!("{}/{}", D::currency(), N::currency());
+    writer.write_record(["date", pair.as_str()])?;
+
+    let range = (
+        from.map_or(Bound::Unbounded, Bound::Included),
+        to.map_or(Bound::Unbounded, Bound::Included),
+    );
+    for rate in rates.range(range) {
+        writer.serialize(rate)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes stored rates as CSV, with the columns of a rate store.
+pub fn write_stored_rates<'a, W>(
+    output: W,
+    rates: impl IntoIterator<Item = &'a StoredRate>,
+) -> Result<(), csv::Error>
+where
+    W: Write,
+{
+    let mut writer = csv::Writer::from_writer(output);
+    for rate in rates {
+        writer.serialize(rate)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the coverage of every pair as CSV, with the sources joined by `; `. The missing
+/// business days are left empty if they were not counted.
+pub fn write_coverage<W>(output: W, coverage: &[PairCoverage]) -> Result<(), csv::Error>
+where
+    W: Write,
+{
+    let mut writer = csv::Writer::from_writer(output);
+    writer.write_record(["pair", "first", "last", "rates", "missing", "sources"])?;
+    for pair in coverage {
+        writer.write_record([
+            pair.pair.to_string(),
+            pair.first.to_string(),
+            pair.last.to_string(),
+            pair.rates.to_string(),
+            pair.missing
+                .map(|missing| missing.to_string())
+                .unwrap_or_default(),
+            pair.sources.join("; "),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes every day of `gaps` as CSV, with what kind of day it is in `calendar` and whether
+/// a rate is expected for it.
+pub fn write_gaps<W>(output: W, gaps: &[Gap], calendar: &BusinessCalendar) -> Result<(), csv::Error>
+where
+    W: Write,
+{
+    let mut writer = csv::Writer::from_writer(output);
+    writer.write_record(["date", "day", "missing"])?;
+    for gap in gaps {
+        for date in gap.first.iter_days().take_while(|date| *date <= gap.last) {
+            let day = calendar.day_kind(&date);
+            writer.write_record([
+                date.to_string(),
+                day.to_string(),
+                (day == DayKind::BusinessDay).to_string(),
+            ])?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -1,9 +1,11 @@
 use std::io::Read;
 
-use chrono::NaiveDate;
+use chrono::{NaiveDate, NaiveTime};
 use csv::Reader;
 use serde::{de::Error, Deserialize, Deserializer};
 
+use crate::conversions::timezone::{Timestamp, Zone};
+
 #[derive(Debug, Deserialize, PartialEq)]
 pub struct IbkrInputLine {
     #[serde(rename = "DataDiscriminator")]
@@ -14,7 +16,7 @@ pub struct IbkrInputLine {
     pub symbol: String,
     #[serde(rename = "Date/Time")]
     #[serde(deserialize_with = "parse_ibkr_date_time")]
-    pub date: NaiveDate,
+    pub date_time: IbkrDateTime,
     #[serde(rename = "Quantity")]
     pub quantity: f64,
     #[serde(rename = "T. Price")]
@@ -25,18 +27,35 @@ pub struct IbkrInputLine {
     pub basis: f64,
 }
 
+/// The date of a line, and its time for trades, as shown in the statement.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IbkrDateTime {
+    pub date: NaiveDate,
+    pub time: Option<NaiveTime>,
+}
+
+impl IbkrDateTime {
+    /// The instant in `zone`, the time zone of the statement. Dates without a time are
+    /// taken at midnight.
+    pub fn timestamp(&self, zone: Zone) -> Timestamp {
+        Timestamp::new(self.date.and_time(self.time.unwrap_or_default()), zone)
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct IbkrInput {
     pub lines: Vec<IbkrInputLine>,
+    /// The time zone of the statement, which is set in the account settings.
+    pub zone: Zone,
 }
 
 impl IbkrInput {
-    pub fn read_from<R>(reader: &mut Reader<R>) -> Result<Self, csv::Error>
+    pub fn read_from<R>(reader: &mut Reader<R>, zone: Zone) -> Result<Self, csv::Error>
     where
         R: Read,
     {
         let lines = read_ibkr_trades(reader)?;
-        Ok(IbkrInput { lines })
+        Ok(IbkrInput { lines, zone })
     }
 }
 
@@ -47,28 +66,32 @@ where
     reader.deserialize().collect()
 }
 
-fn parse_ibkr_date_time<'de, D>(date_time: D) -> Result<NaiveDate, D::Error>
+fn parse_ibkr_date_time<'de, D>(date_time: D) -> Result<IbkrDateTime, D::Error>
 where
     D: Deserializer<'de>,
 {
     let date_time = String::deserialize(date_time)?;
-    let date = date_time
-        .split(",")
-        .next()
-        .ok_or_else(|| Error::custom("Missing date"))?;
-    NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(Error::custom)
+    let mut parts = date_time.split(",");
+    let date = parts.next().ok_or_else(|| Error::custom("Missing date"))?;
+    Ok(IbkrDateTime {
+        date: NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(Error::custom)?,
+        time: parts
+            .next()
+            .map(|time| NaiveTime::parse_from_str(time.trim(), "%H:%M:%S"))
+            .transpose()
+            .map_err(Error::custom)?,
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use chrono::NaiveDate;
-
     use super::*;
 
     #[test]
     fn test_read_from() {
         let mut reader = Reader::from_path("test_files/test_ibkr.csv").unwrap();
-        let trades = IbkrInput::read_from(&mut reader).unwrap();
+        let new_york: Zone = "America/New_York".parse().unwrap();
+        let trades = IbkrInput::read_from(&mut reader, new_york).unwrap();
         assert_eq!(
             trades,
             IbkrInput {
@@ -77,7 +100,10 @@ mod tests {
                         data_discriminator: "Order".into(),
                         currency: "USD".into(),
                         symbol: "TST".into(),
-                        date: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+                        date_time: IbkrDateTime {
+                            date: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+                            time: NaiveTime::from_hms_opt(12, 0, 0),
+                        },
                         quantity: 2.0,
                         t_price: 10.0,
                         proceeds: Some(-20.0),
@@ -87,7 +113,10 @@ mod tests {
                         data_discriminator: "Trade".into(),
                         currency: "USD".into(),
                         symbol: "TST".into(),
-                        date: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+                        date_time: IbkrDateTime {
+                            date: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+                            time: NaiveTime::from_hms_opt(12, 0, 0),
+                        },
                         quantity: 2.0,
                         t_price: 10.0,
                         proceeds: Some(-20.0),
@@ -97,7 +126,10 @@ mod tests {
                         data_discriminator: "Order".into(),
                         currency: "USD".into(),
                         symbol: "TST".into(),
-                        date: NaiveDate::from_ymd_opt(2023, 7, 10).unwrap(),
+                        date_time: IbkrDateTime {
+                            date: NaiveDate::from_ymd_opt(2023, 7, 10).unwrap(),
+                            time: NaiveTime::from_hms_opt(12, 0, 0),
+                        },
                         quantity: -3.0,
                         t_price: 12.0,
                         proceeds: Some(36.0),
@@ -107,7 +139,10 @@ mod tests {
                         data_discriminator: "Trade".into(),
                         currency: "USD".into(),
                         symbol: "TST".into(),
-                        date: NaiveDate::from_ymd_opt(2023, 7, 10).unwrap(),
+                        date_time: IbkrDateTime {
+                            date: NaiveDate::from_ymd_opt(2023, 7, 10).unwrap(),
+                            time: NaiveTime::from_hms_opt(12, 0, 0),
+                        },
                         quantity: -3.0,
                         t_price: 12.0,
                         proceeds: Some(36.0),
@@ -117,7 +152,10 @@ mod tests {
                         data_discriminator: "ClosedLot".into(),
                         currency: "USD".into(),
                         symbol: "TST".into(),
-                        date: NaiveDate::from_ymd_opt(2023, 4, 2).unwrap(),
+                        date_time: IbkrDateTime {
+                            date: NaiveDate::from_ymd_opt(2023, 4, 2).unwrap(),
+                            time: None,
+                        },
                         quantity: 1.0,
                         t_price: 11.0,
                         proceeds: None,
@@ -127,14 +165,22 @@ mod tests {
                         data_discriminator: "ClosedLot".into(),
                         currency: "USD".into(),
                         symbol: "TST".into(),
-                        date: NaiveDate::from_ymd_opt(2023, 2, 4).unwrap(),
+                        date_time: IbkrDateTime {
+                            date: NaiveDate::from_ymd_opt(2023, 2, 4).unwrap(),
+                            time: None,
+                        },
                         quantity: 2.0,
                         t_price: 10.0,
                         proceeds: None,
                         basis: 22.0
                     },
-                ]
+                ],
+                zone: new_york,
             }
-        )
+        );
+        assert_eq!(
+            trades.lines[1].date_time.timestamp(new_york).to_string(),
+            "2023-01-01 12:00:00 America/New_York"
+        );
     }
 }
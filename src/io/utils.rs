@@ -1,13 +1,576 @@
-use csv::Reader;
+use std::{error::Error, fmt::Display, fs::File, path::Path};
+
+use chrono::{DateTime, NaiveDate, SubsecRound, Utc};
+use csv::{Reader, StringRecord, Writer};
 use serde::Deserialize;
 
-use crate::conversions::{currency::CurrencyType, daily_exchange_rates::DailyExchangeRates};
+use crate::{
+    conversions::{
+        bid_ask_rates::DailyBidAskRates,
+        calendar::{BuiltinCalendar, BusinessCalendar},
+        currency::CurrencyType,
+        daily_exchange_rates::DailyExchangeRates,
+        decimal::Decimal,
+        period_exchange_rates::PeriodExchangeRates,
+        provenance::RateOrigin,
+        rate_series::RateSeries,
+        rate_store::{RateStore, StoredRate},
+        validation::{read_rows, reject_errors, RateChecks, RateIssue, RateRow, ValidationError},
+        wide_exchange_rates::WideExchangeRates,
+    },
+    currency::{Currency, CurrencyPair},
+    io::{bundesbank, ecb, fed_h10},
+};
+
+#[derive(Debug, PartialEq)]
+pub enum RateFileError {
+    /// Neither the file nor the command line says how the rates are quoted.
+    UndeclaredPair(String),
+    /// The file and the command line declare different pairs.
+    ConflictingPairs {
+        file: CurrencyPair,
+        option: CurrencyPair,
+    },
+    /// The rates are for other currencies than the ones being converted.
+    WrongPair {
+        pair: CurrencyPair,
+        expected: CurrencyPair,
+    },
+    /// A rate that has to be inverted is zero.
+    ZeroRate(String),
+    /// The file has more than one rate column, e.g. `rate` and `EUR/USD`.
+    SeveralRateColumns(String),
+}
+
+impl Display for RateFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UndeclaredPair(path) => write!(
+                f,
+                "{} does not say how its rates are quoted: name the rate column after \
+                 its currency pair (e.g. EUR/USD) or pass --rates-pair",
+                path
+            ),
+            Self::ConflictingPairs { file, option } => write!(
+                f,
+                "The rates file is quoted as {}, but --rates-pair is {}",
+                file, option
+            ),
+            Self::WrongPair { pair, expected } => write!(
+                f,
+                "The rates are quoted as {}, which cannot convert {} to {}",
+                pair,
+                expected.base(),
+                expected.quote()
+            ),
+            Self::ZeroRate(path) => write!(f, "{} contains a zero rate", path),
+            Self::SeveralRateColumns(path) => {
+                write!(f, "{} has more than one rate column", path)
+            }
+        }
+    }
+}
+
+impl Error for RateFileError {}
+
+/// Whether the rates of a file have to be inverted to convert `D` to `N`.
+#[derive(Debug, PartialEq)]
+enum Direction {
+    AsQuoted,
+    Inverted,
+}
+
+/// The pair the rates of a file are quoted in, declared by the file itself or on the
+/// command line.
+fn declared_pair(
+    file_path: &str,
+    declared: Option<CurrencyPair>,
+    option: Option<CurrencyPair>,
+) -> Result<CurrencyPair, RateFileError> {
+    match (declared, option) {
+        (Some(file), Some(option)) if file != option => {
+            Err(RateFileError::ConflictingPairs { file, option })
+        }
+        (Some(pair), _) | (None, Some(pair)) => Ok(pair),
+        (None, None) => Err(RateFileError::UndeclaredPair(file_path.to_string())),
+    }
+}
+
+/// See [`declared_pair`]. Rates quoted as `BASE/QUOTE` convert BASE to QUOTE as they are and the
+/// other way round after inverting them; any other pair is rejected.
+fn direction<N, D>(
+    file_path: &str,
+    declared: Option<CurrencyPair>,
+    option: Option<CurrencyPair>,
+) -> Result<Direction, RateFileError>
+where
+    N: CurrencyType,
+    D: CurrencyType,
+{
+    let pair = declared_pair(file_path, declared, option)?;
+
+    let expected = CurrencyPair::new(D::currency(), N::currency());
+    if pair == expected {
+        Ok(Direction::AsQuoted)
+    } else if pair == expected.invert() {
+        Ok(Direction::Inverted)
+    } else {
+        Err(RateFileError::WrongPair { pair, expected })
+    }
+}
+
+/// Opens a rates file and reads the pair that it declares by naming its rate column after
+/// it, e.g. `date,EUR/USD` or `period,EUR/USD`. That column is renamed to `rate` so the
+/// rates can be read as usual. Files with a plain `rate` column, or with bid and ask
+/// columns, declare nothing.
+fn open_rates_file(
+    file_path: &str,
+) -> Result<(Reader<File>, Option<CurrencyPair>), Box<dyn Error>> {
+    let mut reader = Reader::from_path(file_path)?;
+    let (headers, declared) = rename_pair_column(file_path, reader.headers()?)?;
+    reader.set_headers(headers);
+
+    Ok((reader, declared))
+}
+
+/// Renames the column named after a currency pair to `rate`. Other columns, such as a
+/// `source` column, are left as they are and ignored when reading the rates.
+fn rename_pair_column(
+    file_path: &str,
+    headers: &StringRecord,
+) -> Result<(StringRecord, Option<CurrencyPair>), RateFileError> {
+    let mut declared = None;
+    let mut rate_columns = 0;
+
+    let headers = headers
+        .iter()
+        .map(|header| match header.trim() {
+            "rate" => {
+                rate_columns += 1;
+                header.to_string()
+            }
+            name => match name.parse::<CurrencyPair>() {
+                Ok(pair) => {
+                    rate_columns += 1;
+                    declared = Some(pair);
+                    "rate".to_string()
+                }
+                Err(_) => header.to_string(),
+            },
+        })
+        .collect();
+    if rate_columns > 1 {
+        return Err(RateFileError::SeveralRateColumns(file_path.to_string()));
+    }
+
+    Ok((headers, declared))
+}
+
+/// The origin of the rates in a local file, which were retrieved when it was last modified.
+fn file_origin(file_path: &str, provider: Option<&str>) -> RateOrigin {
+    RateOrigin {
+        file: Some(file_path.to_string()),
+        provider: provider.map(str::to_string),
+        retrieved: std::fs::metadata(file_path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .map(|modified| DateTime::<Utc>::from(modified).trunc_subsecs(0)),
+    }
+}
+
+/// Reads the rates for converting `D` to `N`, inverting them if the file is quoted the
+/// other way round. `pair` declares the quote direction of files that do not declare it
+/// themselves, and the rates are checked with `checks`.
+pub fn read_exchange_rates<N, D>(
+    file_path: &str,
+    pair: Option<CurrencyPair>,
+    checks: &RateChecks,
+) -> Result<DailyExchangeRates<N, D>, Box<dyn Error>>
+where
+    N: CurrencyType + for<'de> Deserialize<'de>,
+    D: CurrencyType + for<'de> Deserialize<'de>,
+{
+    let (reader, declared) = open_rates_file(file_path)?;
+
+    let rates = match direction::<N, D>(file_path, declared, pair)? {
+        Direction::AsQuoted => DailyExchangeRates::read_checked(reader, checks)?,
+        Direction::Inverted => DailyExchangeRates::<D, N>::read_checked(reader, checks)?
+            .checked_invert()
+            .ok_or_else(|| RateFileError::ZeroRate(file_path.to_string()))?,
+    };
+    Ok(rates.with_origin(file_origin(file_path, None)))
+}
+
+/// Reads the rows of a file with one rate per day without parsing the rates, to check them.
+pub fn read_rate_rows(file_path: &str) -> Result<Vec<RateRow>, Box<dyn Error>> {
+    if rate_file_format(file_path)? != RateFileFormat::Daily {
+        return Err(format!("{} does not have date and rate columns", file_path).into());
+    }
+    let (reader, _) = open_rates_file(file_path)?;
+    Ok(read_rows(reader)?)
+}
+
+/// Reads bid and ask rates like [`read_exchange_rates`].
+pub fn read_bid_ask_rates<N, D>(
+    file_path: &str,
+    pair: Option<CurrencyPair>,
+    checks: &RateChecks,
+) -> Result<DailyBidAskRates<N, D>, Box<dyn Error>>
+where
+    N: CurrencyType + for<'de> Deserialize<'de>,
+    D: CurrencyType + for<'de> Deserialize<'de>,
+{
+    let (reader, declared) = open_rates_file(file_path)?;
+
+    let rates = match direction::<N, D>(file_path, declared, pair)? {
+        Direction::AsQuoted => DailyBidAskRates::read_checked(reader, checks)?,
+        Direction::Inverted => DailyBidAskRates::<D, N>::read_checked(reader, checks)?
+            .checked_invert()
+            .ok_or_else(|| RateFileError::ZeroRate(file_path.to_string()))?,
+    };
+    Ok(rates.with_origin(file_origin(file_path, None)))
+}
 
-pub fn read_exchange_rates<N, D>(file_path: &str) -> Result<DailyExchangeRates<N, D>, csv::Error>
+/// Reads monthly, quarterly or yearly rates like [`read_exchange_rates`].
+pub fn read_period_rates<N, D>(
+    file_path: &str,
+    pair: Option<CurrencyPair>,
+) -> Result<PeriodExchangeRates<N, D>, Box<dyn Error>>
 where
     N: CurrencyType + for<'de> Deserialize<'de>,
     D: CurrencyType + for<'de> Deserialize<'de>,
 {
-    let reader = Reader::from_path(file_path)?;
-    DailyExchangeRates::read_from_csv(reader)
+    let (reader, declared) = open_rates_file(file_path)?;
+
+    let rates = match direction::<N, D>(file_path, declared, pair)? {
+        Direction::AsQuoted => PeriodExchangeRates::read_from_csv(reader)?,
+        Direction::Inverted => PeriodExchangeRates::<D, N>::read_from_csv(reader)?
+            .checked_invert()
+            .ok_or_else(|| RateFileError::ZeroRate(file_path.to_string()))?,
+    };
+    Ok(rates.with_origin(file_origin(file_path, None)))
+}
+
+/// Reads a table with rates of many currencies against `base`, like the ECB's
+/// `eurofxref-hist.csv`, checking the rates of every currency with `checks`. Zip archives
+/// and SDMX-ML files as published by the ECB are read as well, with the default checks for
+/// the CSV file in an archive.
+pub fn read_wide_rates(
+    file_path: &str,
+    base: Currency,
+    checks: &RateChecks,
+) -> Result<WideExchangeRates, Box<dyn Error>> {
+    let rates = match extension(file_path).as_deref() {
+        Some("zip") => ecb::read_ecb_zip(File::open(file_path)?, base)?
+            .with_origin(file_origin(file_path, Some(ecb::PROVIDER))),
+        Some("xml") => ecb::read_sdmx(File::open(file_path)?, base)?
+            .with_origin(file_origin(file_path, Some(ecb::PROVIDER))),
+        _ => {
+            let reader = Reader::from_path(file_path)?;
+            WideExchangeRates::read_checked(reader, base, checks)?
+                .with_origin(file_origin(file_path, None))
+        }
+    };
+    Ok(rates)
+}
+
+fn extension(file_path: &str) -> Option<String> {
+    Path::new(file_path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+}
+
+/// Reads the exchange rate series of a Bundesbank time series export.
+pub fn read_bundesbank_rates(file_path: &str) -> Result<Vec<RateSeries>, Box<dyn Error>> {
+    let origin = file_origin(file_path, Some(bundesbank::PROVIDER));
+    Ok(bundesbank::read_bundesbank(File::open(file_path)?)?
+        .into_iter()
+        .map(|series| series.with_origin(origin.clone()))
+        .collect())
+}
+
+/// Reads the exchange rate series of a Federal Reserve H.10 download.
+pub fn read_fed_h10_rates(file_path: &str) -> Result<Vec<RateSeries>, Box<dyn Error>> {
+    let origin = file_origin(file_path, Some(fed_h10::PROVIDER));
+    Ok(fed_h10::read_fed_h10(File::open(file_path)?)?
+        .into_iter()
+        .map(|series| series.with_origin(origin.clone()))
+        .collect())
+}
+
+/// Reads a rate store, which is empty if the file does not exist yet.
+pub fn read_rate_store(file_path: &str) -> Result<RateStore, Box<dyn Error>> {
+    if !Path::new(file_path).exists() {
+        return Ok(RateStore::new());
+    }
+    Ok(RateStore::read_from_csv(Reader::from_path(file_path)?)?)
+}
+
+/// Writes the store to a temporary file next to `file_path` and renames it, so that a
+/// failed write leaves the previous store intact.
+pub fn write_rate_store(file_path: &str, store: &RateStore) -> Result<(), Box<dyn Error>> {
+    let temporary = format!("{}.tmp", file_path);
+    store.write_to_csv(Writer::from_path(&temporary)?)?;
+    std::fs::rename(&temporary, file_path)?;
+    Ok(())
+}
+
+/// Reads the daily rates of a file in any format with one rate per day, to add them to a
+/// rate store as coming from `source` and retrieved when the file was last modified.
+/// `pair` declares the quote direction of files with a single pair that do not declare it
+/// themselves, and `base` the currency that files with one column per currency are quoted
+/// against. Fails with the errors that `checks` finds and logs the suspicious rates as
+/// warnings. Rate stores were checked when their rates were added, so they are only
+/// checked for rates that are not positive and for conflicts.
+pub fn read_stored_rates(
+    file_path: &str,
+    pair: Option<CurrencyPair>,
+    base: Currency,
+    source: &str,
+    checks: &RateChecks,
+) -> Result<Vec<StoredRate>, Box<dyn Error>> {
+    let retrieved = file_origin(file_path, None).retrieved;
+    let stored = |pair: CurrencyPair, date: NaiveDate, rate: Decimal| StoredRate {
+        date,
+        base: pair.base(),
+        quote: pair.quote(),
+        rate,
+        source: source.to_string(),
+        retrieved,
+    };
+    let from_series = |series: Vec<RateSeries>| -> Result<Vec<_>, ValidationError> {
+        for series in &series {
+            reject_errors(checks.check_rates(&series.rows()))?;
+        }
+        Ok(series
+            .iter()
+            .flat_map(|series| {
+                series
+                    .rates()
+                    .map(|(date, rate)| stored(series.pair(), *date, *rate))
+            })
+            .collect())
+    };
+
+    Ok(match rate_file_format(file_path)? {
+        RateFileFormat::Daily => {
+            let (reader, declared) = open_rates_file(file_path)?;
+            let pair = declared_pair(file_path, declared, pair)?;
+            let rows = read_rows(reader)?;
+            reject_errors(checks.check(&rows))?;
+            rows.into_iter()
+                .map(|row| {
+                    let rate = row.rate.parse().map_err(|_| {
+                        ValidationError::Invalid(vec![RateIssue::InvalidRate {
+                            line: row.line,
+                            rate: row.rate.clone(),
+                        }])
+                    })?;
+                    Ok(stored(pair, row.date, rate))
+                })
+                .collect::<Result<_, ValidationError>>()?
+        }
+        RateFileFormat::Wide => {
+            let rates = read_wide_rates(file_path, base, checks)?;
+            rates
+                .rates()
+                .map(|(currency, date, rate)| {
+                    stored(CurrencyPair::new(rates.base(), currency), date, rate)
+                })
+                .collect()
+        }
+        RateFileFormat::Bundesbank => from_series(read_bundesbank_rates(file_path)?)?,
+        RateFileFormat::FedH10 => from_series(read_fed_h10_rates(file_path)?)?,
+        RateFileFormat::Store => read_rate_store(file_path)?.rates().cloned().collect(),
+        RateFileFormat::BidAsk | RateFileFormat::Period => {
+            return Err(format!(
+                "{} does not have one rate per day, so it cannot be added to a rate store",
+                file_path
+            )
+            .into())
+        }
+    })
+}
+
+/// The calendar named `calendar`, `TARGET2`, `NYSE` or `Fed`, or else the one whose holidays
+/// are listed in the CSV file `calendar`, with `date` and `name` columns. Calendars joined
+/// with `+`, such as `TARGET2+closures.csv`, have the holidays of all of them.
+pub fn read_calendar(calendar: &str) -> Result<BusinessCalendar, Box<dyn Error>> {
+    let mut calendars = calendar.split('+').map(read_single_calendar);
+    let first = calendars.next().expect("split returns at least one part")?;
+    calendars.try_fold(first, |joined, calendar| Ok(joined.extend(calendar?)))
+}
+
+fn read_single_calendar(calendar: &str) -> Result<BusinessCalendar, Box<dyn Error>> {
+    if let Ok(builtin) = calendar.parse::<BuiltinCalendar>() {
+        return Ok(BusinessCalendar::builtin(builtin));
+    }
+    if !Path::new(calendar).exists() {
+        return Err(format!(
+            "{} is neither a file nor one of the calendars TARGET2, NYSE and Fed",
+            calendar
+        )
+        .into());
+    }
+    let name = Path::new(calendar)
+        .file_stem()
+        .map_or(calendar.to_string(), |name| {
+            name.to_string_lossy().into_owned()
+        });
+    Ok(BusinessCalendar::read_from_csv(
+        name,
+        Reader::from_path(calendar)?,
+    )?)
+}
+
+/// The pair that a rates file declares in its header, or else `pair`.
+pub fn rates_pair(
+    file_path: &str,
+    pair: Option<CurrencyPair>,
+) -> Result<CurrencyPair, Box<dyn Error>> {
+    let (_, declared) = open_rates_file(file_path)?;
+    Ok(declared
+        .or(pair)
+        .ok_or_else(|| RateFileError::UndeclaredPair(file_path.to_string()))?)
+}
+
+/// The layouts of rates files that we can read.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RateFileFormat {
+    /// One rate per day, in `date` and `rate` columns.
+    Daily,
+    /// Bid and ask rates per day, in `date`, `bid`, `ask` and optionally `mid` columns.
+    BidAsk,
+    /// One rate per month, quarter or year, in `period` and `rate` columns.
+    Period,
+    /// Daily rates of many currencies against a base, in a date column followed by one
+    /// column per currency, or in an ECB zip archive or SDMX-ML file.
+    Wide,
+    /// A time series export of the Bundesbank.
+    Bundesbank,
+    /// A download of the Federal Reserve's H.10 release.
+    FedH10,
+    /// A rate store, with `date`, `base`, `quote`, `rate` and `source` columns.
+    Store,
+}
+
+/// Tells the format of a rates file from its extension or header.
+pub fn rate_file_format(file_path: &str) -> Result<RateFileFormat, csv::Error> {
+    if matches!(extension(file_path).as_deref(), Some("zip" | "xml")) {
+        return Ok(RateFileFormat::Wide);
+    }
+
+    let headers = Reader::from_path(file_path)?.headers()?.clone();
+    Ok(header_format(&headers))
+}
+
+/// Tells the format of a CSV rates file from its header. A file is wide if its first
+/// column is the date and the next one is named like a currency code, even one we do not
+/// know, such as CYP.
+fn header_format(headers: &StringRecord) -> RateFileFormat {
+    let has = |column: &str| headers.iter().any(|header| header.trim() == column);
+    let first = headers.get(0).unwrap_or_default().trim();
+    let is_code = |header: &str| {
+        let header = header.trim();
+        header.len() == 3 && header.chars().all(|c| c.is_ascii_uppercase())
+    };
+
+    if has("base") && has("quote") && has("source") {
+        RateFileFormat::Store
+    } else if first == "Series Description" {
+        RateFileFormat::FedH10
+    } else if first.starts_with(";BB")
+        || (first.is_empty() && headers.get(1).is_some_and(|id| id.starts_with("BB")))
+    {
+        RateFileFormat::Bundesbank
+    } else if has("period") {
+        RateFileFormat::Period
+    } else if first.eq_ignore_ascii_case("date") && headers.get(1).is_some_and(is_code) {
+        RateFileFormat::Wide
+    } else if has("bid") && has("ask") {
+        RateFileFormat::BidAsk
+    } else {
+        RateFileFormat::Daily
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversions::currency::{EUR, JPY, USD};
+    use crate::currency::Currency;
+
+    fn pair(pair: &str) -> Option<CurrencyPair> {
+        Some(pair.parse().unwrap())
+    }
+
+    #[test]
+    fn test_direction() {
+        assert_eq!(
+            direction::<USD, EUR>("rates.csv", pair("EUR/USD"), None),
+            Ok(Direction::AsQuoted)
+        );
+        assert_eq!(
+            direction::<USD, EUR>("rates.csv", None, pair("USD/EUR")),
+            Ok(Direction::Inverted)
+        );
+        assert_eq!(
+            direction::<USD, EUR>("rates.csv", pair("EUR/USD"), pair("EUR/USD")),
+            Ok(Direction::AsQuoted)
+        );
+    }
+
+    #[test]
+    fn test_rename_pair_column() {
+        let headers = |headers: &[&str]| StringRecord::from(headers.to_vec());
+
+        assert_eq!(
+            rename_pair_column("rates.csv", &headers(&["date", "EUR/USD", "source"])),
+            Ok((headers(&["date", "rate", "source"]), pair("EUR/USD")))
+        );
+        assert_eq!(
+            rename_pair_column("rates.csv", &headers(&["date", "rate", "note"])),
+            Ok((headers(&["date", "rate", "note"]), None))
+        );
+        for columns in [["date", "EUR/USD", "EUR/GBP"], ["date", "rate", "EUR/USD"]] {
+            assert_eq!(
+                rename_pair_column("rates.csv", &headers(&columns)),
+                Err(RateFileError::SeveralRateColumns("rates.csv".to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_header_format() {
+        let format = |headers: &[&str]| header_format(&StringRecord::from(headers.to_vec()));
+
+        assert_eq!(format(&["Date", "USD", "JPY"]), RateFileFormat::Wide);
+        assert_eq!(format(&["Date", "CYP", "USD"]), RateFileFormat::Wide);
+        assert_eq!(format(&["date", "rate"]), RateFileFormat::Daily);
+        assert_eq!(format(&["date", "EUR/USD"]), RateFileFormat::Daily);
+        assert_eq!(format(&["date", "bid", "ask"]), RateFileFormat::BidAsk);
+        assert_eq!(format(&["period", "EUR/USD"]), RateFileFormat::Period);
+    }
+
+    #[test]
+    fn test_direction_errors() {
+        assert_eq!(
+            direction::<USD, EUR>("rates.csv", None, None),
+            Err(RateFileError::UndeclaredPair("rates.csv".to_string()))
+        );
+        assert_eq!(
+            direction::<USD, EUR>("rates.csv", pair("EUR/USD"), pair("USD/EUR")),
+            Err(RateFileError::ConflictingPairs {
+                file: pair("EUR/USD").unwrap(),
+                option: pair("USD/EUR").unwrap(),
+            })
+        );
+        assert_eq!(
+            direction::<JPY, EUR>("rates.csv", pair("EUR/USD"), None),
+            Err(RateFileError::WrongPair {
+                pair: pair("EUR/USD").unwrap(),
+                expected: CurrencyPair::new(Currency::EUR, Currency::JPY),
+            })
+        );
+    }
 }
@@ -2,11 +2,11 @@ use std::error::Error;
 
 use clap::{Parser, Subcommand};
 
-mod commands;
-mod conversions;
-mod currency;
-mod io;
-mod trades;
+pub mod commands;
+pub mod conversions;
+pub mod currency;
+pub mod io;
+pub mod trades;
 
 /// Simple CLI tool to help with currency conversions.
 #[derive(Debug, Parser)]
@@ -19,6 +19,9 @@ struct Cli {
 #[derive(Debug, Subcommand)]
 enum Commands {
     ConvertTransactions(commands::convert_transactions::ConvertTransactions),
+    AverageRates(commands::average_rates::AverageRates),
+    Rates(commands::rates::Rates),
+    CheckRates(commands::check_rates::CheckRates),
 }
 
 pub fn run_cli() -> Result<(), Box<dyn Error>> {
@@ -26,7 +29,10 @@ pub fn run_cli() -> Result<(), Box<dyn Error>> {
 
     match &cli.command {
         Commands::ConvertTransactions(args) => {
-            commands::convert_transactions::convert_transactions(&args)
+            commands::convert_transactions::convert_transactions(args)
         }
+        Commands::AverageRates(args) => commands::average_rates::average_rates(args),
+        Commands::Rates(args) => commands::rates::rates(args),
+        Commands::CheckRates(args) => commands::check_rates::check_rates(args),
     }
 }
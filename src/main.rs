@@ -1,3 +1,10 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Warnings about the input, such as suspicious rates, are meant for the user, so they
+    // are shown unless RUST_LOG says otherwise.
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn"))
+        .format_timestamp(None)
+        .format_target(false)
+        .init();
+
     t_utils::run_cli()
 }
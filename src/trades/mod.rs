@@ -1 +1 @@
-mod sale;
+pub mod sale;
@@ -1,13 +1,24 @@
 use std::{
     error::Error,
     fmt::{self, Display, Formatter},
-    slice::Iter,
 };
 
 use chrono::NaiveDate;
 
+use serde::Deserialize;
+
 use crate::{
-    currency::Currency,
+    conversions::{
+        allocation::AllocationError,
+        currency::CurrencyType,
+        daily_exchange_rates::ConversionError,
+        decimal::Decimal,
+        multi_currency_rates::MultiCurrencyRates,
+        rounding::RoundingMode,
+        timezone::{Timestamp, Zone},
+        transaction::Transaction,
+    },
+    currency::{Currency, Money},
     io::read_ibkr_trades::{IbkrInput, IbkrInputLine},
 };
 
@@ -20,7 +31,7 @@ pub struct IbkrSales {
 pub struct IbkrSale {
     currency: Currency,
     symbol: String,
-    date: NaiveDate,
+    time: Timestamp,
     quantity: f64,
     t_price: f64,
     proceeds: f64,
@@ -42,6 +53,7 @@ pub enum IbkrSaleError {
     LotClosedAfterTrade,
     LotSumMismatch,
     TradeMissingProceeds,
+    UnknownCurrency(String),
 }
 
 impl Display for IbkrSaleError {
@@ -52,6 +64,7 @@ impl Display for IbkrSaleError {
             Self::LotClosedAfterTrade => write!(f, "Lot closed after trade"),
             Self::TradeMissingProceeds => write!(f, "Trade missing proceeds"),
             Self::LotSumMismatch => write!(f, "Sum of closed lots does not match trade basis"),
+            Self::UnknownCurrency(currency) => write!(f, "Unknown currency: {}", currency),
         }
     }
 }
@@ -62,14 +75,14 @@ impl ClosedLot {
         line: &IbkrInputLine,
         trade: &IbkrInputLine,
     ) -> Result<Self, IbkrSaleError> {
-        let date = line.date;
+        let date = line.date_time.date;
         let quantity = line.quantity;
         let t_price = line.t_price;
         let basis = line.basis;
 
         if trade.symbol != line.symbol || trade.currency != line.currency {
             return Err(IbkrSaleError::UnmatchedClosedLot);
-        } else if line.date > trade.date {
+        } else if line.date_time.date > trade.date_time.date {
             return Err(IbkrSaleError::LotClosedAfterTrade);
         }
 
@@ -82,12 +95,78 @@ impl ClosedLot {
     }
 }
 
+impl IbkrSales {
+    pub fn sales(&self) -> &[IbkrSale] {
+        &self.sales
+    }
+}
+
 impl IbkrSale {
-    fn from_sales_slice(sale_info: &[IbkrInputLine]) -> Result<Self, IbkrSaleError> {
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// When the trade was made, in the time zone of the statement.
+    pub fn time(&self) -> &Timestamp {
+        &self.time
+    }
+
+    /// The date of the trade in the `reporting` time zone.
+    pub fn date(&self, reporting: Zone) -> NaiveDate {
+        self.time.date_in(reporting)
+    }
+
+    /// The year that the trade belongs to in the `reporting` time zone.
+    pub fn tax_year(&self, reporting: Zone) -> i32 {
+        self.time.year_in(reporting)
+    }
+
+    pub fn proceeds(&self) -> Result<Money, ConversionError> {
+        if !self.proceeds.is_finite() {
+            return Err(ConversionError::InvalidAmount(self.proceeds.to_string()));
+        }
+        let proceeds =
+            Decimal::from_f64(self.proceeds).map_err(|_| ConversionError::AmountOverflow)?;
+        Money::from_decimal(proceeds, self.currency, RoundingMode::default())
+            .map_err(|_| ConversionError::AmountOverflow)
+    }
+
+    /// Converts the proceeds into `N` with the rate of the trade date in the `reporting`
+    /// time zone.
+    pub fn convert_proceeds<N>(
+        &self,
+        rates: &MultiCurrencyRates<N>,
+        mode: RoundingMode,
+        reporting: Zone,
+    ) -> Result<Transaction<N>, ConversionError>
+    where
+        N: CurrencyType + for<'de> Deserialize<'de> + 'static,
+    {
+        let converted = rates.convert(self.date(reporting), self.proceeds()?, mode)?;
+        Ok(Transaction::at(self.time, reporting, converted.amount()))
+    }
+
+    /// Spreads `amount`, e.g. a commission, over the closed lots in proportion to their
+    /// quantities. The parts are in the order of the lots and add up to `amount` exactly.
+    pub fn allocate_to_lots(&self, amount: Money) -> Result<Vec<Money>, AllocationError> {
+        let quantities = self
+            .closed_lots
+            .iter()
+            .map(|lot| Decimal::from_f64(lot.quantity.abs()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| AllocationError::Overflow)?;
+        amount.allocate(&quantities)
+    }
+
+    fn from_sales_slice(sale_info: &[IbkrInputLine], zone: Zone) -> Result<Self, IbkrSaleError> {
         let trade = &sale_info[0];
         let closed_lots = sale_info[1..]
             .iter()
-            .map(|line| ClosedLot::from_closed_lot_line(line, &trade))
+            .map(|line| ClosedLot::from_closed_lot_line(line, trade))
             .collect::<Result<Vec<_>, _>>()?;
 
         if closed_lots.iter().map(|lot| lot.basis).sum::<f64>() != trade.basis
@@ -97,9 +176,12 @@ impl IbkrSale {
         }
 
         Ok(Self {
-            currency: trade.currency.parse().expect("Infallible"),
+            currency: trade
+                .currency
+                .parse()
+                .map_err(|_| IbkrSaleError::UnknownCurrency(trade.currency.clone()))?,
             symbol: trade.symbol.clone(),
-            date: trade.date,
+            time: trade.date_time.timestamp(zone),
             quantity: trade.quantity,
             t_price: trade.t_price,
             proceeds: trade.proceeds.ok_or(IbkrSaleError::TradeMissingProceeds)?,
@@ -118,14 +200,14 @@ impl TryFrom<IbkrInput> for IbkrSales {
             .filter(|line| {
                 line.data_discriminator == "Trade" || line.data_discriminator == "ClosedLot"
             })
-            .filter(|line| line.proceeds.map_or(true, |proceeds| proceeds >= 0.0))
+            .filter(|line| line.proceeds.is_none_or(|proceeds| proceeds >= 0.0))
             .collect();
 
         let sales_slices = sales_slices(&trades)?;
 
         let sales = sales_slices
             .iter()
-            .map(|slice| IbkrSale::from_sales_slice(slice))
+            .map(|slice| IbkrSale::from_sales_slice(slice, input.zone))
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok(Self { sales })
@@ -145,7 +227,7 @@ fn sales_slices(sales_trades: &[IbkrInputLine]) -> Result<Vec<&[IbkrInputLine]>,
         })
         .collect();
 
-    if trades_indices.len() == 0 {
+    if trades_indices.is_empty() {
         return Err(IbkrSaleError::NoSales);
     } else if trades_indices[0] != 0 {
         return Err(IbkrSaleError::UnmatchedClosedLot);
@@ -160,7 +242,10 @@ fn sales_slices(sales_trades: &[IbkrInputLine]) -> Result<Vec<&[IbkrInputLine]>,
 
 #[cfg(test)]
 mod tests {
+    use chrono::NaiveTime;
+
     use super::*;
+    use crate::io::read_ibkr_trades::IbkrDateTime;
 
     #[test]
     fn test_ibkr_sales() {
@@ -170,7 +255,10 @@ mod tests {
                     data_discriminator: "Trade".into(),
                     currency: "USD".into(),
                     symbol: "TST".into(),
-                    date: NaiveDate::from_ymd_opt(2023, 1, 4).unwrap(),
+                    date_time: IbkrDateTime {
+                        date: NaiveDate::from_ymd_opt(2023, 1, 4).unwrap(),
+                        time: NaiveTime::from_hms_opt(12, 0, 0),
+                    },
                     quantity: 2.0,
                     t_price: 11.0,
                     proceeds: Some(22.0),
@@ -180,13 +268,17 @@ mod tests {
                     data_discriminator: "ClosedLot".into(),
                     currency: "USD".into(),
                     symbol: "TST".into(),
-                    date: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+                    date_time: IbkrDateTime {
+                        date: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+                        time: None,
+                    },
                     quantity: 2.0,
                     t_price: 10.0,
                     proceeds: None,
                     basis: 20.0,
                 },
             ],
+            zone: Zone::utc(),
         };
 
         let sales = IbkrSales::try_from(input).unwrap();
@@ -197,7 +289,7 @@ mod tests {
             IbkrSale {
                 currency: Currency::USD,
                 symbol: "TST".into(),
-                date: NaiveDate::from_ymd_opt(2023, 1, 4).unwrap(),
+                time: "2023-01-04 12:00:00 UTC".parse().unwrap(),
                 quantity: 2.0,
                 t_price: 11.0,
                 proceeds: 22.0,
@@ -211,6 +303,91 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_convert_proceeds() {
+        use crate::conversions::{
+            currency::{self, EUR, USD},
+            daily_exchange_rates::DailyExchangeRates,
+            fallback::{Fallback, FallbackPolicy},
+        };
+
+        let new_york: Zone = "America/New_York".parse().unwrap();
+        let berlin: Zone = "Europe/Berlin".parse().unwrap();
+        let sale = IbkrSale {
+            currency: Currency::USD,
+            symbol: "TST".into(),
+            time: "2023-12-31 19:30:00 America/New_York".parse().unwrap(),
+            quantity: 2.0,
+            t_price: 11.0,
+            proceeds: 22.0,
+            closed_lots: vec![],
+        };
+        let mut rates = MultiCurrencyRates::<EUR>::new();
+        rates.insert::<USD>(
+            DailyExchangeRates::read_from_csv(csv::Reader::from_reader(
+                "date,rate\n2023-12-29,0.6\n2024-01-02,0.5\n".as_bytes(),
+            ))
+            .unwrap()
+            .with_fallback(Fallback::new(FallbackPolicy::Nearest, 7)),
+        );
+
+        assert_eq!(sale.tax_year(new_york), 2023);
+        assert_eq!(sale.tax_year(berlin), 2024);
+        assert_eq!(
+            sale.convert_proceeds(&rates, RoundingMode::HalfUp, new_york),
+            Ok(Transaction::at(
+                sale.time,
+                new_york,
+                currency::Currency::<EUR>::try_from(13.2).unwrap()
+            ))
+        );
+        assert_eq!(
+            sale.convert_proceeds(&rates, RoundingMode::HalfUp, berlin),
+            Ok(Transaction::at(
+                sale.time,
+                berlin,
+                currency::Currency::<EUR>::from(11)
+            ))
+        );
+
+        let invalid = IbkrSale {
+            proceeds: f64::NAN,
+            ..sale
+        };
+        assert_eq!(
+            invalid.proceeds(),
+            Err(ConversionError::InvalidAmount("NaN".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_allocate_to_lots() {
+        let lot = |quantity| ClosedLot {
+            date: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+            quantity,
+            t_price: 10.0,
+            basis: 10.0 * quantity,
+        };
+        let sale = IbkrSale {
+            currency: Currency::USD,
+            symbol: "TST".into(),
+            time: "2023-01-04 12:00:00 UTC".parse().unwrap(),
+            quantity: 3.0,
+            t_price: 11.0,
+            proceeds: 33.0,
+            closed_lots: vec![lot(1.0), lot(1.0), lot(1.0)],
+        };
+
+        assert_eq!(
+            sale.allocate_to_lots(Money::from_minor_units(100, Currency::USD)),
+            Ok(vec![
+                Money::from_minor_units(34, Currency::USD),
+                Money::from_minor_units(33, Currency::USD),
+                Money::from_minor_units(33, Currency::USD),
+            ])
+        );
+    }
+
     #[test]
     fn test_ibkr_sales_multiple_lots() {
         let input = IbkrInput {
@@ -219,7 +396,10 @@ mod tests {
                     data_discriminator: "Trade".into(),
                     currency: "USD".into(),
                     symbol: "TST".into(),
-                    date: NaiveDate::from_ymd_opt(2023, 1, 4).unwrap(),
+                    date_time: IbkrDateTime {
+                        date: NaiveDate::from_ymd_opt(2023, 1, 4).unwrap(),
+                        time: NaiveTime::from_hms_opt(12, 0, 0),
+                    },
                     quantity: 3.0,
                     t_price: 11.0,
                     proceeds: Some(33.0),
@@ -229,7 +409,10 @@ mod tests {
                     data_discriminator: "ClosedLot".into(),
                     currency: "USD".into(),
                     symbol: "TST".into(),
-                    date: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+                    date_time: IbkrDateTime {
+                        date: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+                        time: None,
+                    },
                     quantity: 1.0,
                     t_price: 10.0,
                     proceeds: None,
@@ -239,13 +422,17 @@ mod tests {
                     data_discriminator: "ClosedLot".into(),
                     currency: "USD".into(),
                     symbol: "TST".into(),
-                    date: NaiveDate::from_ymd_opt(2023, 1, 2).unwrap(),
+                    date_time: IbkrDateTime {
+                        date: NaiveDate::from_ymd_opt(2023, 1, 2).unwrap(),
+                        time: None,
+                    },
                     quantity: 2.0,
                     t_price: 10.0,
                     proceeds: None,
                     basis: 20.0,
                 },
             ],
+            zone: Zone::utc(),
         };
 
         let sales = IbkrSales::try_from(input).unwrap();
@@ -256,7 +443,7 @@ mod tests {
             IbkrSale {
                 currency: Currency::USD,
                 symbol: "TST".into(),
-                date: NaiveDate::from_ymd_opt(2023, 1, 4).unwrap(),
+                time: "2023-01-04 12:00:00 UTC".parse().unwrap(),
                 quantity: 3.0,
                 t_price: 11.0,
                 proceeds: 33.0,
@@ -286,7 +473,10 @@ mod tests {
                     data_discriminator: "Trade".into(),
                     currency: "USD".into(),
                     symbol: "TST".into(),
-                    date: NaiveDate::from_ymd_opt(2023, 1, 4).unwrap(),
+                    date_time: IbkrDateTime {
+                        date: NaiveDate::from_ymd_opt(2023, 1, 4).unwrap(),
+                        time: NaiveTime::from_hms_opt(12, 0, 0),
+                    },
                     quantity: 2.0,
                     t_price: 11.0,
                     proceeds: Some(22.0),
@@ -296,7 +486,10 @@ mod tests {
                     data_discriminator: "ClosedLot".into(),
                     currency: "USD".into(),
                     symbol: "TST".into(),
-                    date: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+                    date_time: IbkrDateTime {
+                        date: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+                        time: None,
+                    },
                     quantity: 2.0,
                     t_price: 10.0,
                     proceeds: None,
@@ -306,7 +499,10 @@ mod tests {
                     data_discriminator: "Trade".into(),
                     currency: "USD".into(),
                     symbol: "TST".into(),
-                    date: NaiveDate::from_ymd_opt(2023, 1, 4).unwrap(),
+                    date_time: IbkrDateTime {
+                        date: NaiveDate::from_ymd_opt(2023, 1, 4).unwrap(),
+                        time: NaiveTime::from_hms_opt(12, 0, 0),
+                    },
                     quantity: 3.0,
                     t_price: 11.0,
                     proceeds: Some(33.0),
@@ -316,7 +512,10 @@ mod tests {
                     data_discriminator: "ClosedLot".into(),
                     currency: "USD".into(),
                     symbol: "TST".into(),
-                    date: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+                    date_time: IbkrDateTime {
+                        date: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+                        time: None,
+                    },
                     quantity: 1.0,
                     t_price: 10.0,
                     proceeds: None,
@@ -326,13 +525,17 @@ mod tests {
                     data_discriminator: "ClosedLot".into(),
                     currency: "USD".into(),
                     symbol: "TST".into(),
-                    date: NaiveDate::from_ymd_opt(2023, 1, 2).unwrap(),
+                    date_time: IbkrDateTime {
+                        date: NaiveDate::from_ymd_opt(2023, 1, 2).unwrap(),
+                        time: None,
+                    },
                     quantity: 2.0,
                     t_price: 10.0,
                     proceeds: None,
                     basis: 20.0,
                 },
             ],
+            zone: Zone::utc(),
         };
 
         let sales = IbkrSales::try_from(input).unwrap();
@@ -343,7 +546,7 @@ mod tests {
             IbkrSale {
                 currency: Currency::USD,
                 symbol: "TST".into(),
-                date: NaiveDate::from_ymd_opt(2023, 1, 4).unwrap(),
+                time: "2023-01-04 12:00:00 UTC".parse().unwrap(),
                 quantity: 2.0,
                 t_price: 11.0,
                 proceeds: 22.0,
@@ -360,7 +563,7 @@ mod tests {
             IbkrSale {
                 currency: Currency::USD,
                 symbol: "TST".into(),
-                date: NaiveDate::from_ymd_opt(2023, 1, 4).unwrap(),
+                time: "2023-01-04 12:00:00 UTC".parse().unwrap(),
                 quantity: 3.0,
                 t_price: 11.0,
                 proceeds: 33.0,
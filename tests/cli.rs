@@ -0,0 +1,141 @@
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+/// A directory for the files of a test, removed when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("t-utils-cli-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    /// The path of `name` in the directory, as an argument.
+    fn path(&self, name: &str) -> String {
+        self.0.join(name).to_str().unwrap().to_string()
+    }
+
+    fn write(&self, name: &str, contents: &str) -> String {
+        fs::write(self.path(name), contents).unwrap();
+        self.path(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Runs the binary with `args` and the default log level.
+fn t_utils(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_t-utils"))
+        .args(args)
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_rate_warnings_reach_stderr() {
+    let dir = TempDir::new("warnings");
+    let input = dir.write("input.csv", "date,amount\n2024-05-03,100\n");
+    let rates = dir.write(
+        "rates.csv",
+        "date,EUR/USD\n2024-05-02,1.07\n2024-05-06,1.08\n2024-05-03,1.5\n",
+    );
+
+    let result = t_utils(&[
+        "convert-transactions",
+        "--input",
+        &input,
+        "--output",
+        &dir.path("output.csv"),
+        "--exchange-rates",
+        &rates,
+    ]);
+
+    assert!(result.status.success());
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    assert!(
+        stderr.contains("Line 4: 2024-05-03 is out of order after 2024-05-06"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("differs from the previous rate"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_unconverted_rows_reach_stderr() {
+    let dir = TempDir::new("unconverted");
+    let input = dir.write(
+        "input.csv",
+        "date,amount,note\n2024-05-03,100,a\n2024-05-03,,b\n2024-05-03,n/a,c\n",
+    );
+    let rates = dir.write("rates.csv", "date,EUR/USD\n2024-05-03,1.0773\n");
+
+    let result = t_utils(&[
+        "convert-transactions",
+        "--input",
+        &input,
+        "--output",
+        &dir.path("output.csv"),
+        "--exchange-rates",
+        &rates,
+        "--keep-columns",
+    ]);
+
+    assert!(result.status.success());
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    assert!(
+        stderr.contains(&format!(
+            "2 rows of {} could not be read and were copied without a conversion: lines 3, 4",
+            input
+        )),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_rates_import_checks_rates() {
+    let dir = TempDir::new("import");
+    let store = dir.path("store.csv");
+    let rates = dir.write(
+        "rates.csv",
+        "date,EUR/USD\n2024-05-02,1.0702\n2024-05-03,0\n",
+    );
+
+    let result = t_utils(&["rates", "--store", &store, "import", &rates]);
+
+    assert!(!result.status.success());
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    assert!(stderr.contains("NonPositiveRate { line: 3"), "{}", stderr);
+    assert!(!std::path::Path::new(&store).exists());
+}
+
+#[test]
+fn test_rates_store_conflicts() {
+    let dir = TempDir::new("store");
+    let store = dir.write(
+        "store.csv",
+        "date,base,quote,rate,source\n\
+         2024-05-02,EUR,USD,1.0702,ecb\n\
+         2024-05-02,EUR,USD,1.0802,fed\n",
+    );
+
+    let result = t_utils(&["rates", "--store", &store, "show"]);
+
+    assert!(!result.status.success());
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    assert!(stderr.contains("Conflicts"), "{}", stderr);
+}